@@ -0,0 +1,282 @@
+use anyhow::Result;
+use base64::{engine::general_purpose, Engine as _};
+use image::codecs::jpeg::JpegEncoder;
+use std::fs;
+use tauri::Emitter;
+use tokio::time::{Duration, Instant};
+
+use crate::gemini::document_prompt;
+use crate::types::{
+    ClaudeContentBlock, ClaudeImageSource, ClaudeMessage, ClaudeRequest, ClaudeResponse,
+    DocumentLanguage, FrameOutputFormat, ImageEmbedFrequency, NumberingStyle, ProcessTimeouts,
+    ProgressUpdate,
+};
+use crate::video::{extract_multiple_frames_from_video, get_video_duration};
+
+const DEFAULT_CLAUDE_API_BASE_URL: &str = "https://api.anthropic.com";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const KEYFRAME_INTERVAL_SECS: f64 = 30.0;
+// The Messages API accepts at most 100 images per request
+const MAX_KEYFRAMES: usize = 100;
+const MAX_OUTPUT_TOKENS: u32 = 16_000;
+// Larger images are downscaled by the API anyway, so they are only sent at this size
+const MAX_KEYFRAME_EDGE_PX: u32 = 1568;
+const KEYFRAME_JPEG_QUALITY: u8 = 80;
+// Requests are limited to 32 MB and base64 adds a third to the image size
+const MAX_KEYFRAME_BYTES: usize = 20 * 1024 * 1024;
+
+/// Connection settings for the Claude API
+#[derive(Debug, Clone)]
+pub struct ClaudeClient {
+    pub api_key: String,
+    pub base_url: String,
+    pub timeout_secs: u64,
+}
+
+impl ClaudeClient {
+    pub fn new(api_key: &str, timeout_secs: u64) -> Self {
+        ClaudeClient {
+            api_key: api_key.to_string(),
+            base_url: DEFAULT_CLAUDE_API_BASE_URL.to_string(),
+            timeout_secs,
+        }
+    }
+
+    /// Sends the requests to another server, e.g. a proxy or a mock server in tests
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    fn http_client(&self) -> reqwest::Client {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(self.timeout_secs.max(1)))
+            .build()
+            .unwrap_or_else(|e| {
+                println!(
+                    "⚠️ [CLAUDE] Failed to build HTTP client with timeout: {}",
+                    e
+                );
+                reqwest::Client::new()
+            })
+    }
+}
+
+/// Keyframe timestamps every 30 seconds, spread out further for long videos
+/// so that no more than `MAX_KEYFRAMES` images are sent
+pub fn keyframe_timestamps(duration: f64) -> Vec<f64> {
+    if duration <= 0.0 {
+        return vec![0.0];
+    }
+    let interval = KEYFRAME_INTERVAL_SECS.max(duration / MAX_KEYFRAMES as f64);
+    (0..MAX_KEYFRAMES)
+        .map(|i| i as f64 * interval)
+        .take_while(|timestamp| *timestamp < duration)
+        .collect()
+}
+
+pub async fn generate_with_claude_with_progress(
+    file_path: &str,
+    language: &DocumentLanguage,
+    claude: &ClaudeClient,
+    model: &str,
+    custom_prompt: Option<&str>,
+    embed_images: bool,
+    image_embed_frequency: &ImageEmbedFrequency,
+    numbering_style: &NumberingStyle,
+    max_heading_depth: u8,
+    timeouts: &ProcessTimeouts,
+    app: &tauri::AppHandle,
+    base_step: usize,
+    total_steps: usize,
+    started_at: Instant,
+) -> Result<String> {
+    let emit_progress = |message: String| {
+        let progress = ProgressUpdate::new(message.clone(), base_step, total_steps, started_at);
+        if let Err(e) = app.emit("progress_update", &progress) {
+            println!("❌ [CLAUDE_EVENT] Failed to emit progress: {}", e);
+        }
+    };
+
+    generate_with_claude_internal(
+        file_path,
+        language,
+        claude,
+        model,
+        custom_prompt,
+        embed_images,
+        image_embed_frequency,
+        numbering_style,
+        max_heading_depth,
+        timeouts,
+        emit_progress,
+    )
+    .await
+}
+
+/// Re-encodes a keyframe as JPEG, shrinking it so that its longer edge is at most `MAX_KEYFRAME_EDGE_PX`
+pub fn downscale_keyframe(image: &[u8]) -> Result<Vec<u8>> {
+    let mut image = image::load_from_memory(image)?;
+    if image.width().max(image.height()) > MAX_KEYFRAME_EDGE_PX {
+        image = image.resize(
+            MAX_KEYFRAME_EDGE_PX,
+            MAX_KEYFRAME_EDGE_PX,
+            image::imageops::FilterType::Triangle,
+        );
+    }
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, KEYFRAME_JPEG_QUALITY)
+        .encode_image(&image.to_rgb8())?;
+    Ok(jpeg)
+}
+
+/// Drops every other keyframe until the total image size fits in `max_bytes`
+pub fn fit_keyframes_to_budget(
+    mut keyframes: Vec<(f64, Vec<u8>)>,
+    max_bytes: usize,
+) -> Vec<(f64, Vec<u8>)> {
+    while keyframes.len() > 1
+        && keyframes
+            .iter()
+            .map(|(_, image)| image.len())
+            .sum::<usize>()
+            > max_bytes
+    {
+        keyframes = keyframes.into_iter().step_by(2).collect();
+    }
+    keyframes
+}
+
+/// Generates a document from keyframes of a local video with the Claude Messages API.
+/// Claude has no file upload for video, so the frames are sent as base64 image blocks.
+pub async fn generate_with_claude_internal<F>(
+    file_path: &str,
+    language: &DocumentLanguage,
+    claude: &ClaudeClient,
+    model: &str,
+    custom_prompt: Option<&str>,
+    embed_images: bool,
+    image_embed_frequency: &ImageEmbedFrequency,
+    numbering_style: &NumberingStyle,
+    max_heading_depth: u8,
+    timeouts: &ProcessTimeouts,
+    emit_progress: F,
+) -> Result<String>
+where
+    F: Fn(String),
+{
+    println!("🤖 [CLAUDE] Starting document generation with Claude API");
+    println!(
+        "📋 [CLAUDE] Language: {}, File: {}",
+        language.as_str(),
+        file_path
+    );
+
+    let duration = get_video_duration(file_path, timeouts).await?;
+    let timestamps = keyframe_timestamps(duration);
+
+    emit_progress(format!("キーフレームを抽出中 ({}枚)...", timestamps.len()));
+    let frame_dir = tempfile::tempdir()?;
+    let frame_paths = extract_multiple_frames_from_video(
+        file_path,
+        &timestamps,
+        &frame_dir.path().to_string_lossy(),
+        "keyframe",
+        &FrameOutputFormat::Jpeg { quality: 80 },
+        timeouts,
+    )
+    .await?;
+    println!("🖼️ [CLAUDE] Extracted {} keyframes", frame_paths.len());
+
+    let mut keyframes = Vec::new();
+    for (frame_path, timestamp) in frame_paths.iter().zip(&timestamps) {
+        let image = fs::read(frame_path)
+            .map_err(|e| anyhow::anyhow!("Failed to read keyframe {}: {}", frame_path, e))?;
+        keyframes.push((*timestamp, downscale_keyframe(&image)?));
+    }
+    let extracted_count = keyframes.len();
+    let keyframes = fit_keyframes_to_budget(keyframes, MAX_KEYFRAME_BYTES);
+    if keyframes.len() < extracted_count {
+        println!(
+            "⚠️ [CLAUDE] Keyframes exceed {} MB, sending {} of {}",
+            MAX_KEYFRAME_BYTES / 1024 / 1024,
+            keyframes.len(),
+            extracted_count
+        );
+    }
+
+    // Each frame is labelled with its timestamp so screenshot references still work
+    let mut content = Vec::new();
+    for (timestamp, image) in &keyframes {
+        let seconds = timestamp.round() as u64;
+        content.push(ClaudeContentBlock::Text {
+            text: format!("Frame at {:02}:{:02}", seconds / 60, seconds % 60),
+        });
+        content.push(ClaudeContentBlock::Image {
+            source: ClaudeImageSource {
+                source_type: "base64".to_string(),
+                media_type: "image/jpeg".to_string(),
+                data: general_purpose::STANDARD.encode(image),
+            },
+        });
+    }
+
+    let prompt = document_prompt(
+        language,
+        custom_prompt,
+        embed_images,
+        image_embed_frequency,
+        numbering_style,
+        max_heading_depth,
+    );
+    content.push(ClaudeContentBlock::Text {
+        text: format!(
+            "The images above are keyframes taken from a video every {:.0} seconds.\n\n{}",
+            keyframes
+                .get(1)
+                .map(|(second, _)| second - keyframes[0].0)
+                .unwrap_or(KEYFRAME_INTERVAL_SECS),
+            prompt
+        ),
+    });
+
+    let request = ClaudeRequest {
+        model: model.to_string(),
+        max_tokens: MAX_OUTPUT_TOKENS,
+        messages: vec![ClaudeMessage {
+            role: "user".to_string(),
+            content,
+        }],
+    };
+
+    emit_progress("Claudeでドキュメントを生成中...".to_string());
+    let response = claude
+        .http_client()
+        .post(format!("{}/v1/messages", claude.base_url))
+        .header("x-api-key", claude.api_key.as_str())
+        .header("anthropic-version", ANTHROPIC_VERSION)
+        .json(&request)
+        .send()
+        .await?;
+
+    if response.status().is_success() {
+        let claude_response: ClaudeResponse = response.json().await?;
+        let text = claude_response
+            .content
+            .iter()
+            .filter_map(|block| match block {
+                ClaudeContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("");
+        if text.trim().is_empty() {
+            return Err(anyhow::anyhow!("No text content in Claude response"));
+        }
+        println!("✅ [CLAUDE] Document generated (length: {})", text.len());
+        Ok(text)
+    } else {
+        let error_text = response.text().await?;
+        Err(anyhow::anyhow!("Claude generation failed: {}", error_text))
+    }
+}
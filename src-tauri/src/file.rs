@@ -1,5 +1,47 @@
+use crate::types::{
+    default_line_ending, AppSettings, AudioChapter, AudioFile, CleanupReport, DiskSpaceCheck,
+    DocumentWatermarkPosition, DuplicateFilesError, Flashcard, GenerationSidecar, HookFailure,
+    HookOutput, ImageEmbedFrequency, NotificationSound, OpenFailure, OutputEncoding,
+    ProcessTimeouts, ValidationError, VideoFile,
+};
+use crate::video::{
+    compute_video_fingerprint, find_companion_subtitle, find_executable, hash_first_megabyte,
+};
+use anyhow::anyhow;
 use std::fs;
-use crate::types::VideoFile;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tauri::Emitter;
+use tokio::time::{timeout, Duration};
+
+const SCREENSHOT_SIZE_ESTIMATE: u64 = 500 * 1024; // 500 KB per screenshot
+const DISK_SPACE_OVERHEAD: u64 = 100 * 1024 * 1024; // 100 MB
+const POST_GENERATION_HOOK_TIMEOUT_SECS: u64 = 60;
+const SUSPICIOUSLY_SMALL_FILE_SIZE: u64 = 1024; // 1 KB
+const IMAGE_SEQUENCE_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+// Intermediate files recorded by jobs, stored as absolute paths in the directory they were created for
+const INTERMEDIATE_FILES_MANIFEST: &str = ".document_encoder_intermediates.json";
+const STALE_INTERMEDIATE_FILE_AGE: Duration = Duration::from_secs(60 * 60);
+const DEFAULT_HTML_REPORT_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{{title}}</title>
+<style>
+body { font-family: -apple-system, "Segoe UI", "Hiragino Sans", "Meiryo", sans-serif; line-height: 1.7; max-width: 860px; margin: 2em auto; padding: 0 1em; color: #222; }
+img { max-width: 100%; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 4px 8px; }
+pre { background: #f5f5f5; padding: 1em; overflow-x: auto; }
+footer { margin-top: 3em; color: #888; font-size: 0.85em; }
+</style>
+</head>
+<body>
+{{content}}
+<footer>{{source_video}} {{generated_date}}</footer>
+</body>
+</html>
+"#;
 
 #[tauri::command]
 pub async fn select_video_files(app: tauri::AppHandle) -> Result<Vec<VideoFile>, String> {
@@ -38,10 +80,24 @@ pub async fn select_video_files(app: tauri::AppHandle) -> Result<Vec<VideoFile>,
                         .unwrap_or("Unknown")
                         .to_string();
 
+                    let subtitle_path = find_companion_subtitle(&path_str);
+                    let sha256_first_mb = match hash_first_megabyte(&path_str).await {
+                        Ok(hash) => Some(hash),
+                        Err(e) => {
+                            println!("⚠️ [FILE] Failed to hash {}: {}", file_name, e);
+                            None
+                        }
+                    };
+
                     video_files.push(VideoFile {
                         path: path_str,
                         name: file_name,
                         size: metadata.len(),
+                        subtitle_path,
+                        start_offset_seconds: None,
+                        end_offset_seconds: None,
+                        custom_prompt_override: None,
+                        sha256_first_mb,
                     });
                 }
             }
@@ -51,6 +107,144 @@ pub async fn select_video_files(app: tauri::AppHandle) -> Result<Vec<VideoFile>,
     }
 }
 
+#[tauri::command]
+pub async fn select_audio_files(app: tauri::AppHandle) -> Result<Vec<AudioFile>, String> {
+    use tauri_plugin_dialog::DialogExt;
+    use tokio::sync::oneshot;
+
+    let (tx, rx) = oneshot::channel();
+
+    app.dialog()
+        .file()
+        .add_filter(
+            "Audio files",
+            &["mp3", "aac", "m4a", "wav", "flac", "ogg", "opus", "wma"],
+        )
+        .set_title("Select audio files")
+        .pick_files(move |files| {
+            let _ = tx.send(files);
+        });
+
+    let files = rx
+        .await
+        .map_err(|e| format!("Failed to receive dialog result: {}", e))?;
+
+    match files {
+        Some(paths) => {
+            let mut audio_files = Vec::new();
+            for file_path in paths {
+                let path_str = file_path.to_string();
+                let path_buf = std::path::PathBuf::from(&path_str);
+                if let Ok(metadata) = fs::metadata(&path_buf) {
+                    let file_name = path_buf
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .unwrap_or("Unknown")
+                        .to_string();
+
+                    // Duration is fetched lazily with get_audio_duration
+                    audio_files.push(AudioFile {
+                        path: path_str,
+                        name: file_name,
+                        size: metadata.len(),
+                        duration: None,
+                    });
+                }
+            }
+            Ok(audio_files)
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
+#[tauri::command]
+pub async fn get_audio_duration(path: String) -> Result<f64, String> {
+    crate::video::get_video_duration(&path, &ProcessTimeouts::default())
+        .await
+        .map_err(|e| format!("Failed to get audio duration: {}", e))
+}
+
+/// Splits an audio file at its chapter markers so each chapter can be processed separately.
+/// The chapter files are written to `save_directory` and recorded as intermediate files there,
+/// so `clean_stale_intermediate_files` removes them once they are no longer needed.
+#[tauri::command]
+pub async fn split_audio_file_at_chapters(
+    path: String,
+    save_directory: String,
+    settings: AppSettings,
+) -> Result<Vec<AudioChapter>, String> {
+    let source = Path::new(&path);
+    let output_dir = Path::new(&save_directory);
+    let segments = crate::video::split_audio_at_chapters(
+        source,
+        output_dir,
+        settings.chapter_split_min_duration_seconds,
+        &ProcessTimeouts::from_settings(&settings),
+    )
+    .await
+    .map_err(|e| format!("Failed to split audio at chapters: {}", e))?;
+
+    let chapter_files: Vec<PathBuf> = segments
+        .iter()
+        .map(|(segment_path, _)| segment_path.clone())
+        .filter(|segment_path| segment_path != source)
+        .collect();
+    record_intermediate_files(output_dir, &chapter_files);
+
+    Ok(segments
+        .into_iter()
+        .map(|(segment_path, title)| AudioChapter {
+            path: segment_path.to_string_lossy().to_string(),
+            title,
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub async fn select_image_sequence_dir(app: tauri::AppHandle) -> Result<Option<String>, String> {
+    use tauri_plugin_dialog::DialogExt;
+    use tokio::sync::oneshot;
+
+    let (tx, rx) = oneshot::channel();
+
+    app.dialog()
+        .file()
+        .set_title("スライド画像のディレクトリを選択")
+        .pick_folder(move |folder| {
+            let _ = tx.send(folder);
+        });
+
+    let folder = rx
+        .await
+        .map_err(|e| format!("Failed to receive dialog result: {}", e))?;
+
+    match folder {
+        Some(path) => Ok(Some(path.to_string())),
+        None => Ok(None),
+    }
+}
+
+/// Lists the slide images (.jpg/.jpeg/.png) in a directory sorted by file name
+pub fn list_image_sequence(image_dir: &str) -> Result<Vec<PathBuf>, String> {
+    let entries =
+        fs::read_dir(image_dir).map_err(|e| format!("Failed to read image directory: {}", e))?;
+
+    let mut images: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| {
+                        IMAGE_SEQUENCE_EXTENSIONS.contains(&ext.to_lowercase().as_str())
+                    })
+        })
+        .collect();
+    images.sort_by_key(|path| path.file_name().map(|name| name.to_os_string()));
+    Ok(images)
+}
+
 #[tauri::command]
 pub async fn select_save_directory(app: tauri::AppHandle) -> Result<Option<String>, String> {
     use tauri_plugin_dialog::DialogExt;
@@ -75,17 +269,1034 @@ pub async fn select_save_directory(app: tauri::AppHandle) -> Result<Option<Strin
     }
 }
 
+/// Saves the document as `{filename}.md` together with `{filename}.meta.json`
+/// describing how it was generated. The sidecar never contains API keys.
+#[tauri::command]
+pub async fn save_document_with_metadata_sidecar(
+    content: String,
+    generation_config: GenerationSidecar,
+    save_path: String,
+    filename: String,
+    settings: Option<AppSettings>,
+) -> Result<(), String> {
+    use std::path::Path;
+
+    let basename = filename.strip_suffix(".md").unwrap_or(&filename);
+    let directory = Path::new(&save_path);
+
+    let document_path = directory.join(format!("{}.md", basename));
+    write_document_with_settings(&document_path, &content, settings.as_ref())?;
+
+    let sidecar_path = directory.join(format!("{}.meta.json", basename));
+    let sidecar = serde_json::to_string_pretty(&generation_config)
+        .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
+    fs::write(&sidecar_path, sidecar).map_err(|e| format!("Failed to save metadata: {}", e))?;
+
+    println!(
+        "💾 [SAVE] Saved {} with metadata {}",
+        document_path.display(),
+        sidecar_path.display()
+    );
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn save_document_to_file(
     content: String,
     save_path: String,
     filename: String,
+    settings: Option<AppSettings>,
+    app: tauri::AppHandle,
 ) -> Result<String, String> {
     use std::path::Path;
 
     let full_path = Path::new(&save_path).join(&filename);
+    write_document_with_settings(&full_path, &content, settings.as_ref())?;
+
+    let saved_path = full_path.to_string_lossy().to_string();
+
+    if settings.as_ref().is_some_and(|s| s.auto_open_document) {
+        open_saved_document(&saved_path, &app);
+    }
+
+    // Run the post generation hook in the background (experimental feature)
+    if let Some(settings) = settings {
+        if settings.enable_experimental_features {
+            if let Some(hook) = settings
+                .post_generation_hook
+                .filter(|h| !h.trim().is_empty())
+            {
+                let document_path = saved_path.clone();
+                tokio::spawn(async move {
+                    run_post_generation_hook(&hook, &document_path, &app).await;
+                });
+            }
+        }
+    }
+
+    Ok(saved_path)
+}
+
+/// Writes a document with the encoding, BOM and line ending settings
+/// (plain UTF-8 with LF line endings when no settings are given)
+fn write_document_with_settings(
+    path: &Path,
+    content: &str,
+    settings: Option<&AppSettings>,
+) -> Result<(), String> {
+    let output_encoding = settings
+        .map(|s| s.output_encoding.clone())
+        .unwrap_or(OutputEncoding::Utf8);
+    let add_utf8_bom = settings.is_some_and(|s| s.add_utf8_bom);
+    let line_ending = settings
+        .map(|s| s.line_ending.clone())
+        .unwrap_or_else(default_line_ending);
+    write_document(
+        path,
+        &line_ending.apply(content),
+        &output_encoding,
+        add_utf8_bom,
+    )
+}
+
+/// Opens a saved document in the OS default application.
+/// Failures are reported with an `open_failed` event instead of an error.
+pub fn open_saved_document(path: &str, app: &tauri::AppHandle) {
+    println!("📂 [SAVE] Opening document: {}", path);
+    if let Err(e) = tauri_plugin_opener::open_path(path, None::<&str>) {
+        println!("⚠️ [SAVE] Failed to open document {}: {}", path, e);
+        let failure = OpenFailure {
+            path: path.to_string(),
+            message: "Please open the file manually".to_string(),
+        };
+        if let Err(e) = app.emit("open_failed", &failure) {
+            println!("❌ [SAVE] Failed to emit open_failed event: {}", e);
+        }
+    }
+}
+
+/// Lets the user know that a long-running generation has finished
+pub fn notify_generation_complete(sound: &NotificationSound, app: &tauri::AppHandle) {
+    let method = match sound {
+        NotificationSound::None => return,
+        NotificationSound::System => {
+            use tauri_plugin_notification::NotificationExt;
+
+            if let Err(e) = app
+                .notification()
+                .builder()
+                .title("Document Encoder")
+                .body("Document generation complete")
+                .sound("default")
+                .show()
+            {
+                println!("⚠️ [NOTIFY] Failed to show notification: {}", e);
+            }
+            "system"
+        }
+        NotificationSound::Custom(path) => {
+            if let Err(e) = play_sound_file(path) {
+                println!("⚠️ [NOTIFY] Failed to play {}: {}", path, e);
+            }
+            "custom"
+        }
+    };
+
+    if let Err(e) = app.emit("notification_sent", method) {
+        println!("❌ [NOTIFY] Failed to emit notification_sent event: {}", e);
+    }
+}
+
+/// Plays an audio file with the platform's command line player without waiting for it
+fn play_sound_file(path: &str) -> anyhow::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = std::process::Command::new(find_executable("afplay")?);
+        command.arg(path);
+        command
+    } else if cfg!(target_os = "windows") {
+        let mut command = std::process::Command::new(find_executable("powershell")?);
+        command.arg("-c").arg(format!(
+            "(New-Object Media.SoundPlayer '{}').PlaySync()",
+            path.replace('\'', "''")
+        ));
+        command
+    } else {
+        let mut command = std::process::Command::new(find_executable("paplay")?);
+        command.arg(path);
+        command
+    };
+
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    println!("🔔 [NOTIFY] Playing notification sound: {}", path);
+    Ok(())
+}
+
+/// Replaces characters that are not allowed in file names on Windows or macOS
+pub fn sanitize_filename(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let sanitized = sanitized.trim().trim_end_matches('.').to_string();
+    if sanitized.is_empty() {
+        "document".to_string()
+    } else {
+        sanitized
+    }
+}
+
+/// Saves flashcards as `{basename}_flashcards.json` next to the Markdown document
+pub fn save_flashcards(
+    flashcards: &[Flashcard],
+    save_directory: &str,
+    basename: &str,
+) -> Result<String, String> {
+    let path = PathBuf::from(save_directory).join(format!("{}_flashcards.json", basename));
+    let json = serde_json::to_string_pretty(flashcards)
+        .map_err(|e| format!("Failed to serialize flashcards: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to save flashcards: {}", e))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Creates a `{YYYY-MM-DD_HH-MM-SS}_{suffix}` subdirectory so repeated jobs do not overwrite each other
+pub fn create_job_output_dir(save_directory: &str) -> anyhow::Result<PathBuf> {
+    let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S");
+    let suffix: String = uuid::Uuid::new_v4()
+        .simple()
+        .to_string()
+        .chars()
+        .take(6)
+        .collect();
+    let job_dir = PathBuf::from(save_directory).join(format!("{}_{}", timestamp, suffix));
+    fs::create_dir_all(&job_dir)?;
+    Ok(job_dir)
+}
+
+/// Converts the document to the bytes of the requested output encoding
+pub fn encode_document(content: &str, encoding: &OutputEncoding) -> Result<Vec<u8>, String> {
+    match encoding {
+        OutputEncoding::Utf8 => Ok(content.as_bytes().to_vec()),
+        OutputEncoding::Utf8Bom => {
+            let mut bytes = b"\xEF\xBB\xBF".to_vec();
+            bytes.extend_from_slice(content.as_bytes());
+            Ok(bytes)
+        }
+        OutputEncoding::ShiftJis => {
+            let (bytes, unmappable) = encode_shift_jis(content);
+            if !unmappable.is_empty() {
+                println!(
+                    "⚠️ [SAVE] Replaced {} characters that cannot be represented in Shift-JIS with '?': {}",
+                    unmappable.len(),
+                    unmappable.iter().collect::<String>()
+                );
+            }
+            Ok(bytes)
+        }
+    }
+}
+
+/// Encodes to Shift-JIS, replacing unmappable characters with `?` rather than the HTML
+/// numeric character references `encoding_rs` would insert. Also returns the replaced characters.
+fn encode_shift_jis(content: &str) -> (Vec<u8>, Vec<char>) {
+    let mut encoder = encoding_rs::SHIFT_JIS.new_encoder();
+    let mut bytes = Vec::with_capacity(
+        encoder
+            .max_buffer_length_from_utf8_without_replacement(content.len())
+            .unwrap_or(content.len() * 2),
+    );
+    let mut unmappable = Vec::new();
+    let mut remaining = content;
+
+    loop {
+        let (result, read) =
+            encoder.encode_from_utf8_to_vec_without_replacement(remaining, &mut bytes, true);
+        remaining = &remaining[read..];
+        match result {
+            encoding_rs::EncoderResult::InputEmpty => break,
+            encoding_rs::EncoderResult::OutputFull => bytes.reserve(remaining.len() * 2 + 16),
+            encoding_rs::EncoderResult::Unmappable(c) => {
+                bytes.push(b'?');
+                unmappable.push(c);
+            }
+        }
+    }
+
+    (bytes, unmappable)
+}
+
+/// Saves the document as a standalone HTML file next to the Markdown output.
+/// Uses `html_report_template` when it is set, the built-in stylesheet otherwise.
+#[tauri::command]
+pub async fn save_document_as_html(
+    content: String,
+    save_path: String,
+    filename: String,
+    settings: Option<AppSettings>,
+    source_video: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<String, String> {
+    use std::path::Path;
+
+    let template = match settings
+        .as_ref()
+        .and_then(|s| s.html_report_template.as_deref())
+        .filter(|path| !path.trim().is_empty())
+    {
+        Some(path) => Some(validate_html_report_template(path)?),
+        None => None,
+    };
+
+    let basename = filename.strip_suffix(".md").unwrap_or(&filename);
+    let title = content
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .map(|title| title.trim().to_string())
+        .unwrap_or_else(|| basename.to_string());
+    let generated_date = chrono::Local::now().format("%Y-%m-%d %H:%M").to_string();
+
+    let html = render_html_report(
+        &content,
+        template.as_deref(),
+        &title,
+        &generated_date,
+        source_video.as_deref().unwrap_or_default(),
+    );
+
+    let line_ending = settings
+        .as_ref()
+        .map(|s| s.line_ending.clone())
+        .unwrap_or_else(default_line_ending);
+
+    let full_path = Path::new(&save_path).join(format!("{}.html", basename));
+    fs::write(&full_path, line_ending.apply(&html))
+        .map_err(|e| format!("Failed to save HTML document: {}", e))?;
+    println!("💾 [SAVE] Saved HTML document: {}", full_path.display());
+
+    let saved_path = full_path.to_string_lossy().to_string();
+    if settings.as_ref().is_some_and(|s| s.auto_open_document) {
+        open_saved_document(&saved_path, &app);
+    }
+
+    Ok(saved_path)
+}
+
+/// Converts the Markdown document and fills the `{{content}}`, `{{title}}`,
+/// `{{generated_date}}` and `{{source_video}}` placeholders of the template
+pub fn render_html_report(
+    markdown: &str,
+    template: Option<&str>,
+    title: &str,
+    generated_date: &str,
+    source_video: &str,
+) -> String {
+    use pulldown_cmark::{html, Options, Parser};
 
-    fs::write(&full_path, content).map_err(|e| format!("Failed to save document: {}", e))?;
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    let mut body = String::new();
+    html::push_html(&mut body, Parser::new_ext(markdown, options));
 
-    Ok(full_path.to_string_lossy().to_string())
-}
\ No newline at end of file
+    // The content is substituted last so that placeholders inside the document are left alone
+    template
+        .unwrap_or(DEFAULT_HTML_REPORT_TEMPLATE)
+        .replace("{{title}}", &escape_html(title))
+        .replace("{{generated_date}}", &escape_html(generated_date))
+        .replace("{{source_video}}", &escape_html(source_video))
+        .replace("{{content}}", &body)
+}
+
+/// Reads an HTML report template and checks that it can hold the document
+pub fn validate_html_report_template(path: &str) -> Result<String, String> {
+    let template = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read HTML template {}: {}", path, e))?;
+    if !template.contains("{{content}}") {
+        return Err(format!(
+            "HTML template {} must contain a {{{{content}}}} placeholder",
+            path
+        ));
+    }
+    if !template.to_lowercase().contains("<html") {
+        return Err(format!("HTML template {} is not an HTML document", path));
+    }
+    Ok(template)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Writes the document in the requested encoding.
+/// `add_utf8_bom` turns plain UTF-8 into UTF-8 with BOM so that old versions of Notepad detect it.
+pub fn write_document(
+    path: &std::path::Path,
+    content: &str,
+    encoding: &OutputEncoding,
+    add_utf8_bom: bool,
+) -> Result<(), String> {
+    let encoding = match encoding {
+        OutputEncoding::Utf8 if add_utf8_bom => &OutputEncoding::Utf8Bom,
+        other => other,
+    };
+    let bytes = encode_document(content, encoding)?;
+    fs::write(path, bytes).map_err(|e| format!("Failed to save document: {}", e))
+}
+
+/// Adds the watermark text as a Markdown blockquote at the start or end of the document
+pub fn apply_document_watermark(
+    document: &str,
+    watermark_text: &str,
+    position: &DocumentWatermarkPosition,
+) -> String {
+    let blockquote = watermark_text
+        .trim()
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            if i == 0 {
+                format!("> ⚠️ {}", line)
+            } else {
+                format!("> {}", line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    match position {
+        DocumentWatermarkPosition::Top => format!("{}\n\n{}", blockquote, document),
+        DocumentWatermarkPosition::Bottom => {
+            format!("{}\n\n{}\n", document.trim_end(), blockquote)
+        }
+    }
+}
+
+/// Builds the "> Source:" lines that link back to the source videos
+/// Local files have no URL of their own, so they are linked under `base_url`
+pub fn source_video_links(files: &[VideoFile], base_url: &str) -> String {
+    let base_url = base_url.trim().trim_end_matches('/');
+    files
+        .iter()
+        .map(|file| {
+            format!(
+                "> Source: [{}]({}/{})",
+                file.name,
+                base_url,
+                encode_url_path_segment(&file.name)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n>\n")
+}
+
+fn encode_url_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Output files of a document split per level-1 section, as (filename, content) pairs
+pub struct SplitDocumentFiles {
+    pub sections: Vec<(String, String)>,
+    /// Table of contents linking to the sections, or the whole document if it has no level-1 headings
+    pub index: (String, String),
+}
+
+/// Splits the document into one file per level-1 section plus an index file
+pub fn split_document_files(content: &str, prefix: &str) -> SplitDocumentFiles {
+    let (preamble, sections) = split_document_sections(content);
+
+    if sections.is_empty() {
+        return SplitDocumentFiles {
+            sections: Vec::new(),
+            index: (format!("{}.md", prefix), content.to_string()),
+        };
+    }
+
+    let mut index = String::new();
+    if !preamble.trim().is_empty() {
+        index.push_str(preamble.trim_end());
+        index.push_str("\n\n");
+    }
+    index.push_str("## Table of Contents\n\n");
+
+    let mut section_files = Vec::with_capacity(sections.len());
+    for (i, (heading, section)) in sections.into_iter().enumerate() {
+        let filename = format!(
+            "{}_section_{:02}_{}.md",
+            prefix,
+            i + 1,
+            slugify_heading(&heading)
+        );
+        index.push_str(&format!("{}. [{}](./{})\n", i + 1, heading, filename));
+        section_files.push((filename, section));
+    }
+
+    SplitDocumentFiles {
+        sections: section_files,
+        index: (format!("{}_index.md", prefix), index),
+    }
+}
+
+/// Saves each level-1 section of the document as a separate file and writes an index
+/// file with links to all sections. Every file goes through `save_document_to_file`, so the
+/// encoding and line ending settings apply to each of them; only the index is opened when
+/// `auto_open_document` is set and passed to the post generation hook. Returns the path of the index file,
+/// or of the single saved file if the document has no level-1 headings.
+pub async fn save_split_document(
+    content: &str,
+    save_directory: &str,
+    prefix: &str,
+    settings: &AppSettings,
+    app: &tauri::AppHandle,
+) -> Result<String, String> {
+    let files = split_document_files(content, prefix);
+
+    let section_settings = AppSettings {
+        auto_open_document: false,
+        post_generation_hook: None,
+        ..settings.clone()
+    };
+    for (filename, section) in files.sections {
+        save_document_to_file(
+            section,
+            save_directory.to_string(),
+            filename,
+            Some(section_settings.clone()),
+            app.clone(),
+        )
+        .await?;
+    }
+
+    let (filename, index) = files.index;
+    save_document_to_file(
+        index,
+        save_directory.to_string(),
+        filename,
+        Some(settings.clone()),
+        app.clone(),
+    )
+    .await
+}
+
+/// Splits a Markdown document on level-1 ATX headings (`# `), ignoring fenced code blocks.
+/// Returns the text before the first heading and the (heading, section) pairs.
+fn split_document_sections(content: &str) -> (String, Vec<(String, String)>) {
+    let mut preamble = String::new();
+    let mut sections: Vec<(String, String)> = Vec::new();
+    let mut in_code_block = false;
+
+    for line in content.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+
+        if !in_code_block && line.starts_with("# ") {
+            let heading = line[2..].trim().to_string();
+            sections.push((heading, String::new()));
+        }
+
+        let target = match sections.last_mut() {
+            Some((_, section)) => section,
+            None => &mut preamble,
+        };
+        target.push_str(line);
+        target.push('\n');
+    }
+
+    (preamble, sections)
+}
+
+/// Converts a heading into a file name friendly slug
+fn slugify_heading(heading: &str) -> String {
+    let mut slug = String::new();
+    for c in heading.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    let slug: String = slug.trim_matches('-').chars().take(50).collect();
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug
+    }
+}
+
+/// Detects the encoding of a text file (UTF-8, UTF-8 with BOM or Shift-JIS)
+pub fn detect_encoding(path: &str) -> anyhow::Result<OutputEncoding> {
+    let bytes = fs::read(path)?;
+    if bytes.starts_with(b"\xEF\xBB\xBF") {
+        return Ok(OutputEncoding::Utf8Bom);
+    }
+    if std::str::from_utf8(&bytes).is_ok() {
+        return Ok(OutputEncoding::Utf8);
+    }
+
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(&bytes, true);
+    let encoding = detector.guess(None, true);
+    if encoding == encoding_rs::SHIFT_JIS {
+        Ok(OutputEncoding::ShiftJis)
+    } else {
+        Err(anyhow!("Unsupported text encoding: {}", encoding.name()))
+    }
+}
+
+/// Reads a text file, decoding it according to its detected encoding
+pub fn read_text_file(path: &std::path::Path) -> anyhow::Result<String> {
+    let encoding = detect_encoding(&path.to_string_lossy())?;
+    let bytes = fs::read(path)?;
+    let content = match encoding {
+        OutputEncoding::Utf8 => String::from_utf8(bytes)?,
+        OutputEncoding::Utf8Bom => String::from_utf8(bytes[3..].to_vec())?,
+        OutputEncoding::ShiftJis => encoding_rs::SHIFT_JIS.decode(&bytes).0.into_owned(),
+    };
+    Ok(content)
+}
+
+/// Builds the shell command for the post generation hook. `{}` in the hook refers to the
+/// document, which is passed as a positional argument (`$1`) or environment variable
+/// (`%DOCUMENT_ENCODER_DOCUMENT%`) rather than spliced into the command line, so spaces and
+/// shell metacharacters in file names are never interpreted. Returns the command line too.
+pub fn post_generation_hook_command(
+    hook: &str,
+    document_path: &str,
+) -> (tokio::process::Command, String) {
+    #[cfg(target_os = "windows")]
+    let (mut command, command_line) = {
+        let command_line = hook.replace("{}", "\"%DOCUMENT_ENCODER_DOCUMENT%\"");
+        let mut command = tokio::process::Command::new("cmd");
+        command.args(["/C", &command_line]);
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+        (command, command_line)
+    };
+    #[cfg(not(target_os = "windows"))]
+    let (mut command, command_line) = {
+        let command_line = hook.replace("{}", "\"$1\"");
+        let mut command = tokio::process::Command::new("sh");
+        command.args(["-c", &command_line, "sh", document_path]);
+        (command, command_line)
+    };
+
+    command.env("DOCUMENT_ENCODER_DOCUMENT", document_path);
+    (command, command_line)
+}
+
+async fn run_post_generation_hook(hook: &str, document_path: &str, app: &tauri::AppHandle) {
+    let (mut command, command_line) = post_generation_hook_command(hook, document_path);
+    println!(
+        "🪝 [HOOK] Running post generation hook: {} ({})",
+        command_line, document_path
+    );
+
+    command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let emit_failure = |exit_code: Option<i32>, message: String| {
+        println!("❌ [HOOK] {}", message);
+        let failure = HookFailure {
+            command: command_line.clone(),
+            exit_code,
+            message,
+        };
+        if let Err(e) = app.emit("hook_failed", &failure) {
+            println!("❌ [HOOK] Failed to emit hook_failed event: {}", e);
+        }
+    };
+
+    let child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            emit_failure(None, format!("Failed to start post generation hook: {}", e));
+            return;
+        }
+    };
+
+    let output = match timeout(
+        Duration::from_secs(POST_GENERATION_HOOK_TIMEOUT_SECS),
+        child.wait_with_output(),
+    )
+    .await
+    {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => {
+            emit_failure(
+                None,
+                format!("Failed to wait for post generation hook: {}", e),
+            );
+            return;
+        }
+        Err(_) => {
+            // The child is killed when its future is dropped (kill_on_drop)
+            emit_failure(
+                None,
+                format!(
+                    "Post generation hook timed out after {} seconds",
+                    POST_GENERATION_HOOK_TIMEOUT_SECS
+                ),
+            );
+            return;
+        }
+    };
+
+    let hook_output = HookOutput {
+        command: command_line.clone(),
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+    };
+    if let Err(e) = app.emit("hook_output", &hook_output) {
+        println!("❌ [HOOK] Failed to emit hook_output event: {}", e);
+    }
+
+    if output.status.success() {
+        println!("✅ [HOOK] Post generation hook completed successfully");
+    } else {
+        emit_failure(
+            output.status.code(),
+            format!("Post generation hook exited with status {}", output.status),
+        );
+    }
+}
+
+/// Intermediate files (split segments, encoded videos) created while generating a document.
+/// Each file is recorded in the manifest of `directory` as soon as it is added, so files kept with
+/// `save_intermediate_files` or left behind by a crash can be found by `clean_stale_intermediate_files`.
+/// Unless the files are kept, they are deleted when the list is dropped, so cleanup also happens on early returns.
+#[derive(Debug)]
+pub struct CleanupList {
+    directory: PathBuf,
+    paths: Vec<PathBuf>,
+    delete_on_drop: bool,
+}
+
+impl CleanupList {
+    pub fn new(directory: &Path, delete_on_drop: bool) -> Self {
+        Self {
+            directory: directory.to_path_buf(),
+            paths: Vec::new(),
+            delete_on_drop,
+        }
+    }
+
+    pub fn add(&mut self, path: PathBuf) {
+        if !self.paths.contains(&path) {
+            record_intermediate_files(&self.directory, std::slice::from_ref(&path));
+            self.paths.push(path);
+        }
+    }
+}
+
+impl Drop for CleanupList {
+    fn drop(&mut self) {
+        if !self.delete_on_drop {
+            return;
+        }
+        for path in &self.paths {
+            match fs::remove_file(path) {
+                Ok(()) => println!("🧹 [CLEANUP] Removed intermediate file: {}", path.display()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => println!(
+                    "⚠️ [CLEANUP] Failed to remove intermediate file {}: {}",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+        forget_intermediate_files(&self.directory, &self.paths);
+    }
+}
+
+// Jobs running in parallel may update the same manifest
+static INTERMEDIATE_MANIFEST_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+fn read_intermediate_manifest(directory: &Path) -> Vec<PathBuf> {
+    fs::read_to_string(directory.join(INTERMEDIATE_FILES_MANIFEST))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_intermediate_manifest(directory: &Path, paths: &[PathBuf]) {
+    let manifest_path = directory.join(INTERMEDIATE_FILES_MANIFEST);
+    let result = if paths.is_empty() {
+        match fs::remove_file(&manifest_path) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            other => other,
+        }
+    } else {
+        serde_json::to_string_pretty(paths)
+            .map_err(std::io::Error::other)
+            .and_then(|json| fs::write(&manifest_path, json))
+    };
+    if let Err(e) = result {
+        println!(
+            "⚠️ [CLEANUP] Failed to update {}: {}",
+            manifest_path.display(),
+            e
+        );
+    }
+}
+
+/// Records files created by a job in the manifest of `directory` so that
+/// `clean_stale_intermediate_files` can remove them later. Only recorded files are ever deleted.
+pub fn record_intermediate_files(directory: &Path, paths: &[PathBuf]) {
+    let _guard = INTERMEDIATE_MANIFEST_LOCK
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    let mut recorded = read_intermediate_manifest(directory);
+    for path in paths {
+        if !recorded.contains(path) {
+            recorded.push(path.clone());
+        }
+    }
+    write_intermediate_manifest(directory, &recorded);
+}
+
+fn forget_intermediate_files(directory: &Path, paths: &[PathBuf]) {
+    let _guard = INTERMEDIATE_MANIFEST_LOCK
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    let mut recorded = read_intermediate_manifest(directory);
+    recorded.retain(|path| !paths.contains(path));
+    write_intermediate_manifest(directory, &recorded);
+}
+
+/// Finds encoded videos, split segments and extracted screenshots that earlier jobs recorded in the
+/// manifest of `save_directory`. Files that were not recorded are never touched, whatever their name.
+/// Files modified less than an hour ago are skipped because a job may still be using them.
+#[tauri::command]
+pub async fn clean_stale_intermediate_files(
+    save_directory: String,
+    dry_run: bool,
+) -> Result<CleanupReport, String> {
+    let report = clean_intermediate_files(
+        Path::new(&save_directory),
+        dry_run,
+        STALE_INTERMEDIATE_FILE_AGE,
+    )?;
+    println!(
+        "🧹 [CLEANUP] Found {} intermediate files ({} bytes), deleted {} ({} bytes)",
+        report.files_found.len(),
+        report.total_bytes_found,
+        report.files_deleted.len(),
+        report.total_bytes_freed
+    );
+    Ok(report)
+}
+
+pub fn clean_intermediate_files(
+    save_directory: &Path,
+    dry_run: bool,
+    min_age: Duration,
+) -> Result<CleanupReport, String> {
+    let mut report = CleanupReport::default();
+    for directory in intermediate_manifest_directories(save_directory) {
+        clean_recorded_files(&directory, dry_run, min_age, &mut report);
+    }
+    Ok(report)
+}
+
+/// The save directory and the job output directories directly below it that have a manifest
+fn intermediate_manifest_directories(save_directory: &Path) -> Vec<PathBuf> {
+    let mut directories = vec![save_directory.to_path_buf()];
+    if let Ok(entries) = fs::read_dir(save_directory) {
+        directories.extend(
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.join(INTERMEDIATE_FILES_MANIFEST).is_file()),
+        );
+    }
+    directories
+}
+
+fn clean_recorded_files(
+    directory: &Path,
+    dry_run: bool,
+    min_age: Duration,
+    report: &mut CleanupReport,
+) {
+    // Deleted files and files that no longer exist are dropped from the manifest
+    let mut forgotten = Vec::new();
+
+    for path in read_intermediate_manifest(directory) {
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => {
+                forgotten.push(path);
+                continue;
+            }
+        };
+        let modified = metadata
+            .modified()
+            .unwrap_or_else(|_| std::time::SystemTime::now());
+        if modified.elapsed().unwrap_or_default() < min_age {
+            continue;
+        }
+        let path_str = path.to_string_lossy().to_string();
+        report.files_found.push(path_str.clone());
+        report.total_bytes_found += metadata.len();
+
+        if dry_run {
+            continue;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => {
+                report.files_deleted.push(path_str);
+                report.total_bytes_freed += metadata.len();
+                forgotten.push(path);
+            }
+            Err(e) => println!(
+                "⚠️ [CLEANUP] Failed to remove intermediate file {}: {}",
+                path.display(),
+                e
+            ),
+        }
+    }
+
+    if !dry_run && !forgotten.is_empty() {
+        forget_intermediate_files(directory, &forgotten);
+    }
+}
+
+/// Validates the sizes of the input files before any processing starts.
+/// Returns the warnings and the errors found across all files.
+pub fn validate_video_file_sizes(
+    files: &[VideoFile],
+    max_upload_bytes: u64,
+) -> (Vec<String>, Vec<ValidationError>) {
+    let mut warnings = Vec::new();
+    let mut errors = Vec::new();
+
+    for file in files {
+        if file.size == 0 {
+            errors.push(ValidationError::EmptyFile {
+                name: file.name.clone(),
+            });
+        } else if file.size < SUSPICIOUSLY_SMALL_FILE_SIZE {
+            warnings.push(format!(
+                "{}: ファイルサイズが非常に小さいです ({} bytes)。正しいファイルか確認してください",
+                file.name, file.size
+            ));
+        } else if file.size > max_upload_bytes {
+            errors.push(ValidationError::ExceedsUploadLimit {
+                name: file.name.clone(),
+                size: file.size,
+                limit: max_upload_bytes,
+            });
+        }
+    }
+
+    (warnings, errors)
+}
+
+/// Finds input files that are the same video (and the same clip range), based on their fingerprints.
+/// Files whose fingerprint cannot be computed are skipped.
+pub async fn find_duplicate_files(
+    files: &[VideoFile],
+    timeouts: &ProcessTimeouts,
+) -> Option<DuplicateFilesError> {
+    let mut fingerprints: Vec<(String, &VideoFile)> = Vec::new();
+    let mut pairs = Vec::new();
+
+    for file in files {
+        let fingerprint = match compute_video_fingerprint(&file.path, timeouts).await {
+            Ok(fingerprint) => fingerprint,
+            Err(e) => {
+                println!(
+                    "⚠️ [VALIDATION] Failed to fingerprint {}, skipping duplicate check: {}",
+                    file.name, e
+                );
+                continue;
+            }
+        };
+
+        // Different ranges of the same video are intentional, not duplicates
+        for (existing, existing_file) in &fingerprints {
+            if *existing == fingerprint
+                && existing_file.start_offset_seconds == file.start_offset_seconds
+                && existing_file.end_offset_seconds == file.end_offset_seconds
+            {
+                pairs.push((existing_file.name.clone(), file.name.clone()));
+            }
+        }
+        fingerprints.push((fingerprint, file));
+    }
+
+    if pairs.is_empty() {
+        None
+    } else {
+        Some(DuplicateFilesError { pairs })
+    }
+}
+
+/// Estimates the disk space a generation job needs and compares it with the free space
+/// available in the save directory
+#[tauri::command]
+pub async fn check_disk_space(
+    files: Vec<VideoFile>,
+    settings: AppSettings,
+    save_directory: String,
+) -> Result<DiskSpaceCheck, String> {
+    let total_size: u64 = files.iter().map(|f| f.size).sum();
+
+    // Rough number of screenshots Gemini is asked to reference per file
+    let expected_screenshots = if settings.embed_images {
+        let per_file = match settings.image_embed_frequency {
+            ImageEmbedFrequency::Minimal => 5,
+            ImageEmbedFrequency::Moderate => 15,
+            ImageEmbedFrequency::Detailed => 30,
+        };
+        per_file * files.len() as u64
+    } else {
+        0
+    };
+
+    let required_bytes = total_size.saturating_mul(2)
+        + expected_screenshots * SCREENSHOT_SIZE_ESTIMATE
+        + DISK_SPACE_OVERHEAD;
+
+    let available_bytes = fs2::available_space(&save_directory)
+        .map_err(|e| format!("Failed to get available disk space: {}", e))?;
+
+    let sufficient = available_bytes >= required_bytes;
+    let recommendation = if sufficient {
+        "十分な空き容量があります".to_string()
+    } else {
+        format!(
+            "空き容量が不足しています。保存先のディスクに少なくとも {:.1} GB の空きを確保してください（現在の空き容量: {:.1} GB）",
+            required_bytes as f64 / 1_000_000_000.0,
+            available_bytes as f64 / 1_000_000_000.0
+        )
+    };
+
+    Ok(DiskSpaceCheck {
+        required_bytes,
+        available_bytes,
+        sufficient,
+        recommendation,
+    })
+}
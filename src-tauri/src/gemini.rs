@@ -1,17 +1,578 @@
 use anyhow::Result;
+use base64::{engine::general_purpose, Engine as _};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tauri::Emitter;
-use tokio::time::{sleep, Duration};
+use tokio::io::AsyncReadExt;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio::time::{sleep, Duration, Instant};
 
 use crate::types::{
-    AppSettings, FrameExtractionMethod, GeminiContent, GeminiFileData, GeminiGenerationConfig,
-    GeminiPart, GeminiRequest, GeminiResponse, GeminiUploadResponse, ImageEmbedFrequency,
-    ProgressUpdate,
+    default_gemini_generation_timeout_seconds, default_gemini_request_timeout_seconds, AppSettings,
+    DocumentFormatPreset, DocumentLanguage, Flashcard, FrameExtractionMethod, FrameOutputFormat,
+    GeminiApiError, GeminiApiVersion, GeminiContent, GeminiFileData, GeminiGenerationConfig,
+    GeminiGroundingMetadata, GeminiInlineData, GeminiPart, GeminiRequest, GeminiResponse,
+    GeminiSafetySetting, GeminiThinkingConfig, GeminiUploadResponse, ImageEmbedFrequency,
+    IntegrationStrategy, ModelCache, ModelFallback, NumberingStyle, ProcessTimeouts,
+    ProgressUpdate, ProgressVerbosity, PromptChunking, PromptPreset, SampledFrame,
+    StructuredDocument,
 };
 
+const DEFAULT_GEMINI_API_BASE_URL: &str = "https://generativelanguage.googleapis.com";
+// The Files API is only available under v1beta, regardless of the selected API version
+const FILE_API_VERSION: GeminiApiVersion = GeminiApiVersion::V1Beta;
+
+pub const SUPPORTED_REFERENCE_EXTENSIONS: [&str; 3] = ["md", "txt", "pdf"];
+const MAX_REFERENCE_DOCUMENT_CHARS: usize = 50_000;
+const MODEL_CACHE_TTL_SECS: i64 = 24 * 60 * 60;
+const CONTEXT_CACHE_TTL_SECS: u64 = 10 * 60;
+// Used when the model list cannot be fetched
+pub const KNOWN_STABLE_MODELS: &[&str] = &[
+    "gemini-2.5-pro",
+    "gemini-2.5-flash",
+    "gemini-2.5-flash-lite",
+    "gemini-2.0-flash",
+    "gemini-2.0-flash-lite",
+];
+// Request size limit for inline data in generateContent
+const MAX_INLINE_DATA_BYTES: usize = 20 * 1024 * 1024;
+const TITLE_EXCERPT_CHARS: usize = 500;
+const IMAGE_CAPTION_PROMPT: &str = "Describe this screenshot in one sentence for use as an image alt-text, focusing on what the user sees and any visible text.";
+
+// Built-in prompts for the document format presets (shipped with the app, not user-editable)
+const USER_MANUAL_PROMPT: &str = "この動画の内容を詳細に分析し、ユーザーマニュアルとして構成してください。操作手順、注意点、トラブルシューティングを含めて説明してください。";
+const TECHNICAL_SPEC_PROMPT: &str = "この動画の内容を技術仕様書として構成してください。システムの概要、機能詳細、API仕様、データ構造を含めて説明してください。";
+const MEETING_NOTES_PROMPT: &str = "この動画の会議内容を議事録として構成してください。参加者、議題、議論の要点、決定事項、アクションアイテム（担当者と期限）を含めて整理してください。";
+const TUTORIAL_GUIDE_PROMPT: &str = "この動画の内容をチュートリアルガイドとして構成してください。前提条件、学習目標、段階的な手順、各ステップの確認ポイント、よくある間違いを含めて説明してください。";
+const LECTURE_NOTES_PROMPT: &str = "この動画の講義内容を講義ノートとして構成してください。主要なテーマ、重要な概念と定義、具体例、要点のまとめ、復習用の質問を含めて整理してください。";
+// Sent with the first 30 seconds of the video when auto_detect_document_type is enabled
+const DOCUMENT_TYPE_CLASSIFICATION_PROMPT: &str = "In one word, classify this video: manual, specification, tutorial, lecture, meeting, demo. Answer only with the classification word.";
+// Injected in front of the prompt by generate_document_from_screen_recording
+const SCREEN_RECORDING_PROMPT_PREFIX: &str = "This is a screen recording. Identify and document: menu interactions, button clicks, form inputs, terminal commands (reproduce them exactly), dialog messages, and any visible error messages. Structure the output as a step-by-step guide.";
+
+/// Returns the built-in prompt for a format preset, or `None` for `Custom`
+pub fn format_preset_prompt(preset: &DocumentFormatPreset) -> Option<&'static str> {
+    match preset {
+        DocumentFormatPreset::UserManual => Some(USER_MANUAL_PROMPT),
+        DocumentFormatPreset::TechnicalSpec => Some(TECHNICAL_SPEC_PROMPT),
+        DocumentFormatPreset::MeetingNotes => Some(MEETING_NOTES_PROMPT),
+        DocumentFormatPreset::TutorialGuide => Some(TUTORIAL_GUIDE_PROMPT),
+        DocumentFormatPreset::LectureNotes => Some(LECTURE_NOTES_PROMPT),
+        DocumentFormatPreset::Custom => None,
+    }
+}
+
+/// The prompt used for generation: the user's custom prompt when one is set,
+/// otherwise the built-in prompt of the selected format preset
+pub fn effective_prompt(settings: &AppSettings) -> Option<&str> {
+    settings
+        .custom_prompt
+        .as_deref()
+        .filter(|prompt| !prompt.trim().is_empty())
+        .or_else(|| format_preset_prompt(&settings.format_preset))
+}
+
+/// Prepends the screen recording instructions to the prompt that would otherwise be used
+pub fn screen_recording_prompt(settings: &AppSettings) -> String {
+    match effective_prompt(settings) {
+        Some(prompt) if !prompt.trim().is_empty() => {
+            format!("{}\n\n{}", SCREEN_RECORDING_PROMPT_PREFIX, prompt)
+        }
+        _ => SCREEN_RECORDING_PROMPT_PREFIX.to_string(),
+    }
+}
+
+/// Parses the body of a failed Gemini API response.
+/// Bodies that are not a Gemini error object keep their text as the message.
+pub fn parse_gemini_error(response_body: &str) -> GeminiApiError {
+    let body: serde_json::Value = serde_json::from_str(response_body).unwrap_or_default();
+    let error = &body["error"];
+    let code = error["code"].as_u64().unwrap_or_default() as u32;
+    let message = error["message"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| response_body.trim().to_string());
+    let status = error["status"].as_str().unwrap_or_default().to_string();
+
+    let lowercase_message = message.to_lowercase();
+    let user_facing_message = match code {
+        400 if lowercase_message.contains("safety") => {
+            "Content was blocked by safety filters. Try adjusting safety settings.".to_string()
+        }
+        429 => "Rate limit reached. Please wait and try again.".to_string(),
+        404 if lowercase_message.contains("model") => {
+            "Model not found. Please check model name in settings.".to_string()
+        }
+        _ => message.clone(),
+    };
+
+    GeminiApiError {
+        code,
+        message,
+        status,
+        user_facing_message,
+    }
+}
+
+/// Prepends the chapter title to the prompt used for one chapter segment of an audio file
+pub fn chapter_segment_prompt(chapter_title: &str, prompt: Option<&str>) -> String {
+    match prompt {
+        Some(prompt) if !prompt.trim().is_empty() => {
+            format!("Chapter: {}\n\n{}", chapter_title, prompt)
+        }
+        _ => format!("Chapter: {}", chapter_title),
+    }
+}
+
+/// Read-only preset entries shown in the UI for the built-in format presets
+pub fn builtin_prompt_presets() -> Vec<PromptPreset> {
+    [
+        (
+            "default_manual",
+            "デフォルト（マニュアル）",
+            DocumentFormatPreset::UserManual,
+        ),
+        (
+            "default_specification",
+            "デフォルト（仕様書）",
+            DocumentFormatPreset::TechnicalSpec,
+        ),
+        (
+            "default_meeting_notes",
+            "デフォルト（議事録）",
+            DocumentFormatPreset::MeetingNotes,
+        ),
+        (
+            "default_tutorial_guide",
+            "デフォルト（チュートリアル）",
+            DocumentFormatPreset::TutorialGuide,
+        ),
+        (
+            "default_lecture_notes",
+            "デフォルト（講義ノート）",
+            DocumentFormatPreset::LectureNotes,
+        ),
+    ]
+    .into_iter()
+    .map(|(id, name, preset)| PromptPreset {
+        id: id.to_string(),
+        name: name.to_string(),
+        prompt: format_preset_prompt(&preset)
+            .unwrap_or_default()
+            .to_string(),
+        is_default: true,
+        format_preset: Some(preset),
+        tags: Vec::new(),
+        version: 1,
+    })
+    .collect()
+}
+
+/// Connection settings for the Gemini API, built from `AppSettings` for each job
+#[derive(Debug, Clone)]
+pub struct GeminiClient {
+    pub api_key: String,
+    pub base_url: String,
+    pub request_timeout_secs: u64,
+    pub generation_timeout_secs: u64,
+}
+
+impl GeminiClient {
+    /// Client with the default timeouts, for commands that are called without settings
+    pub fn new(api_key: &str) -> Self {
+        GeminiClient {
+            api_key: api_key.to_string(),
+            base_url: DEFAULT_GEMINI_API_BASE_URL.to_string(),
+            request_timeout_secs: default_gemini_request_timeout_seconds(),
+            generation_timeout_secs: default_gemini_generation_timeout_seconds(),
+        }
+    }
+
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        GeminiClient {
+            api_key: settings.gemini_api_key.clone(),
+            base_url: DEFAULT_GEMINI_API_BASE_URL.to_string(),
+            request_timeout_secs: settings.gemini_request_timeout_seconds,
+            generation_timeout_secs: settings.gemini_generation_timeout_seconds,
+        }
+    }
+
+    /// Sends the requests to another server, e.g. a proxy or a mock server in tests
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// URL of a Gemini API endpoint, e.g. `api_url(&GeminiApiVersion::V1, "models/gemini-2.5-pro:generateContent")`
+    pub fn api_url(&self, version: &GeminiApiVersion, path: &str) -> String {
+        format!(
+            "{}/{}/{}",
+            self.base_url,
+            version.as_str(),
+            path.trim_start_matches('/')
+        )
+    }
+
+    /// HTTP client for file, model and token count requests
+    fn http_client(&self) -> reqwest::Client {
+        build_client_with_timeout(self.request_timeout_secs)
+    }
+
+    /// HTTP client for `generateContent`, which can take several minutes for long videos
+    fn generation_http_client(&self) -> reqwest::Client {
+        build_client_with_timeout(self.generation_timeout_secs)
+    }
+}
+
+fn build_client_with_timeout(timeout_secs: u64) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(timeout_secs.max(1)))
+        .build()
+        .unwrap_or_else(|e| {
+            println!(
+                "⚠️ [GEMINI] Failed to build HTTP client with timeout: {}",
+                e
+            );
+            reqwest::Client::new()
+        })
+}
+
+/// Caches the video files server-side so that follow-up requests about the same videos
+/// can refer to them through `GeminiRequest::with_cached_content`. Returns the cache name.
+pub async fn create_gemini_cache(
+    files: &[GeminiFileData],
+    gemini: &GeminiClient,
+    model: &str,
+    ttl_seconds: u64,
+) -> Result<String> {
+    let client = gemini.http_client();
+
+    let parts: Vec<GeminiPart> = files
+        .iter()
+        .map(|file| GeminiPart::FileData {
+            file_data: file.clone(),
+        })
+        .collect();
+    let request_body = serde_json::json!({
+        "model": format!("models/{}", model),
+        "contents": [{ "role": "user", "parts": parts }],
+        "ttl": format!("{}s", ttl_seconds),
+    });
+
+    let response = client
+        .post(format!(
+            "{}?key={}",
+            gemini.api_url(&GeminiApiVersion::V1Beta, "cachedContents"),
+            gemini.api_key
+        ))
+        .json(&request_body)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let api_error = parse_gemini_error(&response.text().await?);
+        return Err(anyhow::anyhow!("Failed to create cache: {}", api_error));
+    }
+
+    let body: serde_json::Value = response.json().await?;
+    let cache_name = body["name"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("No name in cachedContents response"))?
+        .to_string();
+    println!(
+        "🗄️ [CACHE] Created {} for {} files (ttl {}s)",
+        cache_name,
+        files.len(),
+        ttl_seconds
+    );
+    Ok(cache_name)
+}
+
+/// Lists the models that support `generateContent` for this API key (without the `models/` prefix)
+pub async fn get_supported_gemini_models(gemini: &GeminiClient) -> Result<Vec<String>> {
+    let client = gemini.http_client();
+    let mut models = Vec::new();
+    let mut page_token: Option<String> = None;
+
+    loop {
+        let mut request = client
+            .get(gemini.api_url(&GeminiApiVersion::V1Beta, "models"))
+            .query(&[("key", gemini.api_key.as_str()), ("pageSize", "1000")]);
+        if let Some(token) = page_token.as_deref() {
+            request = request.query(&[("pageToken", token)]);
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            let api_error = parse_gemini_error(&response.text().await?);
+            return Err(anyhow::anyhow!("Failed to list models: {}", api_error));
+        }
+
+        let body: serde_json::Value = response.json().await?;
+        for model in body["models"].as_array().cloned().unwrap_or_default() {
+            let supports_generate = model["supportedGenerationMethods"]
+                .as_array()
+                .is_some_and(|methods| methods.iter().any(|m| m == "generateContent"));
+            if let (true, Some(name)) = (supports_generate, model["name"].as_str()) {
+                models.push(name.trim_start_matches("models/").to_string());
+            }
+        }
+
+        match body["nextPageToken"].as_str() {
+            Some(token) if !token.is_empty() => page_token = Some(token.to_string()),
+            _ => break,
+        }
+    }
+
+    Ok(models)
+}
+
+/// Counts the input tokens of a prompt with uploaded videos without generating anything
+pub async fn count_gemini_tokens(
+    files: &[GeminiFileData],
+    prompt: &str,
+    gemini: &GeminiClient,
+    model: &str,
+) -> Result<u64> {
+    // Same part layout as generate_with_gemini_internal: prompt first, then the videos
+    let mut parts = vec![GeminiPart::Text {
+        text: prompt.to_string(),
+    }];
+    for file in files {
+        parts.push(GeminiPart::FileData {
+            file_data: file.clone(),
+        });
+    }
+    let request = GeminiRequest {
+        contents: vec![GeminiContent { parts }],
+        generation_config: None,
+        tools: None,
+        safety_settings: None,
+        cached_content: None,
+    };
+
+    let client = gemini.http_client();
+    let response = client
+        .post(gemini.api_url(
+            &GeminiApiVersion::V1Beta,
+            &format!("models/{}:countTokens", model),
+        ))
+        .query(&[("key", gemini.api_key.as_str())])
+        .json(&request)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let api_error = parse_gemini_error(&response.text().await?);
+        return Err(anyhow::anyhow!("Failed to count tokens: {}", api_error));
+    }
+
+    let body: serde_json::Value = response.json().await?;
+    body["totalTokens"]
+        .as_u64()
+        .ok_or_else(|| anyhow::anyhow!("No totalTokens in countTokens response"))
+}
+
+/// Asks Gemini for a one-word classification of an uploaded clip
+pub async fn classify_video_type(
+    file: &GeminiFileData,
+    gemini: &GeminiClient,
+    model: &str,
+    api_version: &GeminiApiVersion,
+    safety_settings: &[GeminiSafetySetting],
+) -> Result<String> {
+    let parts = vec![
+        GeminiPart::Text {
+            text: DOCUMENT_TYPE_CLASSIFICATION_PROMPT.to_string(),
+        },
+        GeminiPart::FileData {
+            file_data: file.clone(),
+        },
+    ];
+    let classification = generate_text(
+        "Classification",
+        parts,
+        None,
+        gemini,
+        model,
+        api_version,
+        safety_settings,
+    )
+    .await?;
+    Ok(classification.trim().to_string())
+}
+
+/// Resource name (`files/...`) of an uploaded file, accepting either the name or the file URI
+pub fn gemini_file_name(file_uri: &str) -> String {
+    match file_uri.find("files/") {
+        Some(index) => file_uri[index..].to_string(),
+        None => file_uri.to_string(),
+    }
+}
+
+/// Deletes a file uploaded through the File API
+pub async fn delete_uploaded_gemini_file(file_uri: &str, gemini: &GeminiClient) -> Result<()> {
+    let client = gemini.http_client();
+    let response = client
+        .delete(gemini.api_url(&FILE_API_VERSION, &gemini_file_name(file_uri)))
+        .query(&[("key", gemini.api_key.as_str())])
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let api_error = parse_gemini_error(&response.text().await?);
+        return Err(anyhow::anyhow!("Failed to delete file: {}", api_error));
+    }
+    Ok(())
+}
+
+/// Checks that a file URI points at the File API and that the file can still be used.
+/// Uploaded files expire after 48 hours, after which the URI has to be replaced by a new upload.
+pub async fn ensure_gemini_file_active(file_uri: &str, gemini: &GeminiClient) -> Result<()> {
+    let files_prefix = format!("{}/{}/files/", gemini.base_url, FILE_API_VERSION.as_str());
+    if !file_uri.starts_with(&files_prefix) || file_uri.len() == files_prefix.len() {
+        return Err(anyhow::anyhow!(
+            "Invalid Gemini file URI: {} (expected {}*)",
+            file_uri,
+            files_prefix
+        ));
+    }
+
+    let client = gemini.http_client();
+    let response = client
+        .get(gemini.api_url(&FILE_API_VERSION, &gemini_file_name(file_uri)))
+        .query(&[("key", gemini.api_key.as_str())])
+        .send()
+        .await?;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::FORBIDDEN {
+        return Err(anyhow::anyhow!(
+            "This Gemini file URI expired. Please re-upload the video. ({})",
+            file_uri
+        ));
+    }
+    if !status.is_success() {
+        let api_error = parse_gemini_error(&response.text().await?);
+        return Err(anyhow::anyhow!("Failed to get file status: {}", api_error));
+    }
+
+    let file_info: GeminiFileStatus = response
+        .json()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to parse file status response: {}", e))?;
+    match file_info.state.as_deref() {
+        Some("ACTIVE") => Ok(()),
+        Some("PROCESSING") => Err(anyhow::anyhow!(
+            "The Gemini file is still being processed: {}",
+            file_uri
+        )),
+        _ => Err(anyhow::anyhow!(
+            "This Gemini file URI expired. Please re-upload the video. ({})",
+            file_uri
+        )),
+    }
+}
+
+/// Deletes every file stored for this API key and returns the deleted file names
+pub async fn delete_all_uploaded_gemini_files(gemini: &GeminiClient) -> Result<Vec<String>> {
+    let client = gemini.http_client();
+    let mut file_names = Vec::new();
+    let mut page_token: Option<String> = None;
+
+    loop {
+        let mut request = client
+            .get(gemini.api_url(&FILE_API_VERSION, "files"))
+            .query(&[("key", gemini.api_key.as_str()), ("pageSize", "100")]);
+        if let Some(token) = page_token.as_deref() {
+            request = request.query(&[("pageToken", token)]);
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            let api_error = parse_gemini_error(&response.text().await?);
+            return Err(anyhow::anyhow!("Failed to list files: {}", api_error));
+        }
+
+        let body: serde_json::Value = response.json().await?;
+        for file in body["files"].as_array().cloned().unwrap_or_default() {
+            if let Some(name) = file["name"].as_str() {
+                file_names.push(name.to_string());
+            }
+        }
+
+        match body["nextPageToken"].as_str() {
+            Some(token) if !token.is_empty() => page_token = Some(token.to_string()),
+            _ => break,
+        }
+    }
+
+    // Collect every page first so that deleting does not shift the pagination
+    for name in &file_names {
+        delete_uploaded_gemini_file(name, gemini).await?;
+        println!("🗑️ [FILES] Deleted {}", name);
+    }
+    Ok(file_names)
+}
+
+/// Models that can be used, read from `cache_path` when it is less than 24 hours old.
+/// Falls back to `KNOWN_STABLE_MODELS` when the API cannot be reached.
+pub async fn available_gemini_models(gemini: &GeminiClient, cache_path: &Path) -> Vec<String> {
+    let now = chrono::Utc::now().timestamp();
+
+    let cached = fs::read_to_string(cache_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<ModelCache>(&content).ok());
+    if let Some(cache) = cached {
+        if now - cache.fetched_at < MODEL_CACHE_TTL_SECS && !cache.models.is_empty() {
+            return cache.models;
+        }
+    }
+
+    match get_supported_gemini_models(gemini).await {
+        Ok(models) if !models.is_empty() => {
+            let cache = ModelCache {
+                fetched_at: now,
+                models: models.clone(),
+            };
+            let written = serde_json::to_string_pretty(&cache)
+                .map_err(anyhow::Error::from)
+                .and_then(|json| {
+                    if let Some(parent) = cache_path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(cache_path, json).map_err(anyhow::Error::from)
+                });
+            if let Err(e) = written {
+                println!("⚠️ [GEMINI] Failed to write model cache: {}", e);
+            }
+            models
+        }
+        Ok(_) => KNOWN_STABLE_MODELS.iter().map(|m| m.to_string()).collect(),
+        Err(e) => {
+            println!(
+                "⚠️ [GEMINI] Could not fetch the model list, using known stable models: {}",
+                e
+            );
+            KNOWN_STABLE_MODELS.iter().map(|m| m.to_string()).collect()
+        }
+    }
+}
+
+/// Checks that the model exists before any work is done, so renamed models do not end in a 404
+pub async fn validate_model_name(
+    model: &str,
+    gemini: &GeminiClient,
+    cache_path: &Path,
+) -> Result<bool> {
+    let models = available_gemini_models(gemini, cache_path).await;
+    Ok(models.iter().any(|m| m == model))
+}
+
 // Internal GeminiFileInfo for status polling (with optional fields)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeminiFileStatus {
@@ -38,17 +599,18 @@ pub struct GeminiFileStatus {
 
 pub async fn upload_to_gemini_with_progress(
     file_path: &str,
-    api_key: &str,
+    gemini: &GeminiClient,
+    chunk_size_bytes: usize,
+    bandwidth_limit_kbps: Option<u64>,
+    verbosity: &ProgressVerbosity,
+    timeouts: &ProcessTimeouts,
     app: &tauri::AppHandle,
     base_step: usize,
     total_steps: usize,
+    started_at: Instant,
 ) -> Result<String> {
     let _emit_progress = |message: String| {
-        let progress = ProgressUpdate {
-            message: message.clone(),
-            step: base_step,
-            total_steps,
-        };
+        let progress = ProgressUpdate::new(message.clone(), base_step, total_steps, started_at);
         println!(
             "📡 [UPLOAD_EVENT] Emitting progress: step={}/{}, message={}",
             base_step, total_steps, message
@@ -60,24 +622,43 @@ pub async fn upload_to_gemini_with_progress(
         }
     };
 
-    // Also create a detailed progress emitter that updates the main progress message
+    // Also create a detailed progress emitter that updates the main progress message.
+    // Minimal verbosity keeps only the step-level message emitted by the caller.
     let emit_detailed_progress = |detail_message: String| {
-        let progress = ProgressUpdate {
-            message: detail_message.clone(),
-            step: base_step,
-            total_steps,
-        };
+        if matches!(verbosity, ProgressVerbosity::Minimal) {
+            return;
+        }
+        let progress =
+            ProgressUpdate::new(detail_message.clone(), base_step, total_steps, started_at);
         if let Err(e) = app.emit("progress_update", &progress) {
             println!("❌ [UPLOAD_EVENT] Failed to emit detailed progress: {}", e);
         }
     };
 
-    upload_to_gemini_internal(file_path, api_key, emit_detailed_progress).await
+    upload_to_gemini_internal(
+        file_path,
+        gemini,
+        chunk_size_bytes,
+        bandwidth_limit_kbps,
+        verbosity,
+        timeouts,
+        emit_detailed_progress,
+    )
+    .await
 }
 
+/// Uploads a file with the resumable protocol in `chunk_size_bytes` chunks.
+///
+/// With `bandwidth_limit_kbps` set, the upload sleeps after each chunk for as long as
+/// the chunk would take at that rate. Chunks themselves are sent at full speed, so the
+/// actual rate may exceed the limit by up to one chunk worth of data.
 pub async fn upload_to_gemini_internal<F>(
     file_path: &str,
-    api_key: &str,
+    gemini: &GeminiClient,
+    chunk_size_bytes: usize,
+    bandwidth_limit_kbps: Option<u64>,
+    verbosity: &ProgressVerbosity,
+    timeouts: &ProcessTimeouts,
     emit_progress: F,
 ) -> Result<String>
 where
@@ -86,15 +667,15 @@ where
     println!("📂 [UPLOAD] Starting upload for file: {}", file_path);
     emit_progress("ファイルを読み込み中...".to_string());
 
-    let client = reqwest::Client::new();
-    let file_data = fs::read(file_path)?;
-    let file_size = file_data.len();
+    let client = gemini.http_client();
+    let mut file = tokio::fs::File::open(file_path).await?;
+    let file_size = file.metadata().await?.len() as usize;
     let file_name_for_display = Path::new(file_path)
         .file_name()
         .and_then(|s| s.to_str())
         .unwrap_or("unnamed_video")
         .to_string();
-    let mime_type = get_mime_type(file_path);
+    let mime_type = detect_mime_type(file_path, timeouts).await?;
 
     println!(
         "📊 [UPLOAD] File info - Name: {}, Size: {} bytes, MIME: {}",
@@ -113,8 +694,10 @@ where
 
     let start_response = client
         .post(format!(
-            "https://generativelanguage.googleapis.com/upload/v1beta/files?key={}",
-            api_key
+            "{}/upload/{}/files?key={}",
+            gemini.base_url,
+            FILE_API_VERSION.as_str(),
+            gemini.api_key
         ))
         .header("X-Goog-Upload-Protocol", "resumable")
         .header("X-Goog-Upload-Command", "start")
@@ -126,14 +709,14 @@ where
         .await?;
 
     if !start_response.status().is_success() {
-        let error_text = start_response.text().await?;
+        let api_error = parse_gemini_error(&start_response.text().await?);
         println!(
             "❌ [UPLOAD] Failed to start resumable upload: {}",
-            error_text
+            api_error
         );
         return Err(anyhow::anyhow!(
             "Failed to start resumable upload: {}",
-            error_text
+            api_error
         ));
     }
 
@@ -149,33 +732,86 @@ where
         }
     };
 
-    // 2. Upload the file bytes
+    // 2. Upload the file bytes in chunks, finalizing with the last one
+    let chunk_size = chunk_size_bytes.max(1);
     println!(
-        "📤 [UPLOAD] Step 2: Uploading file bytes ({} bytes)",
-        file_size
+        "📤 [UPLOAD] Step 2: Uploading file bytes ({} bytes, {} byte chunks)",
+        file_size, chunk_size
     );
     emit_progress(format!(
         "ファイルをアップロード中... ({:.1} MB)",
         file_size as f64 / 1_000_000.0
     ));
 
-    let upload_response = client
-        .post(&upload_url)
-        .header("Content-Length", file_size.to_string())
-        .header("X-Goog-Upload-Offset", "0")
-        .header("X-Goog-Upload-Command", "upload, finalize")
-        .body(file_data)
-        .send()
-        .await?;
+    let bandwidth_limit_kbps = bandwidth_limit_kbps.filter(|kbps| *kbps > 0);
+    let bandwidth_note = bandwidth_limit_kbps
+        .map(|kbps| format!(" (帯域制限: {} kbps)", kbps))
+        .unwrap_or_default();
 
-    if !upload_response.status().is_success() {
-        let error_text = upload_response.text().await?;
-        println!("❌ [UPLOAD] Failed to upload file content: {}", error_text);
-        return Err(anyhow::anyhow!(
-            "Failed to upload file content: {}",
-            error_text
+    let mut offset = 0;
+    let upload_response = loop {
+        let chunk_len = (file_size - offset).min(chunk_size);
+        let mut chunk = vec![0u8; chunk_len];
+        file.read_exact(&mut chunk).await?;
+        let is_last_chunk = offset + chunk_len >= file_size;
+        let upload_command = if is_last_chunk {
+            "upload, finalize"
+        } else {
+            "upload"
+        };
+        if matches!(verbosity, ProgressVerbosity::Verbose) {
+            emit_progress(format!(
+                "チャンク送信中: {}〜{} バイト (全 {} バイト)",
+                offset,
+                offset + chunk_len,
+                file_size
+            ));
+        }
+
+        let response = client
+            .post(&upload_url)
+            .header("Content-Length", chunk_len.to_string())
+            .header("X-Goog-Upload-Offset", offset.to_string())
+            .header("X-Goog-Upload-Command", upload_command)
+            .body(chunk)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let api_error = parse_gemini_error(&response.text().await?);
+            println!(
+                "❌ [UPLOAD] Failed to upload file content at offset {}: {}",
+                offset, api_error
+            );
+            return Err(anyhow::anyhow!(
+                "Failed to upload file content: {}",
+                api_error
+            ));
+        }
+
+        offset += chunk_len;
+        let percent = if file_size == 0 {
+            100.0
+        } else {
+            offset as f64 / file_size as f64 * 100.0
+        };
+        emit_progress(format!(
+            "アップロード中... {:.1} / {:.1} MB ({:.0}%){}",
+            offset as f64 / 1_000_000.0,
+            file_size as f64 / 1_000_000.0,
+            percent,
+            bandwidth_note
         ));
-    }
+
+        if is_last_chunk {
+            break response;
+        }
+
+        if let Some(kbps) = bandwidth_limit_kbps {
+            let bytes_per_second = kbps as f64 * 1024.0 / 8.0;
+            tokio::time::sleep(Duration::from_secs_f64(chunk_len as f64 / bytes_per_second)).await;
+        }
+    };
 
     println!("✅ [UPLOAD] File upload completed successfully");
     let upload_info: GeminiUploadResponse = upload_response
@@ -208,16 +844,17 @@ where
 
         let get_response = client
             .get(format!(
-                "https://generativelanguage.googleapis.com/v1beta/{}?key={}",
-                file_name_on_server, api_key
+                "{}?key={}",
+                gemini.api_url(&FILE_API_VERSION, &file_name_on_server),
+                gemini.api_key
             ))
             .send()
             .await?;
 
         if !get_response.status().is_success() {
-            let error_text = get_response.text().await?;
-            println!("❌ [UPLOAD] Failed to get file status: {}", error_text);
-            return Err(anyhow::anyhow!("Failed to get file status: {}", error_text));
+            let api_error = parse_gemini_error(&get_response.text().await?);
+            println!("❌ [UPLOAD] Failed to get file status: {}", api_error);
+            return Err(anyhow::anyhow!("Failed to get file status: {}", api_error));
         }
 
         let file_info: GeminiFileStatus = get_response
@@ -227,6 +864,12 @@ where
 
         if let Some(state) = &file_info.state {
             println!("📊 [UPLOAD] File state: {}", state);
+            if matches!(verbosity, ProgressVerbosity::Verbose) {
+                emit_progress(format!(
+                    "ファイル状態: {} ({}/{}回目)",
+                    state, retry_count, max_retries
+                ));
+            }
             match state.as_str() {
                 "ACTIVE" => {
                     if let Some(uri) = file_info.uri {
@@ -290,101 +933,322 @@ where
     }
 }
 
+/// Inputs of a single document generation that differ between callers,
+/// everything else (model, sampling, formatting, safety) comes from `AppSettings`
+#[derive(Debug, Clone, Copy)]
+pub struct GenerationOptions<'a> {
+    pub file_uris: &'a [String],
+    pub sampled_frames: &'a [SampledFrame],
+    pub custom_prompt: Option<&'a str>,
+    pub embed_images: bool,
+    pub structured_output: bool,
+    pub search_grounding: bool,
+    pub reference_documents: &'a [String],
+}
+
+impl<'a> GenerationOptions<'a> {
+    /// Generation of a document from uploaded videos as configured in the settings
+    pub fn from_settings(file_uris: &'a [String], settings: &'a AppSettings) -> Self {
+        Self {
+            file_uris,
+            sampled_frames: &[],
+            custom_prompt: effective_prompt(settings),
+            embed_images: settings.embed_images,
+            structured_output: settings.structured_output,
+            search_grounding: settings.enable_experimental_features
+                && settings.enable_search_grounding,
+            reference_documents: &settings.reference_documents,
+        }
+    }
+}
+
 pub async fn generate_with_gemini_with_progress(
-    file_uris: &[String],
-    language: &str,
-    api_key: &str,
-    temperature: f64,
-    custom_prompt: Option<&str>,
-    model: &str,
-    embed_images: bool,
-    image_embed_frequency: &ImageEmbedFrequency,
+    options: &GenerationOptions<'_>,
+    settings: &AppSettings,
+    gemini: &GeminiClient,
+    context_cache_files: Option<&[GeminiFileData]>,
     app: &tauri::AppHandle,
     base_step: usize,
     total_steps: usize,
+    started_at: Instant,
 ) -> Result<String> {
     let emit_progress = |message: String| {
-        let progress = ProgressUpdate {
-            message: message.clone(),
-            step: base_step,
-            total_steps,
-        };
+        if matches!(settings.progress_verbosity, ProgressVerbosity::Minimal) {
+            return;
+        }
+        let progress = ProgressUpdate::new(message.clone(), base_step, total_steps, started_at);
         if let Err(e) = app.emit("progress_update", &progress) {
             println!("❌ [GENERATE_EVENT] Failed to emit progress: {}", e);
         }
     };
 
-    generate_with_gemini_internal(
-        file_uris,
-        language,
-        api_key,
-        temperature,
-        custom_prompt,
-        model,
-        embed_images,
-        image_embed_frequency,
+    let emit_model_fallback = |fallback: ModelFallback| {
+        if let Err(e) = app.emit("model_fallback", &fallback) {
+            println!("❌ [GENERATE_EVENT] Failed to emit model_fallback: {}", e);
+        }
+    };
+
+    // Oversized prompts are split by running one pass per chunk of the reference documents
+    let reference_documents = options.reference_documents;
+    let mut reference_chunks = vec![reference_documents];
+    if let Some(max_tokens) = settings
+        .chunk_long_prompts
+        .then_some(settings.max_prompt_tokens)
+    {
+        let prompt = match options.custom_prompt {
+            Some(custom) => custom.to_string(),
+            None => default_document_prompt(&settings.language),
+        };
+        let estimated_tokens = estimate_prompt_tokens(&prompt, reference_documents)?;
+        if matches!(settings.progress_verbosity, ProgressVerbosity::Verbose) {
+            emit_progress(format!(
+                "プロンプトの推定トークン数: {} (上限 {})",
+                estimated_tokens, max_tokens
+            ));
+        }
+        if estimated_tokens > max_tokens as u64 {
+            if reference_documents.len() < 2 {
+                println!(
+                    "⚠️ [GENERATE] Prompt is about {} tokens (limit {}), but there are no reference documents to split",
+                    estimated_tokens, max_tokens
+                );
+            } else {
+                reference_chunks =
+                    split_reference_documents(&prompt, reference_documents, max_tokens as u64)?;
+                println!(
+                    "✂️ [GENERATE] Prompt is about {} tokens (limit {}), splitting reference documents into {} chunks",
+                    estimated_tokens,
+                    max_tokens,
+                    reference_chunks.len()
+                );
+                let chunking = PromptChunking {
+                    original_tokens: estimated_tokens,
+                    chunks: reference_chunks.len() as u32,
+                };
+                if let Err(e) = app.emit("prompt_chunking_applied", &chunking) {
+                    println!(
+                        "❌ [GENERATE_EVENT] Failed to emit prompt_chunking_applied: {}",
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    // Every chunk refers to the same videos, so they are cached once instead of being sent per request
+    let mut cache_name = None;
+    if let Some(files) = context_cache_files.filter(|_| reference_chunks.len() > 1) {
+        match create_gemini_cache(
+            files,
+            gemini,
+            &settings.gemini_model,
+            CONTEXT_CACHE_TTL_SECS,
+        ).await {
+            Ok(name) => cache_name = Some(name),
+            Err(e) => println!(
+                "⚠️ [CACHE] Failed to create context cache, sending the videos with each request: {}",
+                e
+            ),
+        }
+    }
+
+    let mut documents = Vec::with_capacity(reference_chunks.len());
+    for (index, chunk) in reference_chunks.iter().enumerate() {
+        if reference_chunks.len() > 1 {
+            emit_progress(format!(
+                "参照ドキュメントを分割して生成中 ({}/{})...",
+                index + 1,
+                reference_chunks.len()
+            ));
+        }
+        documents.push(
+            generate_with_gemini_internal(
+                &GenerationOptions {
+                    reference_documents: chunk,
+                    ..*options
+                },
+                settings,
+                gemini,
+                cache_name.as_deref(),
+                emit_progress,
+                emit_model_fallback,
+            )
+            .await?,
+        );
+    }
+
+    if documents.len() == 1 {
+        return Ok(documents.remove(0));
+    }
+    emit_progress("分割した生成結果を統合中...".to_string());
+    integrate_documents(
+        &documents,
+        &settings.language,
+        gemini,
+        settings.temperature,
+        options.custom_prompt,
+        &settings.gemini_model,
+        &settings.gemini_api_version,
+        &settings.safety_settings,
+        0.0,
         emit_progress,
     )
     .await
 }
 
-pub async fn generate_with_gemini_internal<F>(
-    file_uris: &[String],
-    language: &str,
-    api_key: &str,
-    temperature: f64,
-    custom_prompt: Option<&str>,
-    model: &str,
-    embed_images: bool,
-    image_embed_frequency: &ImageEmbedFrequency,
+/// Rough token estimate (4 characters per token) of the prompt and all reference documents
+pub fn estimate_prompt_tokens(prompt: &str, reference_documents: &[String]) -> Result<u64> {
+    let mut chars = prompt.chars().count();
+    for path in reference_documents {
+        chars += read_reference_document(path)?.chars().count();
+    }
+    Ok(chars.div_ceil(4) as u64)
+}
+
+/// Halves the reference documents recursively until the prompt with each chunk is estimated
+/// to fit into `max_tokens`. A single document that is still too large becomes its own chunk.
+pub fn split_reference_documents<'a>(
+    prompt: &str,
+    reference_documents: &'a [String],
+    max_tokens: u64,
+) -> Result<Vec<&'a [String]>> {
+    let document_chars = reference_documents
+        .iter()
+        .map(|path| Ok(read_reference_document(path)?.chars().count()))
+        .collect::<Result<Vec<_>>>()?;
+    let mut chunks = Vec::new();
+    collect_reference_chunks(
+        reference_documents,
+        &document_chars,
+        prompt.chars().count(),
+        max_tokens,
+        &mut chunks,
+    );
+    Ok(chunks)
+}
+
+fn collect_reference_chunks<'a>(
+    documents: &'a [String],
+    document_chars: &[usize],
+    prompt_chars: usize,
+    max_tokens: u64,
+    chunks: &mut Vec<&'a [String]>,
+) {
+    let tokens = (prompt_chars + document_chars.iter().sum::<usize>()).div_ceil(4) as u64;
+    if documents.len() < 2 || tokens <= max_tokens {
+        chunks.push(documents);
+        return;
+    }
+    let middle = documents.len() / 2;
+    collect_reference_chunks(
+        &documents[..middle],
+        &document_chars[..middle],
+        prompt_chars,
+        max_tokens,
+        chunks,
+    );
+    collect_reference_chunks(
+        &documents[middle..],
+        &document_chars[middle..],
+        prompt_chars,
+        max_tokens,
+        chunks,
+    );
+}
+
+pub async fn generate_with_gemini_internal<F, G>(
+    options: &GenerationOptions<'_>,
+    settings: &AppSettings,
+    gemini: &GeminiClient,
+    cached_content: Option<&str>,
     emit_progress: F,
+    on_model_fallback: G,
 ) -> Result<String>
 where
     F: Fn(String),
+    G: Fn(ModelFallback),
 {
+    let GenerationOptions {
+        file_uris,
+        sampled_frames,
+        custom_prompt,
+        embed_images,
+        structured_output,
+        search_grounding,
+        reference_documents,
+    } = *options;
     println!("🤖 [GENERATE] Starting document generation with Gemini API");
     println!(
         "📋 [GENERATE] Language: {}, Files: {}",
-        language,
+        settings.language.as_str(),
         file_uris.len()
     );
     emit_progress("AIによるドキュメント生成を準備中...".to_string());
-    let client = reqwest::Client::new();
+    let client = gemini.generation_http_client();
 
-    let prompt = if let Some(custom) = custom_prompt {
-        let mut final_prompt = custom.to_string();
-        if embed_images {
-            let image_instruction = get_image_instruction(image_embed_frequency);
-            final_prompt.push_str(&image_instruction);
-        }
-        final_prompt
+    let prompt = document_prompt(
+        &settings.language,
+        custom_prompt,
+        embed_images,
+        &settings.image_embed_frequency,
+        &settings.document_numbering_style,
+        settings.max_heading_depth,
+    );
+
+    let prompt = if settings.preferred_code_languages.is_empty() {
+        prompt
     } else {
-        let language_instruction = match language {
-            "english" => "Please write the document in English",
-            "japanese" | _ => "Please write the document in Japanese",
-        };
+        format!(
+            "{}\n\nUse lowercase language identifiers in all code blocks.",
+            prompt
+        )
+    };
 
-        let mut base_prompt = format!("Please analyze the uploaded video(s) and create a comprehensive document based on the content. The document should include:
-        
-        1. Overview of the content
-        2. Key points and important information
-        3. Step-by-step instructions or procedures if applicable
-        4. Technical details and specifications
-        5. Any relevant notes or recommendations
-        
-        {} and format it in a clear, professional manner.", language_instruction);
+    let prompt = if structured_output {
+        format!(
+            "{}\n\nReturn the document as JSON following the provided schema. \
+            Put screenshot timestamps (MM:SS format) for each section into its `screenshots` array instead of inline references.",
+            prompt
+        )
+    } else {
+        prompt
+    };
 
-        if embed_images {
-            let image_instruction = get_image_instruction(image_embed_frequency);
-            base_prompt.push_str(&image_instruction);
-        }
+    let mut parts = Vec::new();
 
-        base_prompt
-    };
+    // Reference documents for style and terminology come before the prompt
+    if !reference_documents.is_empty() {
+        emit_progress("参照ドキュメントを読み込み中...".to_string());
+        let mut remaining_chars = MAX_REFERENCE_DOCUMENT_CHARS;
+        for path in reference_documents {
+            if remaining_chars == 0 {
+                break;
+            }
+            let content = read_reference_document(path)?;
+            let char_count = content.chars().count();
+            let content = if char_count > remaining_chars {
+                println!(
+                    "⚠️ [GENERATE] Reference documents exceed {} characters, truncating {}",
+                    MAX_REFERENCE_DOCUMENT_CHARS, path
+                );
+                emit_progress(format!(
+                    "警告: 参照ドキュメントが{}文字を超えたため切り詰めました",
+                    MAX_REFERENCE_DOCUMENT_CHARS
+                ));
+                content.chars().take(remaining_chars).collect::<String>()
+            } else {
+                content
+            };
+            remaining_chars -= content.chars().count();
+            parts.push(GeminiPart::Text {
+                text: format!("Reference document for style and terminology:\n{}", content),
+            });
+        }
+    }
 
-    let mut parts = vec![GeminiPart::Text {
+    parts.push(GeminiPart::Text {
         text: prompt.to_string(),
-    }];
+    });
 
     for uri in file_uris {
         parts.push(GeminiPart::FileData {
@@ -395,41 +1259,200 @@ where
         });
     }
 
-    let request = GeminiRequest {
+    // Sampled frames are sent inline, each labelled with its timestamp so screenshot references still work
+    if !sampled_frames.is_empty() {
+        emit_progress(format!(
+            "抽出したフレームを読み込み中 ({}枚)...",
+            sampled_frames.len()
+        ));
+        let mut inline_bytes = 0;
+        for frame in sampled_frames {
+            let image = fs::read(&frame.path).map_err(|e| {
+                anyhow::anyhow!("Failed to read frame {}: {}", frame.path.display(), e)
+            })?;
+            inline_bytes += image.len();
+            if inline_bytes > MAX_INLINE_DATA_BYTES {
+                return Err(anyhow::anyhow!(
+                    "Sampled frames exceed the {} MB inline request limit, lower video_sampling_fps",
+                    MAX_INLINE_DATA_BYTES / 1024 / 1024
+                ));
+            }
+            let seconds = frame.timestamp_seconds.round() as u64;
+            parts.push(GeminiPart::Text {
+                text: format!("Frame at {:02}:{:02}", seconds / 60, seconds % 60),
+            });
+            parts.push(GeminiPart::InlineData {
+                inline_data: GeminiInlineData {
+                    mime_type: "image/jpeg".to_string(),
+                    data: general_purpose::STANDARD.encode(&image),
+                },
+            });
+        }
+    }
+
+    // Thinking mode is only supported by Gemini 2.5 models
+    let thinking_budget = settings
+        .thinking_budget
+        .filter(|_| settings.gemini_model.contains("2.5"));
+
+    let mut request = GeminiRequest {
         contents: vec![GeminiContent { parts }],
-        generation_config: if temperature > 0.0 {
+        generation_config: if settings.temperature > 0.0
+            || settings.top_p.is_some()
+            || settings.top_k.is_some()
+            || structured_output
+            || thinking_budget.is_some()
+        {
             Some(GeminiGenerationConfig {
-                temperature: Some(temperature),
+                temperature: if settings.temperature > 0.0 {
+                    Some(settings.temperature)
+                } else {
+                    None
+                },
+                response_mime_type: if structured_output {
+                    Some("application/json".to_string())
+                } else {
+                    None
+                },
+                response_schema: if structured_output {
+                    Some(document_output_schema())
+                } else {
+                    None
+                },
+                top_p: settings.top_p,
+                top_k: settings.top_k,
+                thinking_config: thinking_budget
+                    .map(|thinking_budget| GeminiThinkingConfig { thinking_budget }),
             })
         } else {
             None
         },
+        tools: if search_grounding {
+            Some(vec![serde_json::json!({ "google_search": {} })])
+        } else {
+            None
+        },
+        safety_settings: request_safety_settings(&settings.safety_settings),
+        cached_content: None,
     };
+    // A cache is bound to the model it was created for, so fallback models get the full request
+    let mut uncached_request = cached_content.map(|_| request.clone());
+    if let Some(cache_name) = cached_content {
+        request = request.with_cached_content(cache_name.to_string());
+    }
 
     println!("🌐 [GENERATE] Sending request to Gemini API...");
     emit_progress("Gemini AIにドキュメント生成を依頼中...".to_string());
-    let response = client
-        .post(format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-            model, api_key
-        ))
-        .json(&request)
-        .send()
-        .await?;
+
+    // Rate limited, unavailable or unknown models move on to the next model of the fallback chain
+    // with the same request, so the generation config is preserved
+    let mut current_model = settings.gemini_model.clone();
+    let mut fallback_models = settings.model_fallback_chain.iter();
+    let (generate_url, mut response) = loop {
+        let generate_url = format!(
+            "{}?key={}",
+            gemini.api_url(
+                &settings.gemini_api_version,
+                &format!("models/{}:generateContent", current_model)
+            ),
+            gemini.api_key
+        );
+        let response = client.post(&generate_url).json(&request).send().await?;
+        let status = response.status();
+        let can_fall_back = status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            || status == reqwest::StatusCode::SERVICE_UNAVAILABLE
+            || status == reqwest::StatusCode::NOT_FOUND;
+        if !can_fall_back || settings.model_fallback_chain.is_empty() {
+            break (generate_url, response);
+        }
+
+        let Some(fallback_model) = fallback_models.next() else {
+            let api_error = parse_gemini_error(&response.text().await?);
+            println!(
+                "❌ [GENERATE] All models in fallback chain failed: {}",
+                api_error
+            );
+            emit_progress(format!("エラー: AI生成に失敗しました - {}", api_error));
+            return Err(anyhow::anyhow!(
+                "All models in fallback chain failed. API request failed: {}",
+                api_error
+            ));
+        };
+        let reason = format!(
+            "{} {}",
+            status.as_u16(),
+            status.canonical_reason().unwrap_or_default()
+        );
+        println!(
+            "🔁 [GENERATE] {} failed with {}, falling back to {}",
+            current_model, reason, fallback_model
+        );
+        emit_progress(format!(
+            "{} が利用できないため {} で再試行中...",
+            current_model, fallback_model
+        ));
+        on_model_fallback(ModelFallback {
+            original_model: current_model.clone(),
+            fallback_model: fallback_model.clone(),
+            reason,
+        });
+        current_model = fallback_model.clone();
+        if let Some(full_request) = uncached_request.take() {
+            request = full_request;
+        }
+    };
+
+    // Retry without the thinking config if the model rejects it
+    if response.status() == reqwest::StatusCode::BAD_REQUEST && thinking_budget.is_some() {
+        let api_error = parse_gemini_error(&response.text().await?);
+        if api_error.message.contains("thinkingConfig")
+            || api_error.message.contains("thinking_config")
+        {
+            println!("⚠️ [GENERATE] Model does not support thinking mode, retrying without it");
+            if let Some(config) = request.generation_config.as_mut() {
+                config.thinking_config = None;
+            }
+            response = client.post(&generate_url).json(&request).send().await?;
+        } else {
+            println!("❌ [GENERATE] API request failed: {}", api_error);
+            emit_progress(format!("エラー: AI生成に失敗しました - {}", api_error));
+            return Err(anyhow::anyhow!("API request failed: {}", api_error));
+        }
+    }
 
     if response.status().is_success() {
         println!("✅ [GENERATE] Received successful response from Gemini API");
         emit_progress("AIの応答を受信中...".to_string());
         let gemini_response: GeminiResponse = response.json().await?;
+        if let Some(input_tokens) = gemini_response
+            .usage_metadata
+            .as_ref()
+            .and_then(|usage| usage.prompt_token_count)
+        {
+            println!("🔢 [GENERATE] Input tokens used: {}", input_tokens);
+        }
         if let Some(candidate) = gemini_response.candidates.first() {
             if let Some(part) = candidate.content.parts.first() {
                 if let GeminiPart::Text { text } = part {
+                    let text = if structured_output {
+                        let document: StructuredDocument =
+                            serde_json::from_str(text).map_err(|e| {
+                                anyhow::anyhow!("Failed to parse structured response: {}", e)
+                            })?;
+                        render_structured_document(&document)
+                    } else {
+                        text.clone()
+                    };
+                    let text = match &candidate.grounding_metadata {
+                        Some(metadata) => append_grounding_references(text, metadata),
+                        None => text,
+                    };
                     println!(
                         "📝 [GENERATE] Generated document length: {} characters",
                         text.len()
                     );
                     emit_progress(format!("ドキュメント生成完了！ ({}文字)", text.len()));
-                    return Ok(text.clone());
+                    return Ok(text);
                 }
             }
         }
@@ -437,23 +1460,54 @@ where
         emit_progress("エラー: AIの応答にテキストが含まれていません".to_string());
         Err(anyhow::anyhow!("No text content in response"))
     } else {
-        let error_text = response.text().await?;
-        println!("❌ [GENERATE] API request failed: {}", error_text);
-        emit_progress(format!("エラー: AI生成に失敗しました - {}", error_text));
-        Err(anyhow::anyhow!("API request failed: {}", error_text))
+        let api_error = parse_gemini_error(&response.text().await?);
+        println!("❌ [GENERATE] API request failed: {}", api_error);
+        emit_progress(format!("エラー: AI生成に失敗しました - {}", api_error));
+        Err(anyhow::anyhow!("API request failed: {}", api_error))
+    }
+}
+
+/// Appends the web sources used by search grounding as a "References" section
+fn append_grounding_references(document: String, metadata: &GeminiGroundingMetadata) -> String {
+    let mut seen_uris = std::collections::HashSet::new();
+    let references: Vec<String> = metadata
+        .grounding_chunks
+        .iter()
+        .filter_map(|chunk| chunk.web.as_ref())
+        .filter(|web| seen_uris.insert(web.uri.clone()))
+        .enumerate()
+        .map(|(index, web)| {
+            let title = web.title.as_deref().unwrap_or(&web.uri);
+            format!("{}. [{}]({})", index + 1, title, web.uri)
+        })
+        .collect();
+
+    if references.is_empty() {
+        return document;
     }
+
+    format!(
+        "{}\n\n## References\n\n{}\n",
+        document.trim_end(),
+        references.join("\n")
+    )
 }
 
 pub async fn integrate_documents(
     documents: &[String],
-    language: &str,
-    api_key: &str,
+    language: &DocumentLanguage,
+    gemini: &GeminiClient,
     temperature: f64,
     custom_prompt: Option<&str>,
     model: &str,
+    api_version: &GeminiApiVersion,
+    safety_settings: &[GeminiSafetySetting],
+    overlap_seconds: f64,
+    emit_progress: impl Fn(String),
 ) -> Result<String> {
-    let client = reqwest::Client::new();
+    let client = gemini.generation_http_client();
 
+    emit_progress("統合プロンプトを構築中...".to_string());
     let integration_prompt = if let Some(custom) = custom_prompt {
         format!(
             "{}\n\n=== Documents to integrate ===\n{}",
@@ -466,10 +1520,7 @@ pub async fn integrate_documents(
                 .join("\n")
         )
     } else {
-        let language_instruction = match language {
-            "english" => "Please write the integrated document in English",
-            "japanese" | _ => "ドキュメントは全て日本語で記述してください",
-        };
+        let language_instruction = integration_language_instruction(language);
 
         format!(
             "Please integrate the following documents into one comprehensive, cohesive document. \
@@ -482,7 +1533,16 @@ pub async fn integrate_documents(
                 .join("\n")
         )
     };
+    let integration_prompt = if overlap_seconds > 0.0 {
+        format!(
+            "{}\n\nNote: consecutive documents overlap by approximately {} seconds at their boundaries.",
+            integration_prompt, overlap_seconds
+        )
+    } else {
+        integration_prompt
+    };
 
+    let total_chars: usize = documents.iter().map(|doc| doc.chars().count()).sum();
     let request = GeminiRequest {
         contents: vec![GeminiContent {
             parts: vec![GeminiPart::Text {
@@ -492,22 +1552,37 @@ pub async fn integrate_documents(
         generation_config: if temperature > 0.0 {
             Some(GeminiGenerationConfig {
                 temperature: Some(temperature),
+                top_p: None,
+                top_k: None,
+                response_mime_type: None,
+                response_schema: None,
+                thinking_config: None,
             })
         } else {
             None
         },
+        tools: None,
+        safety_settings: request_safety_settings(safety_settings),
+        cached_content: None,
     };
 
+    emit_progress(format!(
+        "統合リクエストをGemini APIに送信中 (合計 {} ドキュメント, {} 文字)...",
+        documents.len(),
+        total_chars
+    ));
     let response = client
         .post(format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-            model, api_key
+            "{}?key={}",
+            gemini.api_url(api_version, &format!("models/{}:generateContent", model)),
+            gemini.api_key
         ))
         .json(&request)
         .send()
         .await?;
 
     if response.status().is_success() {
+        emit_progress("統合レスポンスを受信中...".to_string());
         let gemini_response: GeminiResponse = response.json().await?;
         if let Some(candidate) = gemini_response.candidates.first() {
             if let Some(part) = candidate.content.parts.first() {
@@ -518,229 +1593,1473 @@ pub async fn integrate_documents(
         }
         Err(anyhow::anyhow!("No text content in integration response"))
     } else {
-        let error_text = response.text().await?;
+        let api_error = parse_gemini_error(&response.text().await?);
         Err(anyhow::anyhow!(
             "Document integration failed: {}",
-            error_text
+            api_error
         ))
     }
 }
 
-pub fn get_mime_type(file_path: &str) -> String {
-    let extension = std::path::Path::new(file_path)
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("");
+fn integration_language_instruction(language: &DocumentLanguage) -> String {
+    match language {
+        DocumentLanguage::Japanese => "ドキュメントは全て日本語で記述してください".to_string(),
+        other => format!(
+            "Please write the integrated document in {}",
+            other.display_name()
+        ),
+    }
+}
 
-    match extension.to_lowercase().as_str() {
-        "mp4" => "video/mp4",
-        "mov" => "video/quicktime",
-        "avi" => "video/x-msvideo",
-        "mkv" => "video/x-matroska",
-        "wmv" => "video/x-ms-wmv",
-        "flv" => "video/x-flv",
-        "webm" => "video/webm",
-        "3gp" => "video/3gpp",
-        "mpg" | "mpeg" => "video/mpeg",
-        _ => "video/mp4", // Default
+/// Name of the integration strategy shown in progress messages
+pub fn integration_strategy_name(strategy: &IntegrationStrategy) -> &'static str {
+    match strategy {
+        IntegrationStrategy::Sequential => "一括統合",
+        IntegrationStrategy::Hierarchical => "段階的統合",
+        IntegrationStrategy::SummaryFirst => "要約優先統合",
     }
-    .to_string()
 }
 
-/// Generates image instruction based on embedding frequency
-fn get_image_instruction(frequency: &ImageEmbedFrequency) -> String {
-    match frequency {
-        ImageEmbedFrequency::Minimal => {
-            "\n\nIMPORTANT: When describing the most critical visual elements or key points in the document, please include screenshot references using this exact format: [Screenshot: XX:XXs] where XX:XX is the timestamp in MM:SS format (e.g., [Screenshot: 00:14s], [Screenshot: 01:23s]). Use these references sparingly, only for the most important moments that are essential for understanding.".to_string()
-        },
-        ImageEmbedFrequency::Moderate => {
-            "\n\nIMPORTANT: When describing visual elements or important points in the document, please include screenshot references using this exact format: [Screenshot: XX:XXs] where XX:XX is the timestamp in MM:SS format (e.g., [Screenshot: 00:14s], [Screenshot: 01:23s]). Use these references to mark key moments that would benefit from visual representation.".to_string()
-        },
-        ImageEmbedFrequency::Detailed => {
-            "\n\nIMPORTANT: When describing visual elements, UI components, or detailed explanations in the document, please include screenshot references using this exact format: [Screenshot: XX:XXs] where XX:XX is the timestamp in MM:SS format (e.g., [Screenshot: 00:14s], [Screenshot: 01:23s]). Use these references frequently to provide detailed visual context for readers.".to_string()
+/// Combines segment documents with the selected strategy
+pub async fn integrate_documents_with_strategy(
+    strategy: &IntegrationStrategy,
+    documents: &[String],
+    language: &DocumentLanguage,
+    gemini: &GeminiClient,
+    temperature: f64,
+    custom_prompt: Option<&str>,
+    model: &str,
+    api_version: &GeminiApiVersion,
+    safety_settings: &[GeminiSafetySetting],
+    overlap_seconds: f64,
+    emit_progress: impl Fn(String),
+) -> Result<String> {
+    match strategy {
+        IntegrationStrategy::Sequential => {
+            integrate_documents(
+                documents,
+                language,
+                gemini,
+                temperature,
+                custom_prompt,
+                model,
+                api_version,
+                safety_settings,
+                overlap_seconds,
+                emit_progress,
+            )
+            .await
+        }
+        IntegrationStrategy::Hierarchical => {
+            integrate_documents_hierarchical(
+                documents,
+                language,
+                gemini,
+                temperature,
+                custom_prompt,
+                model,
+                api_version,
+                safety_settings,
+                overlap_seconds,
+                emit_progress,
+            )
+            .await
+        }
+        IntegrationStrategy::SummaryFirst => {
+            integrate_documents_summary_first(
+                documents,
+                language,
+                gemini,
+                temperature,
+                custom_prompt,
+                model,
+                api_version,
+                safety_settings,
+                overlap_seconds,
+                emit_progress,
+            )
+            .await
         }
     }
 }
 
-/// Parses timestamp string in various formats (MM:SS or SS.SS)
-fn parse_timestamp(timestamp_str: &str) -> f64 {
-    if timestamp_str.contains(':') {
-        // Format: MM:SS or MM:SS.SS
-        let parts: Vec<&str> = timestamp_str.split(':').collect();
-        if parts.len() == 2 {
-            let minutes = parts[0].parse::<f64>().unwrap_or(0.0);
-            let seconds = parts[1].parse::<f64>().unwrap_or(0.0);
-            return minutes * 60.0 + seconds;
+/// Integrates neighbouring pairs of documents round by round (like a merge sort)
+/// so that a single request never contains more than two documents
+pub async fn integrate_documents_hierarchical(
+    documents: &[String],
+    language: &DocumentLanguage,
+    gemini: &GeminiClient,
+    temperature: f64,
+    custom_prompt: Option<&str>,
+    model: &str,
+    api_version: &GeminiApiVersion,
+    safety_settings: &[GeminiSafetySetting],
+    overlap_seconds: f64,
+    emit_progress: impl Fn(String),
+) -> Result<String> {
+    let mut current = documents.to_vec();
+    let mut round = 1;
+
+    while current.len() > 1 {
+        println!(
+            "🔗 [INTEGRATE] Hierarchical round {}: {} documents",
+            round,
+            current.len()
+        );
+        let mut next = Vec::with_capacity(current.len().div_ceil(2));
+        for pair in current.chunks(2) {
+            if pair.len() == 1 {
+                next.push(pair[0].clone());
+                continue;
+            }
+            next.push(
+                integrate_documents(
+                    pair,
+                    language,
+                    gemini,
+                    temperature,
+                    custom_prompt,
+                    model,
+                    api_version,
+                    safety_settings,
+                    overlap_seconds,
+                    &emit_progress,
+                )
+                .await?,
+            );
         }
-    } else {
-        // Format: SS.SS
-        return timestamp_str.parse::<f64>().unwrap_or(0.0);
+        current = next;
+        round += 1;
     }
-    0.0
+
+    current
+        .pop()
+        .ok_or_else(|| anyhow::anyhow!("No documents to integrate"))
 }
 
-/// Processes the generated document to extract screenshot placeholders and replace them with images
-pub async fn process_document_with_images(
-    document: &str,
-    video_files: &[String],
-    output_directory: &str,
-    _image_embed_frequency: &ImageEmbedFrequency,
-    settings: &AppSettings,
+/// Summarises each segment, builds an outline from the summaries and then writes
+/// every outline section from only the segments it references
+pub async fn integrate_documents_summary_first(
+    documents: &[String],
+    language: &DocumentLanguage,
+    gemini: &GeminiClient,
+    temperature: f64,
+    custom_prompt: Option<&str>,
+    model: &str,
+    api_version: &GeminiApiVersion,
+    safety_settings: &[GeminiSafetySetting],
+    overlap_seconds: f64,
+    emit_progress: impl Fn(String),
 ) -> Result<String> {
-    // Create images directory
-    let images_dir = Path::new(output_directory).join("images");
-    if !images_dir.exists() {
-        fs::create_dir_all(&images_dir)?;
+    let language_instruction = integration_language_instruction(language);
+    let overlap_note = if overlap_seconds > 0.0 {
+        format!(
+            "\n\nNote: consecutive segments overlap by approximately {} seconds at their boundaries, so do not repeat content from the overlap.",
+            overlap_seconds
+        )
+    } else {
+        String::new()
+    };
+
+    let mut summaries = Vec::with_capacity(documents.len());
+    for (i, document) in documents.iter().enumerate() {
+        println!(
+            "📋 [INTEGRATE] Summarising segment {}/{}",
+            i + 1,
+            documents.len()
+        );
+        emit_progress(format!(
+            "セグメントを要約中 ({}/{})...",
+            i + 1,
+            documents.len()
+        ));
+        let summary_prompt = format!(
+            "Summarise the following document segment in one paragraph. \
+            Mention every topic it covers so that it can be found again later:\n\n{}",
+            document
+        );
+        let summary = generate_text(
+            "Document integration",
+            vec![GeminiPart::Text {
+                text: summary_prompt,
+            }],
+            temperature_config(temperature),
+            gemini,
+            model,
+            api_version,
+            safety_settings,
+        )
+        .await?;
+        summaries.push(format!("Segment {}: {}", i + 1, summary.trim()));
     }
 
-    // Extract screenshot placeholders using regex
-    // Updated to handle formats like [Screenshot: 00:14s] and [Screenshot: 123.45s]
-    let re = Regex::new(r"\[Screenshot:\s*(\d{1,2}:\d{2}(?:\.\d+)?|\d+(?:\.\d+)?)\s*s\]").unwrap();
-    let mut processed_document = document.to_string();
-    let mut image_counter = 1;
+    emit_progress("統合ドキュメントの構成を作成中...".to_string());
+    let outline_prompt = format!(
+        "The following are summaries of consecutive segments of one recording. \
+        Create the outline of a single integrated document as a list of level-2 Markdown headings (## Heading). \
+        End every heading line with the segments it draws from, in the form (Segments: 1, 3). \
+        Do not write any other text. {}:{}\n\n{}",
+        language_instruction,
+        overlap_note,
+        summaries.join("\n\n")
+    );
+    let outline = generate_text(
+        "Document integration",
+        vec![GeminiPart::Text {
+            text: outline_prompt,
+        }],
+        temperature_config(temperature),
+        gemini,
+        model,
+        api_version,
+        safety_settings,
+    )
+    .await?;
 
-    // Collect all matches first to avoid borrowing issues
-    let matches: Vec<(String, f64)> = re
-        .captures_iter(document)
+    let heading_regex = Regex::new(r"^##\s+(.+?)\s*\(Segments?:\s*([\d,\s]+)\)\s*$").unwrap();
+    let sections: Vec<(String, Vec<usize>)> = outline
+        .lines()
+        .filter_map(|line| heading_regex.captures(line.trim()))
         .map(|caps| {
-            let full_match = caps[0].to_string();
-            let timestamp_str = &caps[1];
-            let timestamp = parse_timestamp(timestamp_str);
-            (full_match, timestamp)
+            let segment_numbers = caps[2]
+                .split(',')
+                .filter_map(|n| n.trim().parse::<usize>().ok())
+                .filter(|n| (1..=documents.len()).contains(n))
+                .collect();
+            (caps[1].trim().to_string(), segment_numbers)
         })
         .collect();
 
-    println!(
-        "📊 [IMAGE] Found {} screenshot references to process",
-        matches.len()
-    );
+    if sections.is_empty() {
+        return Err(anyhow::anyhow!("Integration outline has no sections"));
+    }
 
-    // Get video durations to help determine which video contains the timestamp
-    let mut video_durations = Vec::new();
-    for video_path in video_files {
-        match crate::video::get_video_duration(video_path).await {
-            Ok(duration) => video_durations.push(duration),
-            Err(e) => {
-                println!("⚠️ Failed to get duration for {}: {}", video_path, e);
-                video_durations.push(f64::INFINITY); // Assume infinite duration if we can't get it
-            }
-        }
+    let mut expanded_sections = Vec::with_capacity(sections.len());
+    for (i, (heading, segment_numbers)) in sections.iter().enumerate() {
+        println!(
+            "📝 [INTEGRATE] Expanding section {}/{}: {}",
+            i + 1,
+            sections.len(),
+            heading
+        );
+        emit_progress(format!(
+            "セクションを作成中 ({}/{}): {}",
+            i + 1,
+            sections.len(),
+            heading
+        ));
+        // Sections without references are written from the summaries only
+        let sources = if segment_numbers.is_empty() {
+            summaries.join("\n\n")
+        } else {
+            segment_numbers
+                .iter()
+                .map(|n| format!("=== Document {} ===\n{}\n", n, documents[n - 1]))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let additional_instructions = custom_prompt
+            .map(|prompt| {
+                format!(
+                    "\n\nFollow these instructions for style and structure:\n{}",
+                    prompt
+                )
+            })
+            .unwrap_or_default();
+        let section_prompt = format!(
+            "Write the section \"{}\" of an integrated document with the outline below. \
+            Start with the heading \"## {}\", use only the source documents given and do not repeat content that belongs to other sections. {}.{}{}\n\n\
+            === Outline ===\n{}\n\n=== Source documents ===\n{}",
+            heading,
+            heading,
+            language_instruction,
+            additional_instructions,
+            overlap_note,
+            outline,
+            sources
+        );
+        let section = generate_text(
+            "Document integration",
+            vec![GeminiPart::Text {
+                text: section_prompt,
+            }],
+            temperature_config(temperature),
+            gemini,
+            model,
+            api_version,
+            safety_settings,
+        )
+        .await?;
+        expanded_sections.push(section.trim().to_string());
     }
 
-    for (placeholder, timestamp) in matches {
-        let mut frame_extracted = false;
+    Ok(expanded_sections.join("\n\n"))
+}
 
-        // First, try to find the most appropriate video based on timestamp and duration
-        let mut video_candidates: Vec<(usize, &String)> = video_files
-            .iter()
-            .enumerate()
-            .filter(|(i, _)| timestamp <= video_durations[*i])
-            .collect();
+/// Sends a single `generateContent` request and returns the text of the first candidate.
+/// `task` names the request in error messages.
+async fn generate_text(
+    task: &str,
+    parts: Vec<GeminiPart>,
+    generation_config: Option<GeminiGenerationConfig>,
+    gemini: &GeminiClient,
+    model: &str,
+    api_version: &GeminiApiVersion,
+    safety_settings: &[GeminiSafetySetting],
+) -> Result<String> {
+    let client = gemini.generation_http_client();
 
-        // If no video can contain this timestamp, try all videos as fallback
-        if video_candidates.is_empty() {
-            video_candidates = video_files.iter().enumerate().collect();
-        }
-
-        // Try to extract frame from candidate videos
-        for (video_index, video_path) in video_candidates {
-            let video_no = video_index + 1; // 1-based indexing
-                                            // Replace decimal point with underscore for filename compatibility
-            let timestamp_str = timestamp.to_string().replace('.', "_");
-            let image_filename = format!("image-{}-{}s.png", video_no, timestamp_str);
-            let image_path = images_dir.join(&image_filename);
-
-            // Extract frame from video using the selected method
-            let extraction_result = if settings.enable_experimental_features {
-                match settings.frame_extraction_method {
-                    FrameExtractionMethod::Fast => {
-                        crate::video::extract_frame_fast(
-                            video_path,
-                            timestamp,
-                            image_path.to_str().unwrap(),
-                        )
-                        .await
-                    }
-                    FrameExtractionMethod::Multiple => {
-                        // For single frame extraction, use multiple method with single timestamp
-                        match crate::video::extract_multiple_frames_from_video(
-                            video_path,
-                            &[timestamp],
-                            images_dir.to_str().unwrap(),
-                            &format!("video-{}-{}s", video_no, timestamp_str),
-                        )
-                        .await
-                        {
-                            Ok(paths) if !paths.is_empty() => {
-                                // If successful, move the generated file to the expected location
-                                if let Some(generated_path) = paths.first() {
-                                    if Path::new(generated_path).exists()
-                                        && generated_path != image_path.to_str().unwrap()
-                                    {
-                                        if let Err(e) =
-                                            fs::rename(generated_path, image_path.to_str().unwrap())
-                                        {
-                                            println!("⚠️ Failed to move generated image: {}", e);
-                                        }
-                                    }
-                                }
-                                Ok(())
-                            }
-                            Ok(_) => Err(anyhow::anyhow!("No frames extracted")),
-                            Err(e) => Err(e),
-                        }
-                    }
-                    FrameExtractionMethod::Standard => {
-                        crate::video::extract_frame_from_video(
-                            video_path,
-                            timestamp,
-                            image_path.to_str().unwrap(),
-                        )
-                        .await
-                    }
-                }
-            } else {
-                crate::video::extract_frame_from_video(
-                    video_path,
-                    timestamp,
-                    image_path.to_str().unwrap(),
-                )
-                .await
-            };
+    let request = GeminiRequest {
+        contents: vec![GeminiContent { parts }],
+        generation_config,
+        tools: None,
+        safety_settings: request_safety_settings(safety_settings),
+        cached_content: None,
+    };
 
-            match extraction_result {
-                Ok(_) => {
-                    let relative_image_path = format!("./images/{}", image_filename);
-                    let markdown_image =
-                        format!("![Screenshot {}]({})", image_counter, relative_image_path);
-                    processed_document = processed_document.replace(&placeholder, &markdown_image);
-                    image_counter += 1;
-                    frame_extracted = true;
-                    println!(
-                        "✅ Successfully extracted frame from video {} at {}s",
-                        video_no, timestamp
-                    );
-                    break; // Stop trying other videos once successful
+    let response = client
+        .post(format!(
+            "{}?key={}",
+            gemini.api_url(api_version, &format!("models/{}:generateContent", model)),
+            gemini.api_key
+        ))
+        .json(&request)
+        .send()
+        .await?;
+
+    if response.status().is_success() {
+        let gemini_response: GeminiResponse = response.json().await?;
+        if let Some(candidate) = gemini_response.candidates.first() {
+            if let Some(GeminiPart::Text { text }) = candidate.content.parts.first() {
+                return Ok(text.clone());
+            }
+        }
+        Err(anyhow::anyhow!(
+            "{} failed: no text content in response",
+            task
+        ))
+    } else {
+        let api_error = parse_gemini_error(&response.text().await?);
+        Err(anyhow::anyhow!("{} failed: {}", task, api_error))
+    }
+}
+
+/// Generation config that only sets the temperature, omitted when the model default is used
+fn temperature_config(temperature: f64) -> Option<GeminiGenerationConfig> {
+    (temperature > 0.0).then_some(GeminiGenerationConfig {
+        temperature: Some(temperature),
+        top_p: None,
+        top_k: None,
+        response_mime_type: None,
+        response_schema: None,
+        thinking_config: None,
+    })
+}
+
+/// JSON schema for structured document output (DocumentOutputSchema)
+pub fn document_output_schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "OBJECT",
+        "properties": {
+            "title": { "type": "STRING" },
+            "sections": {
+                "type": "ARRAY",
+                "items": {
+                    "type": "OBJECT",
+                    "properties": {
+                        "heading": { "type": "STRING" },
+                        "content": { "type": "STRING" },
+                        "screenshots": {
+                            "type": "ARRAY",
+                            "items": { "type": "STRING" }
+                        }
+                    },
+                    "required": ["heading", "content"]
                 }
-                Err(e) => {
+            },
+            "summary": { "type": "STRING" }
+        },
+        "required": ["title", "sections", "summary"]
+    })
+}
+
+/// Renders a structured document back to Markdown.
+/// Screenshots are rendered as placeholders so that image extraction still works.
+pub fn render_structured_document(document: &StructuredDocument) -> String {
+    let mut markdown = format!("# {}\n\n", document.title);
+
+    for section in &document.sections {
+        markdown.push_str(&format!(
+            "## {}\n\n{}\n\n",
+            section.heading, section.content
+        ));
+        for timestamp in &section.screenshots {
+            markdown.push_str(&format!(
+                "[Screenshot: {}s]\n\n",
+                timestamp.trim().trim_end_matches('s')
+            ));
+        }
+    }
+
+    if !document.summary.is_empty() {
+        markdown.push_str(&format!("## Summary\n\n{}\n", document.summary));
+    }
+
+    markdown
+}
+
+/// Translates a generated Markdown document into the target language.
+/// Screenshot placeholders are kept verbatim so that image extraction still works.
+pub async fn translate_document(
+    document: &str,
+    target_language: &str,
+    gemini: &GeminiClient,
+    temperature: f64,
+    model: &str,
+    api_version: &GeminiApiVersion,
+    safety_settings: &[GeminiSafetySetting],
+) -> Result<String> {
+    let translation_prompt = format!(
+        "Please translate the following Markdown document to {}, preserving all Markdown formatting, code blocks, and image references verbatim. \
+        Do not translate or modify screenshot placeholders in the format [Screenshot: XX:XXs]. \
+        Output only the translated document:\n\n{}",
+        target_language, document
+    );
+
+    generate_text(
+        "Document translation",
+        vec![GeminiPart::Text {
+            text: translation_prompt,
+        }],
+        temperature_config(temperature),
+        gemini,
+        model,
+        api_version,
+        safety_settings,
+    )
+    .await
+}
+
+/// Asks Gemini for a one-page executive summary of the document
+pub async fn summarize_document(
+    document: &str,
+    gemini: &GeminiClient,
+    temperature: f64,
+    model: &str,
+    api_version: &GeminiApiVersion,
+    safety_settings: &[GeminiSafetySetting],
+) -> Result<String> {
+    let summary_prompt = format!(
+        "Please write a concise executive summary (maximum 500 words) of the following technical document, using the same language. \
+        Focus on purpose, key findings, and action items:\n\n{}",
+        document
+    );
+
+    let summary = generate_text(
+        "Summary generation",
+        vec![GeminiPart::Text {
+            text: summary_prompt,
+        }],
+        temperature_config(temperature),
+        gemini,
+        model,
+        api_version,
+        safety_settings,
+    )
+    .await?;
+    Ok(summary.trim().to_string())
+}
+
+/// Asks Gemini for a Markdown table of the technical terms used in the document
+pub async fn generate_glossary(
+    document: &str,
+    gemini: &GeminiClient,
+    model: &str,
+    language: &DocumentLanguage,
+    api_version: &GeminiApiVersion,
+    safety_settings: &[GeminiSafetySetting],
+) -> Result<String> {
+    let glossary_prompt = format!(
+        "From the following document, extract all technical terms and acronyms and provide a brief definition for each. \
+        Format as a Markdown table with columns 'Term' and 'Definition'. Respond in {}:\n\n{}",
+        language.display_name(),
+        document
+    );
+
+    let glossary = generate_text(
+        "Glossary generation",
+        vec![GeminiPart::Text {
+            text: glossary_prompt,
+        }],
+        None,
+        gemini,
+        model,
+        api_version,
+        safety_settings,
+    )
+    .await?;
+    Ok(glossary.trim().to_string())
+}
+
+/// Number of rows in a Markdown table, excluding the header and separator rows
+pub fn count_table_rows(table: &str) -> usize {
+    table
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with('|'))
+        .count()
+        .saturating_sub(2)
+}
+
+/// Asks Gemini for Anki-style flashcards covering the key concepts of the document
+pub async fn generate_flashcards_from_document(
+    document: &str,
+    gemini: &GeminiClient,
+    model: &str,
+    language: &DocumentLanguage,
+    api_version: &GeminiApiVersion,
+    safety_settings: &[GeminiSafetySetting],
+) -> Result<Vec<Flashcard>> {
+    let flashcard_prompt = format!(
+        "Extract key concepts from this document and produce 10-20 flashcard pairs in JSON array format: \
+        [{{\"front\":\"...\",\"back\":\"...\",\"tags\":[...]}}]. Write the flashcards in {}.\n\n{}",
+        language.display_name(),
+        document
+    );
+
+    let text = generate_text(
+        "Flashcard generation",
+        vec![GeminiPart::Text {
+            text: flashcard_prompt,
+        }],
+        Some(GeminiGenerationConfig {
+            temperature: None,
+            top_p: None,
+            top_k: None,
+            response_mime_type: Some("application/json".to_string()),
+            response_schema: None,
+            thinking_config: None,
+        }),
+        gemini,
+        model,
+        api_version,
+        safety_settings,
+    )
+    .await?;
+    serde_json::from_str(text.trim())
+        .map_err(|e| anyhow::anyhow!("Failed to parse flashcards: {}", e))
+}
+
+/// Describes an extracted screenshot in one sentence for use as Markdown alt text
+pub async fn generate_image_caption(
+    image_path: &str,
+    gemini: &GeminiClient,
+    model: &str,
+    api_version: &GeminiApiVersion,
+    safety_settings: &[GeminiSafetySetting],
+) -> Result<String> {
+    let image = fs::read(image_path)?;
+    let mime_type = match Path::new(image_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .as_deref()
+    {
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        _ => "image/png",
+    };
+
+    let parts = vec![
+        GeminiPart::Text {
+            text: IMAGE_CAPTION_PROMPT.to_string(),
+        },
+        GeminiPart::InlineData {
+            inline_data: GeminiInlineData {
+                mime_type: mime_type.to_string(),
+                data: general_purpose::STANDARD.encode(&image),
+            },
+        },
+    ];
+    let caption = generate_text(
+        "Caption generation",
+        parts,
+        None,
+        gemini,
+        model,
+        api_version,
+        safety_settings,
+    )
+    .await?;
+    Ok(sanitize_image_caption(&caption))
+}
+
+/// Keeps a caption on a single line and removes characters that break Markdown image syntax
+pub fn sanitize_image_caption(caption: &str) -> String {
+    caption
+        .chars()
+        .filter(|c| !matches!(c, '`' | '[' | ']'))
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Asks Gemini for a short title based on the beginning of the document
+pub async fn generate_document_title(
+    document_excerpt: &str,
+    gemini: &GeminiClient,
+    model: &str,
+    api_version: &GeminiApiVersion,
+    safety_settings: &[GeminiSafetySetting],
+) -> Result<String> {
+    let excerpt: String = document_excerpt.chars().take(TITLE_EXCERPT_CHARS).collect();
+    let title_prompt = format!(
+        "Generate a concise 5-10 word title for the following document excerpt. Return only the title, no punctuation:\n\n{}",
+        excerpt
+    );
+
+    let text = generate_text(
+        "Title generation",
+        vec![GeminiPart::Text { text: title_prompt }],
+        None,
+        gemini,
+        model,
+        api_version,
+        safety_settings,
+    )
+    .await?;
+
+    // Models sometimes still wrap the title in quotes or Markdown
+    let title = text
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or_default()
+        .trim()
+        .trim_start_matches('#')
+        .trim_matches(|c: char| c == '"' || c == '\'' || c == '*' || c == '.' || c.is_whitespace())
+        .to_string();
+    if title.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Title generation failed: no text content in response"
+        ));
+    }
+    Ok(title)
+}
+
+/// Reads the text content of a reference document (.md, .txt or .pdf)
+pub fn read_reference_document(path: &str) -> Result<String> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    match extension.as_str() {
+        "md" | "txt" => fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read reference document {}: {}", path, e)),
+        "pdf" => pdf_extract::extract_text(path)
+            .map_err(|e| anyhow::anyhow!("Failed to extract text from PDF {}: {}", path, e)),
+        _ => Err(anyhow::anyhow!(
+            "Unsupported reference document type: {}",
+            path
+        )),
+    }
+}
+
+/// File part for an uploaded local file, typed the same way as the upload itself
+pub async fn uploaded_file_data(
+    file_path: &str,
+    file_uri: &str,
+    timeouts: &ProcessTimeouts,
+) -> Result<GeminiFileData> {
+    Ok(GeminiFileData {
+        mime_type: detect_mime_type(file_path, timeouts).await?,
+        file_uri: file_uri.to_string(),
+    })
+}
+
+/// Detects the MIME type from the actual video codec using ffprobe.
+/// Falls back to the file extension if ffprobe fails or the codec is unknown.
+pub async fn detect_mime_type(file_path: &str, timeouts: &ProcessTimeouts) -> Result<String> {
+    match crate::video::get_video_codec(file_path, timeouts).await {
+        Ok(codec) => {
+            let mime_type = match codec.to_lowercase().as_str() {
+                "h264" | "avc" | "hevc" | "h265" | "av1" => Some("video/mp4"),
+                "vp9" => Some("video/webm"),
+                _ => None,
+            };
+            match mime_type {
+                Some(mime_type) => Ok(mime_type.to_string()),
+                None => {
                     println!(
-                        "⚠️ Failed to extract frame from video {} at {}s: {}",
-                        video_no, timestamp, e
+                        "⚠️ [UPLOAD] Unknown codec '{}', falling back to file extension",
+                        codec
                     );
-                    // Continue to try next video
+                    Ok(get_mime_type_from_extension(file_path))
                 }
             }
         }
+        Err(e) => {
+            println!(
+                "⚠️ [UPLOAD] Codec detection failed, falling back to file extension: {}",
+                e
+            );
+            Ok(get_mime_type_from_extension(file_path))
+        }
+    }
+}
+
+fn get_mime_type_from_extension(file_path: &str) -> String {
+    let extension = std::path::Path::new(file_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("");
+
+    match extension.to_lowercase().as_str() {
+        "mp4" => "video/mp4",
+        "mov" => "video/quicktime",
+        "avi" => "video/x-msvideo",
+        "mkv" => "video/x-matroska",
+        "wmv" => "video/x-ms-wmv",
+        "flv" => "video/x-flv",
+        "webm" => "video/webm",
+        "3gp" => "video/3gpp",
+        "mpg" | "mpeg" => "video/mpeg",
+        _ => "video/mp4", // Default
+    }
+    .to_string()
+}
+
+/// Safety setting overrides for a request, `None` keeps the API defaults
+fn request_safety_settings(
+    safety_settings: &[GeminiSafetySetting],
+) -> Option<Vec<GeminiSafetySetting>> {
+    if safety_settings.is_empty() {
+        None
+    } else {
+        Some(safety_settings.to_vec())
+    }
+}
+
+/// Prompt used when neither a format preset nor a custom prompt is selected
+pub fn default_document_prompt(language: &DocumentLanguage) -> String {
+    let language_instruction = document_language_instruction(language);
+
+    format!("Please analyze the uploaded video(s) and create a comprehensive document based on the content. The document should include:
+        
+        1. Overview of the content
+        2. Key points and important information
+        3. Step-by-step instructions or procedures if applicable
+        4. Technical details and specifications
+        5. Any relevant notes or recommendations
+        
+        {} and format it in a clear, professional manner.", language_instruction)
+}
+
+/// The generation prompt shared by all backends: the custom prompt (or the default one)
+/// followed by the screenshot, numbering and heading depth instructions
+pub fn document_prompt(
+    language: &DocumentLanguage,
+    custom_prompt: Option<&str>,
+    embed_images: bool,
+    image_embed_frequency: &ImageEmbedFrequency,
+    numbering_style: &NumberingStyle,
+    max_heading_depth: u8,
+) -> String {
+    let mut prompt = match custom_prompt {
+        Some(custom) => custom.to_string(),
+        None => default_document_prompt(language),
+    };
+    if embed_images {
+        prompt.push_str(&get_image_instruction(image_embed_frequency));
+    }
+
+    let prompt = match numbering_instruction(numbering_style) {
+        Some(instruction) => format!("{}\n\n{}", prompt, instruction),
+        None => prompt,
+    };
+
+    match heading_depth_instruction(max_heading_depth) {
+        Some(instruction) => format!("{}\n\n{}", prompt, instruction),
+        None => prompt,
+    }
+}
+
+/// Prompt for a changelog comparing an old and a new recording of the same software
+/// The old video is sent first and the new video second
+pub fn version_diff_prompt(language: &DocumentLanguage) -> String {
+    format!(
+        "The first video is the old version and the second video is the new version.\n\n\
+        Compare these two video recordings of the same software. \
+        Identify what has changed: new features, removed features, changed UI elements, updated workflows. \
+        Format as a changelog document with Added/Changed/Removed sections.\n\n{}.",
+        document_language_instruction(language)
+    )
+}
+
+/// Instruction telling Gemini which language to write the document in
+fn document_language_instruction(language: &DocumentLanguage) -> String {
+    match language {
+        DocumentLanguage::Japanese => "Please write the document in Japanese".to_string(),
+        DocumentLanguage::English => "Please write the document in English".to_string(),
+        DocumentLanguage::ChineseSimplified => {
+            "Please write the document in Simplified Chinese (简体中文)".to_string()
+        }
+        DocumentLanguage::ChineseTraditional => {
+            "Please write the document in Traditional Chinese (繁體中文)".to_string()
+        }
+        DocumentLanguage::Korean => "Please write the document in Korean (한국어)".to_string(),
+        DocumentLanguage::Spanish => "Please write the document in Spanish (Español)".to_string(),
+        DocumentLanguage::French => "Please write the document in French (Français)".to_string(),
+        DocumentLanguage::German => "Please write the document in German (Deutsch)".to_string(),
+        DocumentLanguage::Portuguese => {
+            "Please write the document in Portuguese (Português)".to_string()
+        }
+        DocumentLanguage::Custom(name) => format!("Please write the document in {}", name),
+    }
+}
 
-        // If no video could provide the frame, remove the placeholder
-        if !frame_extracted {
+/// Instruction limiting the heading levels (`None` when all six levels are allowed)
+fn heading_depth_instruction(max_heading_depth: u8) -> Option<String> {
+    match max_heading_depth {
+        0 => Some(
+            "Do not use Markdown headings. Write section titles as plain paragraphs.".to_string(),
+        ),
+        1..=5 => Some(format!(
+            "Do not use heading levels deeper than H{}",
+            max_heading_depth
+        )),
+        _ => None,
+    }
+}
+
+/// Instruction telling Gemini how to number sections (`None` leaves it to the model)
+fn numbering_instruction(style: &NumberingStyle) -> Option<String> {
+    let numerals = match style {
+        NumberingStyle::Automatic => return None,
+        NumberingStyle::None_ => {
+            return Some(
+                "Do not number sections or sub-sections. Use unnumbered headings throughout the document."
+                    .to_string(),
+            )
+        }
+        NumberingStyle::Arabic => "Arabic (1, 2, 3)",
+        NumberingStyle::AlphaLower => "lowercase alphabetic (a, b, c)",
+        NumberingStyle::AlphaUpper => "uppercase alphabetic (A, B, C)",
+        NumberingStyle::RomanLower => "lowercase Roman (i, ii, iii)",
+        NumberingStyle::RomanUpper => "uppercase Roman (I, II, III)",
+    };
+    Some(format!(
+        "Number all sections and sub-sections using {} numerals, and use the same style for numbered lists.",
+        numerals
+    ))
+}
+
+/// Generates image instruction based on embedding frequency
+fn get_image_instruction(frequency: &ImageEmbedFrequency) -> String {
+    match frequency {
+        ImageEmbedFrequency::Minimal => {
+            "\n\nIMPORTANT: When describing the most critical visual elements or key points in the document, please include screenshot references using this exact format: [Screenshot: XX:XXs] where XX:XX is the timestamp in MM:SS format (e.g., [Screenshot: 00:14s], [Screenshot: 01:23s]). Use these references sparingly, only for the most important moments that are essential for understanding.".to_string()
+        },
+        ImageEmbedFrequency::Moderate => {
+            "\n\nIMPORTANT: When describing visual elements or important points in the document, please include screenshot references using this exact format: [Screenshot: XX:XXs] where XX:XX is the timestamp in MM:SS format (e.g., [Screenshot: 00:14s], [Screenshot: 01:23s]). Use these references to mark key moments that would benefit from visual representation.".to_string()
+        },
+        ImageEmbedFrequency::Detailed => {
+            "\n\nIMPORTANT: When describing visual elements, UI components, or detailed explanations in the document, please include screenshot references using this exact format: [Screenshot: XX:XXs] where XX:XX is the timestamp in MM:SS format (e.g., [Screenshot: 00:14s], [Screenshot: 01:23s]). Use these references frequently to provide detailed visual context for readers.".to_string()
+        }
+    }
+}
+
+/// Converts a timestamp on the combined timeline of all videos into a position inside
+/// the video at `video_index` by subtracting the durations of the videos before it.
+/// Overlapping segments repeat the last `overlap_seconds` of the previous segment,
+/// so each of them starts that much earlier on the combined timeline.
+pub fn calculate_relative_timestamp(
+    absolute_ts: f64,
+    video_index: usize,
+    video_durations: &[f64],
+    overlap_seconds: f64,
+) -> f64 {
+    let preceding_duration: f64 = video_durations.iter().take(video_index).sum();
+    absolute_ts - (preceding_duration - overlap_seconds * video_index as f64)
+}
+
+/// Parses timestamp string in various formats (MM:SS or SS.SS)
+pub fn parse_timestamp(timestamp_str: &str) -> Result<f64, String> {
+    let error = || format!("Could not parse timestamp: {}", timestamp_str);
+    if timestamp_str.contains(':') {
+        // Format: MM:SS or MM:SS.SS
+        let parts: Vec<&str> = timestamp_str.split(':').collect();
+        if parts.len() == 2 {
+            let minutes = parts[0].parse::<f64>().map_err(|_| error())?;
+            let seconds = parts[1].parse::<f64>().map_err(|_| error())?;
+            return Ok(minutes * 60.0 + seconds);
+        }
+        Err(error())
+    } else {
+        // Format: SS.SS
+        timestamp_str.parse::<f64>().map_err(|_| error())
+    }
+}
+
+/// Processes the generated document to extract screenshot placeholders and replace them with images
+pub async fn process_document_with_images(
+    document: &str,
+    video_files: &[String],
+    output_directory: &str,
+    image_embed_frequency: &ImageEmbedFrequency,
+    settings: &AppSettings,
+    overlap_seconds: f64,
+) -> Result<String> {
+    // Create images directory
+    let images_dir = Path::new(output_directory).join("images");
+    if !images_dir.exists() {
+        fs::create_dir_all(&images_dir)?;
+    }
+
+    // Extract screenshot placeholders using regex
+    // Updated to handle formats like [Screenshot: 00:14s] and [Screenshot: 123.45s]
+    let re = Regex::new(r"\[Screenshot:\s*(\d{1,2}:\d{2}(?:\.\d+)?|\d+(?:\.\d+)?)\s*s\]").unwrap();
+    let mut processed_document = document.to_string();
+    let mut image_counter = 1;
+
+    // Collect all matches first to avoid borrowing issues
+    let mut matches: Vec<(String, f64)> = Vec::new();
+    for caps in re.captures_iter(document) {
+        let full_match = caps[0].to_string();
+        match parse_timestamp(&caps[1]) {
+            Ok(timestamp) => matches.push((full_match, timestamp)),
+            Err(e) => {
+                // Extracting frame 0 for every malformed placeholder would only produce duplicates
+                println!("⚠️ [IMAGE] {}, removing placeholder", e);
+                processed_document = processed_document.replace(&full_match, "");
+            }
+        }
+    }
+
+    println!(
+        "📊 [IMAGE] Found {} screenshot references to process",
+        matches.len()
+    );
+
+    // The prompt only hints at the screenshot density, so drop references that land too
+    // close to an earlier one. Repeated placeholders share one image and are always kept.
+    let min_spacing = image_embed_frequency.min_screenshot_spacing_seconds();
+    if min_spacing > 0.0 {
+        let mut kept: Vec<(String, f64)> = Vec::new();
+        for (placeholder, timestamp) in matches {
+            let already_kept = kept
+                .iter()
+                .any(|(kept_placeholder, _)| *kept_placeholder == placeholder);
+            let too_close = kept
+                .iter()
+                .any(|(_, kept_timestamp)| (kept_timestamp - timestamp).abs() < min_spacing);
+            if !already_kept && too_close {
+                println!(
+                    "🧹 [IMAGE] Dropping screenshot at {}s, within {}s of another one",
+                    timestamp, min_spacing
+                );
+                processed_document = processed_document.replace(&placeholder, "");
+            } else {
+                kept.push((placeholder, timestamp));
+            }
+        }
+        matches = kept;
+    }
+
+    let timeouts = ProcessTimeouts::from_settings(settings);
+
+    // Get video durations to help determine which video contains the timestamp
+    let mut video_durations = Vec::new();
+    for video_path in video_files {
+        match crate::video::get_video_duration(video_path, &timeouts).await {
+            Ok(duration) => video_durations.push(duration),
+            Err(e) => {
+                println!("⚠️ Failed to get duration for {}: {}", video_path, e);
+                video_durations.push(f64::INFINITY); // Assume infinite duration if we can't get it
+            }
+        }
+    }
+
+    // Fast and multiple extraction are experimental, standard extraction is used otherwise
+    let method = if settings.enable_experimental_features {
+        settings.frame_extraction_method.clone()
+    } else {
+        FrameExtractionMethod::Standard
+    };
+
+    // Standard extraction seeks on the input side, so snapping to a keyframe gives the exact frame
+    let mut video_keyframes = vec![Vec::new(); video_files.len()];
+    if method == FrameExtractionMethod::Standard && settings.keyframe_aligned_extraction {
+        for (keyframes, video_path) in video_keyframes.iter_mut().zip(video_files) {
+            match crate::video::get_video_keyframe_timestamps(video_path, &timeouts).await {
+                Ok(timestamps) => *keyframes = timestamps,
+                Err(e) => println!(
+                    "⚠️ [IMAGE] Failed to get keyframes for {}: {}",
+                    video_path, e
+                ),
+            }
+        }
+    }
+
+    // For each screenshot, the videos that could contain the timestamp in priority order,
+    // with the timestamp converted to the position inside that video
+    let tasks: Vec<Vec<(usize, String, f64)>> = matches
+        .iter()
+        .map(|(_, timestamp)| {
+            let mut video_candidates: Vec<(usize, String, f64)> = video_files
+                .iter()
+                .enumerate()
+                .map(|(i, path)| {
+                    let relative = calculate_relative_timestamp(
+                        *timestamp,
+                        i,
+                        &video_durations,
+                        overlap_seconds,
+                    );
+                    (i, path.clone(), relative)
+                })
+                .filter(|(i, _, relative)| *relative >= 0.0 && *relative <= video_durations[*i])
+                .collect();
+
+            // If no video can contain this timestamp, try all videos as fallback
+            if video_candidates.is_empty() {
+                video_candidates = video_files
+                    .iter()
+                    .enumerate()
+                    .map(|(i, path)| (i, path.clone(), *timestamp))
+                    .collect();
+            }
+            for (i, _, relative) in video_candidates.iter_mut() {
+                *relative = crate::video::nearest_keyframe(*relative, &video_keyframes[*i]);
+            }
+            video_candidates
+        })
+        .collect();
+
+    // Screenshots of the same position would be extracted into the same file, so each distinct
+    // frame is extracted once and shared. Concurrent extractions could otherwise clobber the file.
+    let mut unique_tasks: Vec<Vec<(usize, String, f64)>> = Vec::new();
+    let task_slots: Vec<usize> = tasks
+        .into_iter()
+        .map(
+            |video_candidates| match unique_tasks.iter().position(|t| *t == video_candidates) {
+                Some(slot) => slot,
+                None => {
+                    unique_tasks.push(video_candidates);
+                    unique_tasks.len() - 1
+                }
+            },
+        )
+        .collect();
+
+    let extracted_frames: Vec<Option<String>> =
+        if settings.enable_experimental_features && settings.concurrent_frame_extraction {
             println!(
-                "❌ Failed to extract frame at {}s from any video",
-                timestamp
+                "⚡ [IMAGE] Extracting frames concurrently (max {} ffmpeg processes)",
+                settings.max_concurrent_frame_extractions
             );
-            processed_document = processed_document.replace(&placeholder, "");
+            let semaphore = Arc::new(Semaphore::new(
+                settings.max_concurrent_frame_extractions.max(1),
+            ));
+            let mut join_set = JoinSet::new();
+            for (task_index, video_candidates) in unique_tasks.into_iter().enumerate() {
+                let images_dir = images_dir.clone();
+                let method = method.clone();
+                let format = settings.frame_output_format.clone();
+                let semaphore = semaphore.clone();
+                join_set.spawn(async move {
+                    let image_filename = extract_frame_from_candidates(
+                        &video_candidates,
+                        &images_dir,
+                        &method,
+                        &format,
+                        Some(&semaphore),
+                        &timeouts,
+                    )
+                    .await;
+                    (task_index, image_filename)
+                });
+            }
+
+            let mut results = vec![None; join_set.len()];
+            while let Some(result) = join_set.join_next().await {
+                match result {
+                    Ok((task_index, image_filename)) => results[task_index] = image_filename,
+                    Err(e) => println!("⚠️ [IMAGE] Frame extraction task failed: {}", e),
+                }
+            }
+            results
+        } else {
+            let mut results = Vec::new();
+            for video_candidates in &unique_tasks {
+                results.push(
+                    extract_frame_from_candidates(
+                        video_candidates,
+                        &images_dir,
+                        &method,
+                        &settings.frame_output_format,
+                        None,
+                        &timeouts,
+                    )
+                    .await,
+                );
+            }
+            results
+        };
+    let extracted_images = task_slots
+        .iter()
+        .map(|&slot| extracted_frames[slot].clone());
+
+    // Replace the placeholders in document order so the numbering stays sequential
+    for ((placeholder, timestamp), image_filename) in matches.iter().zip(extracted_images) {
+        match image_filename {
+            Some(image_filename) => {
+                let relative_image_path = format!("./images/{}", image_filename);
+                let mut alt_text = format!("Screenshot {}", image_counter);
+                if settings.generate_image_captions {
+                    let image_path = images_dir.join(&image_filename);
+                    match generate_image_caption(
+                        &image_path.to_string_lossy(),
+                        &GeminiClient::from_settings(settings),
+                        &settings.gemini_model,
+                        &settings.gemini_api_version,
+                        &settings.safety_settings,
+                    )
+                    .await
+                    {
+                        Ok(caption) if !caption.is_empty() => alt_text = caption,
+                        Ok(_) => {}
+                        Err(e) => println!(
+                            "⚠️ [IMAGE] Failed to generate caption for {}: {}",
+                            image_filename, e
+                        ),
+                    }
+                }
+                let markdown_image = format!("![{}]({})", alt_text, relative_image_path);
+                processed_document = processed_document.replace(placeholder, &markdown_image);
+                image_counter += 1;
+            }
+            None => {
+                // If no video could provide the frame, remove the placeholder
+                println!(
+                    "❌ Failed to extract frame at {}s from any video",
+                    timestamp
+                );
+                processed_document = processed_document.replace(placeholder, "");
+            }
         }
     }
 
+    if settings.image_grid_mode {
+        let columns = settings.image_grid_columns;
+        // Grid names are scoped to this call so later jobs in the same directory do not overwrite them
+        let grid_prefix: String = uuid::Uuid::new_v4()
+            .simple()
+            .to_string()
+            .chars()
+            .take(6)
+            .collect();
+        let (combined_document, grid_paths) = tokio::task::spawn_blocking(move || {
+            combine_section_screenshots(&processed_document, &images_dir, columns, &grid_prefix)
+        })
+        .await?;
+        processed_document = combined_document;
+        crate::file::record_intermediate_files(Path::new(output_directory), &grid_paths);
+    }
+
     Ok(processed_document)
 }
+
+/// Replaces the screenshots of each `##` section with a single grid image
+/// Sections are numbered by their `##` heading, the text before the first heading is section 0
+/// Screenshots are matched by their path, the alt text may be a generated caption
+/// Returns the document and the paths of the grid images that were written
+fn combine_section_screenshots(
+    document: &str,
+    images_dir: &Path,
+    columns: u32,
+    grid_prefix: &str,
+) -> (String, Vec<PathBuf>) {
+    let screenshot_regex = Regex::new(r"!\[[^\]]*\]\(\./images/([^)]+)\)").unwrap();
+
+    let mut sections: Vec<(usize, String)> = vec![(0, String::new())];
+    let mut heading_count = 0;
+    let mut in_code_block = false;
+    for line in document.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+        } else if !in_code_block && line.starts_with("## ") {
+            heading_count += 1;
+            sections.push((heading_count, String::new()));
+        }
+        if let Some((_, section)) = sections.last_mut() {
+            section.push_str(line);
+        }
+    }
+
+    let mut output = String::with_capacity(document.len());
+    let mut grid_paths = Vec::new();
+    for (section_number, section) in &sections {
+        let image_paths: Vec<PathBuf> = screenshot_regex
+            .captures_iter(section)
+            .map(|caps| images_dir.join(&caps[1]))
+            .collect();
+        if image_paths.len() < 2 {
+            output.push_str(section);
+            continue;
+        }
+
+        let grid_filename = format!("{}_section_{}_grid.jpg", grid_prefix, section_number);
+        let grid_path = images_dir.join(&grid_filename);
+        if let Err(e) = create_image_grid(&image_paths, columns, &grid_path) {
+            println!(
+                "⚠️ [IMAGE] Failed to create screenshot grid for section {}: {}",
+                section_number, e
+            );
+            output.push_str(section);
+            continue;
+        }
+        println!(
+            "🧩 [IMAGE] Combined {} screenshots into {}",
+            image_paths.len(),
+            grid_filename
+        );
+        grid_paths.push(grid_path);
+
+        // The grid takes the place of the first screenshot, lines left empty are dropped
+        let mut grid_inserted = false;
+        for line in section.split_inclusive('\n') {
+            if !screenshot_regex.is_match(line) {
+                output.push_str(line);
+                continue;
+            }
+            let replaced = screenshot_regex.replace_all(line, |_: &regex::Captures| {
+                if grid_inserted {
+                    String::new()
+                } else {
+                    grid_inserted = true;
+                    format!(
+                        "![Screenshots for this section](./images/{})",
+                        grid_filename
+                    )
+                }
+            });
+            if !replaced.trim().is_empty() {
+                output.push_str(&replaced);
+            }
+        }
+    }
+
+    (output, grid_paths)
+}
+
+const GRID_CELL_WIDTH: u32 = 320;
+const GRID_CELL_HEIGHT: u32 = 180;
+
+/// Scales every image to 320x180 and arranges them row by row, `columns` images per row
+fn create_image_grid(image_paths: &[PathBuf], columns: u32, output_path: &Path) -> Result<()> {
+    let columns = columns.clamp(1, image_paths.len().max(1) as u32);
+    let rows = (image_paths.len() as u32).div_ceil(columns);
+    let mut grid = image::RgbImage::from_pixel(
+        columns * GRID_CELL_WIDTH,
+        rows * GRID_CELL_HEIGHT,
+        image::Rgb([255, 255, 255]),
+    );
+
+    for (index, path) in image_paths.iter().enumerate() {
+        let image = image::open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", path.display(), e))?;
+        let cell = image::imageops::resize(
+            &image.to_rgb8(),
+            GRID_CELL_WIDTH,
+            GRID_CELL_HEIGHT,
+            image::imageops::FilterType::Triangle,
+        );
+        let index = index as u32;
+        image::imageops::replace(
+            &mut grid,
+            &cell,
+            ((index % columns) * GRID_CELL_WIDTH) as i64,
+            ((index / columns) * GRID_CELL_HEIGHT) as i64,
+        );
+    }
+
+    grid.save_with_format(output_path, image::ImageFormat::Jpeg)?;
+    Ok(())
+}
+
+/// Tries the candidate videos in priority order and returns the image file name
+/// from the first one that succeeds. The next video is only tried if the previous one failed.
+async fn extract_frame_from_candidates(
+    video_candidates: &[(usize, String, f64)],
+    images_dir: &Path,
+    method: &FrameExtractionMethod,
+    format: &FrameOutputFormat,
+    semaphore: Option<&Semaphore>,
+    timeouts: &ProcessTimeouts,
+) -> Option<String> {
+    for (video_index, video_path, timestamp) in video_candidates {
+        let timestamp = *timestamp;
+        let video_no = video_index + 1; // 1-based indexing
+                                        // Replace decimal point with underscore for filename compatibility
+        let timestamp_str = timestamp.to_string().replace('.', "_");
+        let image_filename = format!(
+            "image-{}-{}s.{}",
+            video_no,
+            timestamp_str,
+            format.extension()
+        );
+        let image_path = images_dir.join(&image_filename);
+
+        // Limit the number of concurrent ffmpeg processes
+        let _permit = match semaphore {
+            Some(semaphore) => semaphore.acquire().await.ok(),
+            None => None,
+        };
+
+        // Extract frame from video using the selected method
+        let extraction_result = match method {
+            FrameExtractionMethod::Fast => {
+                crate::video::extract_frame_fast(
+                    video_path,
+                    timestamp,
+                    image_path.to_str().unwrap(),
+                    format,
+                    timeouts,
+                )
+                .await
+            }
+            FrameExtractionMethod::Multiple => {
+                // For single frame extraction, use multiple method with single timestamp
+                match crate::video::extract_multiple_frames_from_video(
+                    video_path,
+                    &[timestamp],
+                    images_dir.to_str().unwrap(),
+                    &format!("video-{}-{}s", video_no, timestamp_str),
+                    format,
+                    timeouts,
+                )
+                .await
+                {
+                    Ok(paths) if !paths.is_empty() => {
+                        // If successful, move the generated file to the expected location
+                        if let Some(generated_path) = paths.first() {
+                            if Path::new(generated_path).exists()
+                                && generated_path != image_path.to_str().unwrap()
+                            {
+                                if let Err(e) =
+                                    fs::rename(generated_path, image_path.to_str().unwrap())
+                                {
+                                    println!("⚠️ Failed to move generated image: {}", e);
+                                }
+                            }
+                        }
+                        Ok(())
+                    }
+                    Ok(_) => Err(anyhow::anyhow!("No frames extracted")),
+                    Err(e) => Err(e),
+                }
+            }
+            FrameExtractionMethod::Standard => {
+                crate::video::extract_frame_from_video(
+                    video_path,
+                    timestamp,
+                    image_path.to_str().unwrap(),
+                    format,
+                    timeouts,
+                )
+                .await
+            }
+        };
+
+        match extraction_result {
+            Ok(_) => {
+                println!(
+                    "✅ Successfully extracted frame from video {} at {}s",
+                    video_no, timestamp
+                );
+                return Some(image_filename); // Stop trying other videos once successful
+            }
+            Err(e) => {
+                println!(
+                    "⚠️ Failed to extract frame from video {} at {}s: {}",
+                    video_no, timestamp, e
+                );
+                // Continue to try next video
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captioned_screenshots_are_combined_into_a_grid() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["frame_1.jpg", "frame_2.jpg"] {
+            image::RgbImage::from_pixel(64, 36, image::Rgb([0, 0, 0]))
+                .save(dir.path().join(name))
+                .unwrap();
+        }
+        let first_caption = sanitize_image_caption("Settings dialog with [Save] button");
+        let document = format!(
+            "# Manual\n\n## Setup\n\n![{}](./images/frame_1.jpg)\n\nText\n\n![Screenshot 2](./images/frame_2.jpg)\n",
+            first_caption
+        );
+
+        let (combined, grid_paths) = combine_section_screenshots(&document, dir.path(), 2, "job1");
+
+        assert_eq!(
+            combined,
+            "# Manual\n\n## Setup\n\n![Screenshots for this section](./images/job1_section_1_grid.jpg)\n\nText\n\n"
+        );
+        assert_eq!(grid_paths, vec![dir.path().join("job1_section_1_grid.jpg")]);
+        assert!(grid_paths[0].is_file());
+    }
+
+    #[test]
+    fn prompt_estimate_counts_every_reference_document() {
+        let dir = tempfile::tempdir().unwrap();
+        let reference = dir.path().join("style_guide.md");
+        fs::write(&reference, "a".repeat(MAX_REFERENCE_DOCUMENT_CHARS * 3)).unwrap();
+        let reference = reference.to_string_lossy().to_string();
+
+        let estimate = estimate_prompt_tokens("1234", &[reference.clone(), reference]).unwrap();
+
+        assert_eq!(estimate, (MAX_REFERENCE_DOCUMENT_CHARS as u64 * 6 + 4) / 4);
+    }
+
+    #[test]
+    fn reference_documents_are_split_until_each_chunk_fits() {
+        let dir = tempfile::tempdir().unwrap();
+        let references: Vec<String> = [4000, 400, 400, 400, 400]
+            .iter()
+            .enumerate()
+            .map(|(index, chars)| {
+                let path = dir.path().join(format!("reference_{}.md", index));
+                fs::write(&path, "a".repeat(*chars)).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+
+        // 300 tokens hold the prompt and up to two of the short documents
+        let chunks = split_reference_documents("1234", &references, 300).unwrap();
+
+        assert_eq!(
+            chunks,
+            vec![
+                &references[..1],
+                &references[1..2],
+                &references[2..3],
+                &references[3..5],
+            ]
+        );
+    }
+}
@@ -1,20 +1,110 @@
 use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use tauri::{Emitter, Manager};
+use tokio::time::{Duration, Instant};
 
-mod file;
-mod gemini;
-mod types;
+pub mod claude;
+pub mod file;
+pub mod gemini;
+pub mod postprocess;
+pub mod types;
 mod video;
 
-use crate::file::{save_document_to_file, select_save_directory, select_video_files};
+use crate::claude::{generate_with_claude_with_progress, ClaudeClient};
+use crate::file::{
+    apply_document_watermark, check_disk_space, clean_stale_intermediate_files,
+    create_job_output_dir, find_duplicate_files, get_audio_duration, list_image_sequence,
+    notify_generation_complete, read_text_file, sanitize_filename, save_document_as_html,
+    save_document_to_file, save_document_with_metadata_sidecar, save_flashcards,
+    save_split_document, select_audio_files, select_image_sequence_dir, select_save_directory,
+    select_video_files, source_video_links, split_audio_file_at_chapters,
+    validate_html_report_template, validate_video_file_sizes, CleanupList,
+};
 use crate::gemini::{
-    generate_with_gemini_with_progress, integrate_documents, process_document_with_images,
-    upload_to_gemini_with_progress,
+    available_gemini_models, builtin_prompt_presets, classify_video_type, count_gemini_tokens,
+    count_table_rows, delete_all_uploaded_gemini_files, delete_uploaded_gemini_file,
+    detect_mime_type, effective_prompt, ensure_gemini_file_active, gemini_file_name,
+    generate_document_title, generate_flashcards_from_document, generate_glossary,
+    generate_with_gemini_with_progress, integrate_documents_with_strategy,
+    integration_strategy_name, process_document_with_images, screen_recording_prompt,
+    summarize_document, translate_document, upload_to_gemini_with_progress, uploaded_file_data,
+    validate_model_name, version_diff_prompt, GeminiClient, GenerationOptions,
+    SUPPORTED_REFERENCE_EXTENSIONS,
+};
+use crate::postprocess::{
+    cap_heading_depth, lint_markdown, normalise_code_blocks, normalize_list_numbering,
+    strip_ai_preamble, truncate_at_section_boundary,
+};
+use crate::types::{
+    default_prompt_preset_version, detect_settings_migrations, AiBackend, AppSettings,
+    AudioTrackInfo, ConfigPaths, DocumentFormatPreset, DocumentSizeWarning, DocumentTypeDetection,
+    DuplicatePresetWarning, GeminiApiVersion, GeminiFileData, ImageEmbedFrequency,
+    PresetFileFormat, ProcessTimeouts, ProgressUpdate, ProgressVerbosity, PromptPreset,
+    PromptPresetRevision, SampledFrame, StreamInfo, TelemetryPayload, VideoFile,
+    VideoValidationResult,
+};
+use crate::video::{
+    assemble_image_sequence, encode_video_if_needed, extract_frames_at_rate, get_video_duration,
+    hash_first_megabyte, is_vidstab_available, probe_audio_tracks, probe_streams,
+    split_video_if_needed, trim_video, validate_ffmpeg_extra_args, validate_subtitle_file,
+    validate_video_file,
 };
-use crate::types::{AppSettings, ProgressUpdate, PromptPreset, VideoFile};
-use crate::video::{encode_video_if_needed, split_video_if_needed};
+
+const MAX_RECENT_PROMPTS: usize = 20;
+
+/// Serializes load-modify-save cycles on the settings file
+static SETTINGS_FILE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Emits events, skipping those that arrive within `min_interval` of the previous one
+struct DebouncedEmitter {
+    last_emit: Instant,
+    min_interval: Duration,
+    app: tauri::AppHandle,
+    /// Latest event skipped since the previous emit, sent by `flush`
+    pending: Option<(String, serde_json::Value)>,
+}
+
+impl DebouncedEmitter {
+    fn new(app: tauri::AppHandle, min_interval: Duration) -> Self {
+        // Backdated so that the first event is never skipped
+        let last_emit = Instant::now()
+            .checked_sub(min_interval)
+            .unwrap_or_else(Instant::now);
+        Self {
+            last_emit,
+            min_interval,
+            app,
+            pending: None,
+        }
+    }
+
+    fn emit_if_ready(&mut self, event: &str, payload: &impl serde::Serialize) {
+        if self.last_emit.elapsed() >= self.min_interval {
+            self.emit(event, payload);
+        } else if let Ok(value) = serde_json::to_value(payload) {
+            self.pending = Some((event.to_string(), value));
+        }
+    }
+
+    /// Emits regardless of the interval
+    fn emit(&mut self, event: &str, payload: &impl serde::Serialize) {
+        self.last_emit = Instant::now();
+        self.pending = None;
+        if let Err(e) = self.app.emit(event, payload) {
+            println!("❌ [EVENT] Failed to emit {}: {}", event, e);
+        }
+    }
+
+    /// Emits the last skipped event so the final state reaches the frontend
+    fn flush(&mut self) {
+        if let Some((event, payload)) = self.pending.take() {
+            self.emit(&event, &payload);
+        }
+    }
+}
 
 #[tauri::command]
 async fn generate_document(
@@ -23,23 +113,93 @@ async fn generate_document(
     save_directory: String,
     app: tauri::AppHandle,
 ) -> Result<String, String> {
+    run_document_generation(files, settings, save_directory, app, true).await
+}
+
+/// Shared pipeline behind the generation commands.
+/// `record_prompt` is false when the custom prompt was built by the backend rather than typed by the user.
+async fn run_document_generation(
+    files: Vec<VideoFile>,
+    mut settings: AppSettings,
+    save_directory: String,
+    app: tauri::AppHandle,
+    record_prompt: bool,
+) -> Result<String, String> {
+    let started_at = Instant::now();
     println!(
         "🚀 [BACKEND] Starting generate_document with {} files",
         files.len()
     );
-    println!("📋 [BACKEND] Settings: language={}", settings.language);
+    println!(
+        "📋 [BACKEND] Settings: language={}",
+        settings.language.as_str()
+    );
+    validate_settings(&settings)?;
+    if settings.ai_backend == AiBackend::Claude && settings.claude_api_key.trim().is_empty() {
+        return Err("Claude API key is not set".to_string());
+    }
+    let gemini = GeminiClient::from_settings(&settings);
+    let timeouts = ProcessTimeouts::from_settings(&settings);
+
+    // Renamed or retired models would otherwise only fail after encoding and uploading.
+    // The Claude backend does not generate with the Gemini model.
+    let model_cache_path = get_model_cache_file_path(&app)?;
+    let model_available = settings.ai_backend == AiBackend::Claude
+        || validate_model_name(&settings.gemini_model, &gemini, &model_cache_path)
+            .await
+            .map_err(|e| format!("Failed to validate model: {}", e))?;
+    if !model_available {
+        let models = available_gemini_models(&gemini, &model_cache_path).await;
+        println!(
+            "❌ [BACKEND] Model {} is not available",
+            settings.gemini_model
+        );
+        return Err(format!(
+            "Model '{}' is not available. Available models: {}",
+            settings.gemini_model,
+            models.join(", ")
+        ));
+    }
+    // Make sure the job will not run out of disk space midway
+    let disk_space =
+        check_disk_space(files.clone(), settings.clone(), save_directory.clone()).await?;
+    if !disk_space.sufficient {
+        println!(
+            "❌ [BACKEND] Insufficient disk space: required={} bytes, available={} bytes",
+            disk_space.required_bytes, disk_space.available_bytes
+        );
+        return Err(disk_space.recommendation);
+    }
+
+    // Write everything for this job into its own subdirectory when requested
+    let save_directory = if settings.output_dir_per_job {
+        let job_dir = create_job_output_dir(&save_directory)
+            .map_err(|e| format!("Failed to create job output directory: {}", e))?;
+        let job_dir = job_dir.to_string_lossy().to_string();
+        println!("📁 [BACKEND] Job output directory: {}", job_dir);
+        if let Err(e) = app.emit("job_output_dir", &job_dir) {
+            println!("❌ [EVENT] Failed to emit job_output_dir: {}", e);
+        }
+        job_dir
+    } else {
+        save_directory
+    };
+
+    // Translate the generated document only when the target differs from the generation language
+    let translate_to = settings
+        .translate_output_to
+        .clone()
+        .filter(|lang| !lang.is_empty() && lang != settings.language.as_str());
 
     // Calculate total steps for progress tracking
-    let total_steps = files.len() * 4 + if files.len() > 1 { 1 } else { 0 }; // Split, Encode, Upload, Generate per file + Integration
+    let total_steps = files.len() * 4
+        + if files.len() > 1 { 1 } else { 0 }
+        + if translate_to.is_some() { 1 } else { 0 }; // Split, Encode, Upload, Generate per file + Integration + Translation
     let mut current_step = 0;
 
     // Helper function to emit progress
     let emit_progress = |app_ref: &tauri::AppHandle, step: usize, total: usize, message: String| {
-        let progress = ProgressUpdate {
-            message: message.clone(),
-            step,
-            total_steps: total,
-        };
+        let progress = ProgressUpdate::new(message.clone(), step, total, started_at);
         println!(
             "📡 [EVENT] Emitting progress: step={}/{}, message={}",
             step, total, message
@@ -58,8 +218,61 @@ async fn generate_document(
         "ドキュメント生成を開始しています...".to_string(),
     );
 
+    // Validate input file sizes before starting any FFmpeg work
+    let (size_warnings, size_errors) = validate_video_file_sizes(&files, settings.max_upload_bytes);
+    for warning in size_warnings {
+        println!("⚠️ [BACKEND] {}", warning);
+        emit_progress(&app, current_step, total_steps, warning);
+    }
+    if !size_errors.is_empty() {
+        let messages: Vec<String> = size_errors.iter().map(|e| e.to_string()).collect();
+        println!("❌ [BACKEND] File validation failed: {:?}", messages);
+        return Err(format!("File validation failed:\n{}", messages.join("\n")));
+    }
+
+    // Detect the same video being added twice
+    if files.len() > 1 {
+        emit_progress(
+            &app,
+            current_step,
+            total_steps,
+            "重複ファイルを確認中...".to_string(),
+        );
+        if let Some(duplicates) = find_duplicate_files(&files, &timeouts).await {
+            println!("❌ [BACKEND] {}", duplicates);
+            return Err(duplicates.to_string());
+        }
+    }
+
+    // Only pick a preset when neither the user nor the calling command chose the prompt,
+    // and only once the job has passed validation since detection is a paid API call
+    let uses_custom_prompt = settings.format_preset == DocumentFormatPreset::Custom
+        || settings
+            .custom_prompt
+            .as_deref()
+            .is_some_and(|prompt| !prompt.trim().is_empty());
+    if settings.auto_detect_document_type
+        && settings.ai_backend == AiBackend::Gemini
+        && !uses_custom_prompt
+    {
+        if let Some(first_file) = files.first() {
+            settings.format_preset = detect_document_type(first_file, &settings, &app).await;
+        }
+    }
+
+    // Split and encoded files are recorded for later cleanup and deleted when this guard is
+    // dropped (also on early return) unless the user wants to keep them
+    let mut intermediate_files = CleanupList::new(
+        Path::new(&save_directory),
+        !settings.save_intermediate_files,
+    );
+
     // Process files and split if necessary
     let mut split_files = Vec::new();
+    // Source file of each segment, kept in the same order as split_files
+    let mut segment_sources: Vec<&VideoFile> = Vec::new();
+    // Whether any video was split into overlapping segments
+    let mut segments_overlap = false;
 
     for (index, file) in files.iter().enumerate() {
         current_step += 1;
@@ -81,16 +294,60 @@ async fn generate_document(
             files.len(),
             file.name
         );
-        match split_video_if_needed(&PathBuf::from(&file.path)).await {
+        ensure_file_unchanged(file, &app).await?;
+        // Trim the clip first so that splitting and encoding only work on the selected range
+        let source_path =
+            if file.start_offset_seconds.is_some() || file.end_offset_seconds.is_some() {
+                emit_progress(
+                    &app,
+                    current_step,
+                    total_steps,
+                    format!("動画をトリミング中: {}", file.name),
+                );
+                match trim_video(
+                    &file.path,
+                    file.start_offset_seconds,
+                    file.end_offset_seconds,
+                    Path::new(&save_directory),
+                    &timeouts,
+                )
+                .await
+                {
+                    Ok(trimmed_path) => {
+                        println!("✂️ [BACKEND] Trimmed video: {}", trimmed_path.display());
+                        intermediate_files.add(trimmed_path.clone());
+                        trimmed_path
+                    }
+                    Err(e) => {
+                        println!("❌ [BACKEND] Failed to trim file {}: {}", file.name, e);
+                        return Err(format!("Failed to trim file {}: {}", file.name, e));
+                    }
+                }
+            } else {
+                PathBuf::from(&file.path)
+            };
+
+        match split_video_if_needed(
+            &source_path,
+            &settings.split_mode,
+            settings.segment_overlap_seconds,
+            &timeouts,
+        )
+        .await
+        {
             Ok(segments) => {
                 if segments.len() > 1 {
+                    segments_overlap = settings.segment_overlap_seconds > 0.0;
                     println!("✂️ [BACKEND] Video split into {} segments", segments.len());
                     for segment in segments {
+                        intermediate_files.add(segment.clone());
                         split_files.push(segment);
+                        segment_sources.push(file);
                     }
                 } else {
                     println!("✅ [BACKEND] Video is under 1 hour, no splitting needed");
-                    split_files.push(PathBuf::from(&file.path));
+                    split_files.push(source_path);
+                    segment_sources.push(file);
                 }
             }
             Err(e) => {
@@ -99,6 +356,65 @@ async fn generate_document(
             }
         }
     }
+    let overlap_seconds = if segments_overlap {
+        settings.segment_overlap_seconds
+    } else {
+        0.0
+    };
+
+    // Skip stabilization if ffmpeg was built without vidstab
+    let stabilize_video = if settings.stabilize_video && !is_vidstab_available(&timeouts).await {
+        println!("⚠️ [BACKEND] vidstab filter is not available, skipping stabilization");
+        if let Err(e) = app.emit(
+            "stabilization_unavailable",
+            "ffmpegにvidstabフィルタが含まれていないため、手ブレ補正をスキップします",
+        ) {
+            println!("❌ [EVENT] Failed to emit stabilization_unavailable: {}", e);
+        }
+        false
+    } else {
+        settings.stabilize_video
+    };
+
+    // Subtitle timings only match a single, unsplit video that starts at the beginning
+    let subtitle_burn_in = match settings.subtitle_burn_in.as_deref() {
+        Some(subtitle_path)
+            if files.len() == 1
+                && split_files.len() == 1
+                && files[0].start_offset_seconds.unwrap_or(0.0) == 0.0 =>
+        {
+            validate_subtitle_file(subtitle_path)
+                .map_err(|e| format!("Invalid subtitle file: {}", e))?;
+            Some(subtitle_path)
+        }
+        Some(_) => {
+            println!("⚠️ [BACKEND] Subtitle burn-in is only supported for a single unsplit video, skipping");
+            emit_progress(
+                &app,
+                current_step,
+                total_steps,
+                "警告: 字幕の焼き込みは分割されない単一の動画でのみ利用できるため、スキップします"
+                    .to_string(),
+            );
+            None
+        }
+        None => None,
+    };
+
+    // Extra ffmpeg arguments are an experimental power user option
+    let (ffmpeg_extra_input_args, ffmpeg_extra_output_args) =
+        if settings.enable_experimental_features {
+            validate_ffmpeg_extra_args(&settings.ffmpeg_extra_input_args)
+                .map_err(|e| format!("Invalid ffmpeg input arguments: {}", e))?;
+            validate_ffmpeg_extra_args(&settings.ffmpeg_extra_output_args)
+                .map_err(|e| format!("Invalid ffmpeg output arguments: {}", e))?;
+            (
+                settings.ffmpeg_extra_input_args.as_slice(),
+                settings.ffmpeg_extra_output_args.as_slice(),
+            )
+        } else {
+            (&[][..], &[][..])
+        };
 
     // Encode videos if needed
     let mut processed_files = Vec::new();
@@ -130,16 +446,27 @@ async fn generate_document(
             file_path.display()
         );
 
-        // Create progress callback for encoding
-        let app_clone = app.clone();
-        let progress_callback = move |message: String| {
-            let progress = ProgressUpdate {
-                message,
-                step: current_step,
-                total_steps,
-            };
-            if let Err(e) = app_clone.emit("progress_update", &progress) {
-                println!("❌ [EVENT] Failed to emit encoding progress: {}", e);
+        if let Some(source) = segment_sources.get(index) {
+            ensure_file_unchanged(source, &app).await?;
+        }
+
+        // Create progress callback for encoding. ffmpeg reports progress many times per
+        // second, so the events are debounced unless this is the final step.
+        let emitter = std::sync::Mutex::new(DebouncedEmitter::new(
+            app.clone(),
+            Duration::from_millis(settings.progress_event_debounce_ms),
+        ));
+        let verbosity = &settings.progress_verbosity;
+        let progress_callback = |message: String| {
+            if matches!(verbosity, ProgressVerbosity::Minimal) {
+                return;
+            }
+            let progress = ProgressUpdate::new(message, current_step, total_steps, started_at);
+            let mut emitter = emitter.lock().unwrap();
+            if current_step == total_steps {
+                emitter.emit("progress_update", &progress);
+            } else {
+                emitter.emit_if_ready("progress_update", &progress);
             }
         };
 
@@ -149,14 +476,28 @@ async fn generate_document(
             output_dir,
             progress_callback,
             settings.hardware_encoding,
+            settings.audio_track_index,
+            settings.video_watermark.as_ref(),
+            stabilize_video,
+            settings.two_pass_encoding,
+            subtitle_burn_in,
+            ffmpeg_extra_input_args,
+            ffmpeg_extra_output_args,
+            settings.video_stream_index,
+            &timeouts,
         )
         .await
         {
             Ok(encoded_path) => {
+                // The last progress lines (up to 100%) may have been debounced away
+                emitter.lock().unwrap().flush();
                 println!(
                     "✅ [BACKEND] Video processing completed: {:?}",
                     encoded_path
                 );
+                if encoded_path != *file_path {
+                    intermediate_files.add(encoded_path.clone());
+                }
                 processed_files.push(encoded_path);
             }
             Err(e) => {
@@ -174,6 +515,25 @@ async fn generate_document(
         }
     }
 
+    // The Files API only exists under v1beta
+    if settings.gemini_api_version != GeminiApiVersion::V1Beta {
+        println!("⚠️ [BACKEND] Files API is not available under v1, using v1beta for uploads");
+        if let Err(e) = app.emit(
+            "api_version_fallback",
+            "ファイルのアップロードにはv1beta APIを使用します",
+        ) {
+            println!("❌ [EVENT] Failed to emit api_version_fallback: {}", e);
+        }
+    }
+
+    // Experimental: send frames sampled at a custom rate instead of uploading the video
+    let video_sampling_fps = settings
+        .video_sampling_fps
+        .filter(|fps| settings.enable_experimental_features && *fps > 0.0);
+    // The temporary frame directories are removed when these are dropped
+    let mut frame_dirs = Vec::new();
+    let mut segment_frames: Vec<Vec<SampledFrame>> = Vec::new();
+
     // Upload files to Gemini API
     let mut file_uris = Vec::new();
     println!(
@@ -187,6 +547,54 @@ async fn generate_document(
             .file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("不明なファイル");
+
+        // Claude receives keyframes taken from the local file instead of an upload
+        if settings.ai_backend == AiBackend::Claude {
+            continue;
+        }
+
+        if let Some(fps) = video_sampling_fps {
+            emit_progress(
+                &app,
+                current_step,
+                total_steps,
+                format!(
+                    "フレーム抽出中 ({}/{}): {} ({} fps)",
+                    index + 1,
+                    processed_files.len(),
+                    file_name,
+                    fps
+                ),
+            );
+            let frame_dir = tempfile::tempdir()
+                .map_err(|e| format!("Failed to create temporary directory: {}", e))?;
+            let frames = extract_frames_at_rate(
+                &file_path.to_string_lossy(),
+                fps,
+                frame_dir.path(),
+                &timeouts,
+            )
+            .await
+            .map_err(|e| format!("Failed to sample frames from {}: {}", file_name, e))?;
+            println!(
+                "🖼️ [BACKEND] Sampled {} frames from {}",
+                frames.len(),
+                file_path.display()
+            );
+            segment_frames.push(
+                frames
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, path)| SampledFrame {
+                        path,
+                        timestamp_seconds: i as f64 / fps,
+                    })
+                    .collect(),
+            );
+            frame_dirs.push(frame_dir);
+            continue;
+        }
+
         emit_progress(
             &app,
             current_step,
@@ -207,10 +615,15 @@ async fn generate_document(
         );
         match upload_to_gemini_with_progress(
             &file_path.to_string_lossy(),
-            &settings.gemini_api_key,
+            &gemini,
+            settings.upload_chunk_size_mb.max(1) as usize * 1024 * 1024,
+            settings.upload_bandwidth_limit_kbps,
+            &settings.progress_verbosity,
+            &timeouts,
             &app,
             current_step,
             total_steps,
+            started_at,
         )
         .await
         {
@@ -235,54 +648,107 @@ async fn generate_document(
 
     // Generate documents for each file/segment
     let mut documents = Vec::new();
+    let segment_count = processed_files.len();
     println!(
-        "🤖 [BACKEND] Starting document generation for {} uploaded files",
-        file_uris.len()
+        "🤖 [BACKEND] Starting document generation for {} files",
+        segment_count
     );
 
-    for (index, file_uri) in file_uris.iter().enumerate() {
+    for (index, processed_file) in processed_files.iter().enumerate() {
         current_step += 1;
-        emit_progress(
-            &app,
-            current_step,
-            total_steps,
-            format!("ドキュメント生成中 ({}/{})", index + 1, file_uris.len()),
-        );
+        let segment_uris = file_uris
+            .get(index)
+            .map(std::slice::from_ref)
+            .unwrap_or(&[]);
+        let sampled_frames = segment_frames.get(index).map(Vec::as_slice).unwrap_or(&[]);
+        let segment_path = processed_file.display();
+        let prompt_override = segment_sources
+            .get(index)
+            .and_then(|file| file.custom_prompt_override.as_deref());
+        let message = if prompt_override.is_some() {
+            format!(
+                "ドキュメント生成中 (カスタムプロンプト使用) ({}/{})",
+                index + 1,
+                segment_count
+            )
+        } else {
+            format!("ドキュメント生成中 ({}/{})", index + 1, segment_count)
+        };
+        emit_progress(&app, current_step, total_steps, message);
 
         println!(
-            "📝 [BACKEND] Generating document {}/{} for URI: {}",
+            "📝 [BACKEND] Generating document {}/{} for: {}",
             index + 1,
-            file_uris.len(),
-            file_uri
+            segment_count,
+            segment_path
         );
-        match generate_with_gemini_with_progress(
-            &[file_uri.clone()],
-            &settings.language,
-            &settings.gemini_api_key,
-            settings.temperature,
-            settings.custom_prompt.as_deref(),
-            &settings.gemini_model,
-            settings.embed_images,
-            &settings.image_embed_frequency,
-            &app,
-            current_step,
-            total_steps,
-        )
-        .await
-        {
+        let result = if settings.ai_backend == AiBackend::Claude {
+            generate_with_claude_with_progress(
+                &processed_file.to_string_lossy(),
+                &settings.language,
+                // Keyframe requests are answered as slowly as Gemini video generation
+                &ClaudeClient::new(
+                    &settings.claude_api_key,
+                    settings.gemini_generation_timeout_seconds,
+                ),
+                &settings.claude_model,
+                prompt_override.or(effective_prompt(&settings)),
+                settings.embed_images,
+                &settings.image_embed_frequency,
+                &settings.document_numbering_style,
+                settings.max_heading_depth,
+                &timeouts,
+                &app,
+                current_step,
+                total_steps,
+                started_at,
+            )
+            .await
+        } else {
+            let context_cache_files = match segment_uris.first() {
+                Some(uri) if settings.enable_context_cache => Some(vec![uploaded_file_data(
+                    &processed_file.to_string_lossy(),
+                    uri,
+                    &timeouts,
+                )
+                .await
+                .map_err(|e| format!("Failed to detect MIME type: {}", e))?]),
+                _ => None,
+            };
+            generate_with_gemini_with_progress(
+                &GenerationOptions {
+                    sampled_frames,
+                    custom_prompt: prompt_override.or(effective_prompt(&settings)),
+                    ..GenerationOptions::from_settings(segment_uris, &settings)
+                },
+                &settings,
+                &gemini,
+                context_cache_files.as_deref(),
+                &app,
+                current_step,
+                total_steps,
+                started_at,
+            )
+            .await
+        };
+        match result {
             Ok(document) => {
                 println!(
                     "✅ [BACKEND] Successfully generated document {}/{} (length: {})",
                     index + 1,
-                    file_uris.len(),
+                    segment_count,
                     document.len()
                 );
-                documents.push(document);
+                if settings.strip_ai_preamble {
+                    documents.push(strip_ai_preamble(&document));
+                } else {
+                    documents.push(document);
+                }
             }
             Err(e) => {
                 println!(
                     "❌ [BACKEND] Failed to generate document for file {}: {}",
-                    file_uri, e
+                    segment_path, e
                 );
                 return Err(format!("Failed to generate document for file: {}", e));
             }
@@ -296,20 +762,29 @@ async fn generate_document(
             &app,
             current_step,
             total_steps,
-            "複数のドキュメントを統合中...".to_string(),
+            format!(
+                "複数のドキュメントを統合中 ({})...",
+                integration_strategy_name(&settings.integration_strategy)
+            ),
         );
 
         println!(
-            "🔗 [BACKEND] Integrating {} documents into final document",
-            documents.len()
+            "🔗 [BACKEND] Integrating {} documents into final document ({:?})",
+            documents.len(),
+            settings.integration_strategy
         );
-        match integrate_documents(
+        match integrate_documents_with_strategy(
+            &settings.integration_strategy,
             &documents,
             &settings.language,
-            &settings.gemini_api_key,
+            &gemini,
             settings.temperature,
-            settings.custom_prompt.as_deref(),
+            effective_prompt(&settings),
             &settings.gemini_model,
+            &settings.gemini_api_version,
+            &settings.safety_settings,
+            overlap_seconds,
+            |message| emit_progress(&app, current_step, total_steps, message),
         )
         .await
         {
@@ -330,8 +805,57 @@ async fn generate_document(
         documents.into_iter().next().unwrap_or_default()
     };
 
+    // Translate the document if requested (before image processing so placeholders remain)
+    let final_document = if let Some(target_language) = &translate_to {
+        current_step += 1;
+        emit_progress(
+            &app,
+            current_step,
+            total_steps,
+            format!("ドキュメントを翻訳中 ({})...", target_language),
+        );
+
+        println!("🌐 [BACKEND] Translating document to {}", target_language);
+        match translate_document(
+            &final_document,
+            target_language,
+            &gemini,
+            settings.temperature,
+            &settings.gemini_model,
+            &settings.gemini_api_version,
+            &settings.safety_settings,
+        )
+        .await
+        {
+            Ok(translated) => {
+                println!(
+                    "✅ [BACKEND] Successfully translated document (length: {})",
+                    translated.len()
+                );
+                translated
+            }
+            Err(e) => {
+                println!("❌ [BACKEND] Failed to translate document: {}", e);
+                return Err(format!("Failed to translate document: {}", e));
+            }
+        }
+    } else {
+        final_document
+    };
+
+    // Integration and translation responses can add their own conversational openings
+    let final_document = if settings.strip_ai_preamble {
+        strip_ai_preamble(&final_document)
+    } else {
+        final_document
+    };
+
     // Process images if embed_images is enabled
     let final_processed_document = if settings.embed_images && !processed_files.is_empty() {
+        // Frames may be extracted from the original files when no encoding was needed
+        for file in &files {
+            ensure_file_unchanged(file, &app).await?;
+        }
         emit_progress(
             &app,
             total_steps,
@@ -354,6 +878,7 @@ async fn generate_document(
             &output_dir,
             &settings.image_embed_frequency,
             &settings,
+            overlap_seconds,
         )
         .await
         {
@@ -373,55 +898,1017 @@ async fn generate_document(
         final_document
     };
 
-    emit_progress(
-        &app,
-        total_steps,
-        total_steps,
-        "ドキュメント生成が完了しました！".to_string(),
-    );
-    println!(
-        "🎉 [BACKEND] Document generation completed successfully (final length: {})",
-        final_processed_document.len()
-    );
-    Ok(final_processed_document)
-}
-
-#[tauri::command]
-async fn save_settings(settings: AppSettings, app: tauri::AppHandle) -> Result<(), String> {
-    // println!("save_settings called with: {:?}", settings);
-    let config_path = get_config_file_path(&app)?;
-    // println!("Config path: {:?}", config_path);
+    // Put the executive summary at the top of the document
+    let final_processed_document = if settings.auto_generate_summary {
+        emit_progress(
+            &app,
+            total_steps,
+            total_steps,
+            "エグゼクティブサマリーを生成中...".to_string(),
+        );
+        match generate_executive_summary(
+            final_processed_document.clone(),
+            settings.clone(),
+            app.clone(),
+        )
+        .await
+        {
+            Ok(summary) => format!(
+                "## Executive Summary\n\n{}\n\n{}",
+                summary, final_processed_document
+            ),
+            Err(e) => {
+                println!("⚠️ [BACKEND] Failed to generate executive summary: {}", e);
+                final_processed_document
+            }
+        }
+    } else {
+        final_processed_document
+    };
 
-    // Ensure the parent directory exists
-    if let Some(parent) = config_path.parent() {
-        fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create config directory: {}", e))?;
-    }
+    // Glossary of technical terms for the end of the document, skipped if the request fails
+    let glossary = if settings.generate_glossary {
+        emit_progress(
+            &app,
+            total_steps,
+            total_steps,
+            "用語集を生成中...".to_string(),
+        );
+        match generate_glossary(
+            &final_processed_document,
+            &gemini,
+            &settings.gemini_model,
+            &settings.language,
+            &settings.gemini_api_version,
+            &settings.safety_settings,
+        )
+        .await
+        {
+            Ok(glossary) => {
+                println!(
+                    "📖 [BACKEND] Generated glossary with {} terms",
+                    count_table_rows(&glossary)
+                );
+                Some(glossary)
+            }
+            Err(e) => {
+                println!("⚠️ [BACKEND] Failed to generate glossary: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
 
-    // Encrypt sensitive data before saving
-    let safe_settings = AppSettings {
-        gemini_api_key: encrypt_api_key(&settings.gemini_api_key),
-        language: settings.language,
-        temperature: settings.temperature,
-        custom_prompt: settings.custom_prompt,
-        gemini_model: settings.gemini_model,
-        embed_images: settings.embed_images,
-        image_embed_frequency: settings.image_embed_frequency,
-        video_quality: settings.video_quality,
-        hardware_encoding: settings.hardware_encoding,
-        enable_experimental_features: settings.enable_experimental_features,
-        frame_extraction_method: settings.frame_extraction_method,
+    // Adds the glossary, the links back to the source videos (above the summary and below
+    // a top watermark) and the disclaimer, which goes on the final document only
+    let add_surrounding_blocks = |body: &str| {
+        let document = match glossary.as_deref() {
+            Some(glossary) => format!("{}\n\n## Glossary\n\n{}\n", body.trim_end(), glossary),
+            None => body.to_string(),
+        };
+        let document = match settings.local_file_base_url.as_deref() {
+            Some(base_url) if settings.embed_video_link && !base_url.trim().is_empty() => {
+                format!("{}\n\n{}", source_video_links(&files, base_url), document)
+            }
+            _ => document,
+        };
+        match settings.document_watermark_text.as_deref() {
+            Some(watermark_text) if !watermark_text.trim().is_empty() => apply_document_watermark(
+                &document,
+                watermark_text,
+                &settings.document_watermark_position,
+            ),
+            _ => document,
+        }
+    };
+    let assembled_document = add_surrounding_blocks(&final_processed_document);
+
+    // Warn when the document is longer than the page limit of the destination tool.
+    // Only the body is truncated so that the disclaimer and the other blocks are kept.
+    let final_processed_document = match settings.max_document_size_chars {
+        Some(limit) if assembled_document.chars().count() > limit => {
+            let warning = DocumentSizeWarning {
+                actual: assembled_document.chars().count(),
+                limit,
+            };
+            println!(
+                "⚠️ [BACKEND] Document has {} characters, exceeding the limit of {}",
+                warning.actual, warning.limit
+            );
+            if let Err(e) = app.emit("document_size_warning", &warning) {
+                println!("❌ [EVENT] Failed to emit document_size_warning: {}", e);
+            }
+            if settings.truncate_at_limit {
+                let reserved = warning.actual - final_processed_document.chars().count();
+                add_surrounding_blocks(&truncate_at_section_boundary(
+                    &final_processed_document,
+                    limit.saturating_sub(reserved),
+                ))
+            } else {
+                assembled_document
+            }
+        }
+        _ => assembled_document,
     };
 
-    let config_json = serde_json::to_string_pretty(&safe_settings)
-        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    // Headings deeper than the configured level are flattened
+    let final_processed_document =
+        cap_heading_depth(&final_processed_document, settings.max_heading_depth);
 
-    fs::write(&config_path, config_json)
-        .map_err(|e| format!("Failed to write settings file: {}", e))?;
+    // Consistent code block language identifiers
+    let final_processed_document = if settings.preferred_code_languages.is_empty() {
+        final_processed_document
+    } else {
+        normalise_code_blocks(
+            &final_processed_document,
+            &settings.preferred_code_languages,
+        )
+    };
 
-    // println!("Settings saved successfully to: {:?}", config_path);
-    Ok(())
-}
+    // Make ordered lists follow the configured numbering style
+    let final_processed_document = if settings.document_numbering_style.format_number(1).is_some() {
+        normalize_list_numbering(
+            &final_processed_document,
+            &settings.document_numbering_style,
+        )
+    } else {
+        final_processed_document
+    };
+
+    // Fix common Markdown lint issues so the document renders the same in downstream tools
+    let final_processed_document = if settings.enable_document_linting {
+        let (linted_document, lint_issues) = lint_markdown(&final_processed_document);
+        println!(
+            "🧹 [BACKEND] Markdown lint found {} issues",
+            lint_issues.len()
+        );
+        if let Err(e) = app.emit("document_lint_report", &lint_issues) {
+            println!("❌ [EVENT] Failed to emit document_lint_report: {}", e);
+        }
+        linted_document
+    } else {
+        final_processed_document
+    };
+
+    // Suggest a title based on the content when it is used for the file name
+    // (failures only skip the suggestion)
+    let suggested_title = if settings.use_ai_title {
+        match generate_document_title(
+            &final_processed_document,
+            &gemini,
+            &settings.gemini_model,
+            &settings.gemini_api_version,
+            &settings.safety_settings,
+        )
+        .await
+        {
+            Ok(title) => {
+                println!("🏷️ [BACKEND] Suggested document title: {}", title);
+                if let Err(e) = app.emit("document_title_suggestion", &title) {
+                    println!("❌ [EVENT] Failed to emit document_title_suggestion: {}", e);
+                }
+                Some(title)
+            }
+            Err(e) => {
+                println!("⚠️ [BACKEND] Failed to generate document title: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // Remember the custom prompt so it can be picked again from the history;
+    // a non-empty custom prompt is used under every format preset
+    if record_prompt {
+        if let Some(prompt) = settings
+            .custom_prompt
+            .as_deref()
+            .filter(|prompt| !prompt.trim().is_empty())
+        {
+            if let Err(e) = record_recent_prompt(prompt, &app).await {
+                println!("⚠️ [BACKEND] Failed to record recent prompt: {}", e);
+            }
+        }
+    }
+
+    // Same base name as the Markdown file saved by the frontend
+    let document_basename = match suggested_title.as_deref() {
+        Some(title) => sanitize_filename(title),
+        _ => files
+            .first()
+            .and_then(|f| Path::new(&f.name).file_stem())
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("document")
+            .to_string(),
+    };
+
+    // Flashcards are optional, failures only skip them
+    if settings.generate_flashcards {
+        emit_progress(
+            &app,
+            total_steps,
+            total_steps,
+            "フラッシュカードを生成中...".to_string(),
+        );
+        match generate_flashcards_from_document(
+            &final_processed_document,
+            &gemini,
+            &settings.gemini_model,
+            &settings.language,
+            &settings.gemini_api_version,
+            &settings.safety_settings,
+        )
+        .await
+        {
+            Ok(flashcards) => {
+                match save_flashcards(&flashcards, &save_directory, &document_basename) {
+                    Ok(path) => {
+                        println!(
+                            "🃏 [BACKEND] Saved {} flashcards to {}",
+                            flashcards.len(),
+                            path
+                        );
+                        if let Err(e) = app.emit("flashcards_ready", flashcards.len()) {
+                            println!("❌ [EVENT] Failed to emit flashcards_ready: {}", e);
+                        }
+                    }
+                    Err(e) => println!("⚠️ [BACKEND] {}", e),
+                }
+            }
+            Err(e) => println!("⚠️ [BACKEND] Failed to generate flashcards: {}", e),
+        }
+    }
+
+    // Uploads are only removed after a successful run so that a failed job can be inspected
+    if !settings.keep_uploaded_files && !file_uris.is_empty() {
+        delete_job_uploads(&file_uris, &gemini, &app).await;
+    }
+
+    // Save each section as a separate file and return the index path
+    if settings.split_output_per_section {
+        let index_path = save_split_document(
+            &final_processed_document,
+            &save_directory,
+            &document_basename,
+            &settings,
+            &app,
+        )
+        .await?;
+
+        emit_progress(
+            &app,
+            total_steps,
+            total_steps,
+            "ドキュメント生成が完了しました！".to_string(),
+        );
+        println!(
+            "🎉 [BACKEND] Document saved per section, index: {}",
+            index_path
+        );
+        send_usage_telemetry(&settings, &files);
+        notify_generation_complete(&settings.notification_sound, &app);
+        return Ok(index_path);
+    }
+
+    emit_progress(
+        &app,
+        total_steps,
+        total_steps,
+        "ドキュメント生成が完了しました！".to_string(),
+    );
+    println!(
+        "🎉 [BACKEND] Document generation completed successfully (final length: {})",
+        final_processed_document.len()
+    );
+    send_usage_telemetry(&settings, &files);
+    notify_generation_complete(&settings.notification_sound, &app);
+    Ok(final_processed_document)
+}
+
+/// Sends anonymous usage statistics in the background when the user opted in.
+/// Failures are only logged, telemetry never affects the job.
+fn send_usage_telemetry(settings: &AppSettings, files: &[VideoFile]) {
+    let endpoint = settings.telemetry_endpoint.trim().to_string();
+    if !settings.enable_telemetry || endpoint.is_empty() {
+        return;
+    }
+
+    let model = match settings.ai_backend {
+        AiBackend::Gemini => &settings.gemini_model,
+        AiBackend::Claude => &settings.claude_model,
+    };
+    let model_name_hash = format!("{:x}", Sha256::digest(model.as_bytes()));
+    let language = settings.language.as_str().to_string();
+    let has_custom_prompt = settings
+        .custom_prompt
+        .as_deref()
+        .is_some_and(|prompt| !prompt.trim().is_empty())
+        || files.iter().any(|f| f.custom_prompt_override.is_some());
+    let paths: Vec<String> = files.iter().map(|f| f.path.clone()).collect();
+    let timeouts = ProcessTimeouts::from_settings(settings);
+
+    tokio::spawn(async move {
+        let mut total_duration_seconds = 0.0;
+        for path in &paths {
+            if let Ok(duration) = get_video_duration(path, &timeouts).await {
+                total_duration_seconds += duration;
+            }
+        }
+        let payload = TelemetryPayload {
+            model_name_hash,
+            language,
+            file_count: paths.len() as u32,
+            total_duration_seconds,
+            has_custom_prompt,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+        };
+
+        let client = reqwest::Client::new();
+        match client
+            .post(&endpoint)
+            .timeout(std::time::Duration::from_secs(10))
+            .json(&payload)
+            .send()
+            .await
+        {
+            Ok(response) => println!(
+                "📊 [TELEMETRY] Sent usage statistics ({})",
+                response.status()
+            ),
+            Err(e) => println!("⚠️ [TELEMETRY] Failed to send usage statistics: {}", e),
+        }
+    });
+}
+
+/// Deletes the files uploaded during a job and reports the deleted names
+async fn delete_job_uploads(file_uris: &[String], gemini: &GeminiClient, app: &tauri::AppHandle) {
+    let mut deleted_files = Vec::new();
+    for uri in file_uris {
+        match delete_uploaded_gemini_file(uri, gemini).await {
+            Ok(()) => deleted_files.push(gemini_file_name(uri)),
+            Err(e) => println!("⚠️ [BACKEND] Failed to delete uploaded file {}: {}", uri, e),
+        }
+    }
+
+    if !deleted_files.is_empty() {
+        println!(
+            "🗑️ [BACKEND] Deleted {} uploaded files from Gemini",
+            deleted_files.len()
+        );
+        if let Err(e) = app.emit("files_deleted_from_gemini", &deleted_files) {
+            println!("❌ [EVENT] Failed to emit files_deleted_from_gemini: {}", e);
+        }
+    }
+}
+
+/// Counts the input tokens for already uploaded files and a prompt.
+/// `file_paths` are the local files behind `file_uris`, used to detect their MIME types.
+#[tauri::command]
+async fn count_tokens_for_files(
+    file_paths: Vec<String>,
+    file_uris: Vec<String>,
+    prompt: String,
+    settings: AppSettings,
+) -> Result<u64, String> {
+    if file_paths.len() != file_uris.len() {
+        return Err("Every uploaded file needs its local path".to_string());
+    }
+    let timeouts = ProcessTimeouts::from_settings(&settings);
+    let mut files = Vec::with_capacity(file_uris.len());
+    for (file_path, file_uri) in file_paths.iter().zip(&file_uris) {
+        files.push(
+            uploaded_file_data(file_path, file_uri, &timeouts)
+                .await
+                .map_err(|e| format!("Failed to detect MIME type: {}", e))?,
+        );
+    }
+    count_gemini_tokens(
+        &files,
+        &prompt,
+        &GeminiClient::from_settings(&settings),
+        &settings.gemini_model,
+    )
+    .await
+    .map_err(|e| format!("Failed to count tokens: {}", e))
+}
+
+/// Deletes every file uploaded to Gemini with this API key
+#[tauri::command]
+async fn delete_all_uploaded_files(api_key: String, app: tauri::AppHandle) -> Result<u32, String> {
+    let deleted_files = delete_all_uploaded_gemini_files(&GeminiClient::new(&api_key))
+        .await
+        .map_err(|e| format!("Failed to delete uploaded files: {}", e))?;
+    println!(
+        "🗑️ [BACKEND] Deleted all {} uploaded files",
+        deleted_files.len()
+    );
+    if !deleted_files.is_empty() {
+        if let Err(e) = app.emit("files_deleted_from_gemini", &deleted_files) {
+            println!("❌ [EVENT] Failed to emit files_deleted_from_gemini: {}", e);
+        }
+    }
+    Ok(deleted_files.len() as u32)
+}
+
+/// Generates a document from slides exported as an image sequence
+/// The images are assembled into a temporary video that goes through the normal pipeline
+#[tauri::command]
+async fn generate_document_from_image_sequence(
+    image_dir: String,
+    settings: AppSettings,
+    save_directory: String,
+    app: tauri::AppHandle,
+) -> Result<String, String> {
+    let images = list_image_sequence(&image_dir)?;
+    if images.is_empty() {
+        return Err(format!("No .jpg/.jpeg/.png images found in {}", image_dir));
+    }
+
+    println!(
+        "🖼️ [BACKEND] Assembling {} slides from {}",
+        images.len(),
+        image_dir
+    );
+    let progress = ProgressUpdate::new(
+        format!("スライドシーケンスを処理中 ({} slides)", images.len()),
+        0,
+        0,
+        Instant::now(),
+    );
+    if let Err(e) = app.emit("progress_update", &progress) {
+        println!("❌ [EVENT] Failed to emit progress event: {}", e);
+    }
+
+    let dir_name = Path::new(&image_dir)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("slides")
+        .to_string();
+    // Kept alive until generation finishes, the frames are extracted from this video
+    let temp_dir =
+        tempfile::tempdir().map_err(|e| format!("Failed to create temporary directory: {}", e))?;
+    let video_path = temp_dir.path().join(format!("{}.mp4", dir_name));
+    assemble_image_sequence(
+        &images,
+        settings.slide_frame_rate,
+        &video_path,
+        &ProcessTimeouts::from_settings(&settings),
+    )
+    .await
+    .map_err(|e| format!("Failed to assemble image sequence: {}", e))?;
+
+    let size = fs::metadata(&video_path)
+        .map_err(|e| format!("Failed to read assembled video: {}", e))?
+        .len();
+    let video_file = VideoFile {
+        path: video_path.to_string_lossy().to_string(),
+        name: format!("{}.mp4", dir_name),
+        size,
+        subtitle_path: None,
+        start_offset_seconds: None,
+        end_offset_seconds: None,
+        custom_prompt_override: None,
+        sha256_first_mb: None,
+    };
+
+    generate_document(vec![video_file], settings, save_directory, app).await
+}
+
+/// Generates a document from a screen recording of a software tutorial
+/// Same pipeline as `generate_document`, with UI-oriented instructions and detailed screenshots
+#[tauri::command]
+async fn generate_document_from_screen_recording(
+    files: Vec<VideoFile>,
+    mut settings: AppSettings,
+    save_directory: String,
+    app: tauri::AppHandle,
+) -> Result<String, String> {
+    println!("🖥️ [BACKEND] Generating document from screen recording");
+    settings.custom_prompt = Some(screen_recording_prompt(&settings));
+    settings.format_preset = DocumentFormatPreset::Custom;
+    // Screenshots are essential for screen recording documentation
+    settings.embed_images = true;
+    settings.image_embed_frequency = ImageEmbedFrequency::Detailed;
+
+    // The synthesized instructions are not something the user would pick from the history
+    run_document_generation(files, settings, save_directory, app, false).await
+}
+
+/// Generates a document from files that were already uploaded to the Gemini File API.
+/// The uploads are kept so the same URIs can be reused with other prompts.
+#[tauri::command]
+async fn generate_document_from_uri(
+    file_uris: Vec<String>,
+    settings: AppSettings,
+    save_directory: String,
+    app: tauri::AppHandle,
+) -> Result<String, String> {
+    validate_settings(&settings)?;
+    let gemini = GeminiClient::from_settings(&settings);
+    if file_uris.is_empty() {
+        return Err("No Gemini file URIs were provided".to_string());
+    }
+    println!(
+        "♻️ [BACKEND] Generating document from {} uploaded files",
+        file_uris.len()
+    );
+
+    let started_at = Instant::now();
+    let total_steps = 2;
+    let emit_progress = |step: usize, message: String| {
+        let progress = ProgressUpdate::new(message, step, total_steps, started_at);
+        if let Err(e) = app.emit("progress_update", &progress) {
+            println!("❌ [EVENT] Failed to emit progress event: {}", e);
+        }
+    };
+
+    emit_progress(1, "アップロード済みファイルを確認中...".to_string());
+    for uri in &file_uris {
+        ensure_gemini_file_active(uri, &gemini)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    emit_progress(total_steps, "AIでドキュメントを生成中...".to_string());
+    let document = generate_with_gemini_with_progress(
+        &GenerationOptions {
+            embed_images: false,
+            ..GenerationOptions::from_settings(&file_uris, &settings)
+        },
+        &settings,
+        &gemini,
+        None,
+        &app,
+        total_steps,
+        total_steps,
+        started_at,
+    )
+    .await
+    .map_err(|e| format!("Failed to generate document: {}", e))?;
+
+    let document = if settings.strip_ai_preamble {
+        strip_ai_preamble(&document)
+    } else {
+        document
+    };
+    let document = cap_heading_depth(&document, settings.max_heading_depth);
+    let document = if settings.preferred_code_languages.is_empty() {
+        document
+    } else {
+        normalise_code_blocks(&document, &settings.preferred_code_languages)
+    };
+
+    if settings.split_output_per_section {
+        let index_path =
+            save_split_document(&document, &save_directory, "document", &settings, &app).await?;
+        println!(
+            "🎉 [BACKEND] Document saved per section, index: {}",
+            index_path
+        );
+        notify_generation_complete(&settings.notification_sound, &app);
+        return Ok(index_path);
+    }
+
+    emit_progress(total_steps, "ドキュメント生成が完了しました！".to_string());
+    notify_generation_complete(&settings.notification_sound, &app);
+    Ok(document)
+}
+
+/// Generates a changelog by comparing an old and a new recording of the same software
+/// The changelog is saved to `save_directory` and its content is returned
+#[tauri::command]
+async fn generate_document_diff_from_versions(
+    old_video: VideoFile,
+    new_video: VideoFile,
+    settings: AppSettings,
+    save_directory: String,
+    app: tauri::AppHandle,
+) -> Result<String, String> {
+    validate_settings(&settings)?;
+    let gemini = GeminiClient::from_settings(&settings);
+    let timeouts = ProcessTimeouts::from_settings(&settings);
+    println!(
+        "🔀 [BACKEND] Comparing versions: {} -> {}",
+        old_video.name, new_video.name
+    );
+
+    let started_at = Instant::now();
+    let total_steps = 3;
+    let mut file_uris = Vec::new();
+    for (index, video) in [&old_video, &new_video].into_iter().enumerate() {
+        ensure_file_unchanged(video, &app).await?;
+        let uri = upload_to_gemini_with_progress(
+            &video.path,
+            &gemini,
+            settings.upload_chunk_size_mb.max(1) as usize * 1024 * 1024,
+            settings.upload_bandwidth_limit_kbps,
+            &settings.progress_verbosity,
+            &timeouts,
+            &app,
+            index + 1,
+            total_steps,
+            started_at,
+        )
+        .await
+        .map_err(|e| format!("Failed to upload file {}: {}", video.name, e))?;
+        file_uris.push(uri);
+    }
+
+    let context_cache_files = if settings.enable_context_cache {
+        let mut files = Vec::with_capacity(file_uris.len());
+        for (video, uri) in [&old_video, &new_video].into_iter().zip(&file_uris) {
+            files.push(
+                uploaded_file_data(&video.path, uri, &timeouts)
+                    .await
+                    .map_err(|e| format!("Failed to detect MIME type: {}", e))?,
+            );
+        }
+        Some(files)
+    } else {
+        None
+    };
+
+    let prompt = version_diff_prompt(&settings.language);
+    let changelog = generate_with_gemini_with_progress(
+        &GenerationOptions {
+            custom_prompt: Some(&prompt),
+            embed_images: false,
+            structured_output: false,
+            search_grounding: false,
+            ..GenerationOptions::from_settings(&file_uris, &settings)
+        },
+        &settings,
+        &gemini,
+        context_cache_files.as_deref(),
+        &app,
+        total_steps,
+        total_steps,
+        started_at,
+    )
+    .await
+    .map_err(|e| format!("Failed to generate changelog: {}", e))?;
+
+    if !settings.keep_uploaded_files {
+        delete_job_uploads(&file_uris, &gemini, &app).await;
+    }
+
+    let new_name = Path::new(&new_video.name)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("document");
+    let filename = format!("{}_changelog.md", sanitize_filename(new_name));
+    let saved_path = save_document_to_file(
+        changelog.clone(),
+        save_directory,
+        filename,
+        Some(settings),
+        app.clone(),
+    )
+    .await?;
+    println!("🎉 [BACKEND] Changelog saved to {}", saved_path);
+
+    Ok(changelog)
+}
+
+/// Classifies the first 30 seconds of the selected range of a video to pick a format preset
+/// Falls back to `UserManual` when the clip cannot be classified
+async fn detect_document_type(
+    file: &VideoFile,
+    settings: &AppSettings,
+    app: &tauri::AppHandle,
+) -> DocumentFormatPreset {
+    println!("🔎 [BACKEND] Detecting document type from {}", file.name);
+    let progress = ProgressUpdate::new("動画の種類を判定中...".to_string(), 0, 0, Instant::now());
+    if let Err(e) = app.emit("progress_update", &progress) {
+        println!("❌ [EVENT] Failed to emit progress event: {}", e);
+    }
+
+    let detected = match classify_video_start(file, settings, app).await {
+        Ok(word) => word,
+        Err(e) => {
+            println!("⚠️ [BACKEND] Failed to detect document type: {}", e);
+            String::new()
+        }
+    };
+    let preset = DocumentFormatPreset::from_classification(&detected)
+        .unwrap_or(DocumentFormatPreset::UserManual);
+    println!(
+        "🔎 [BACKEND] Detected document type '{}', using preset {}",
+        detected,
+        preset.as_str()
+    );
+
+    let detection = DocumentTypeDetection {
+        detected,
+        preset: preset.as_str().to_string(),
+    };
+    if let Err(e) = app.emit("document_type_detected", &detection) {
+        println!("❌ [EVENT] Failed to emit document_type_detected: {}", e);
+    }
+    preset
+}
+
+async fn classify_video_start(
+    file: &VideoFile,
+    settings: &AppSettings,
+    app: &tauri::AppHandle,
+) -> anyhow::Result<String> {
+    let gemini = GeminiClient::from_settings(settings);
+    let timeouts = ProcessTimeouts::from_settings(settings);
+    // Classify the first 30 seconds of the range that will actually be documented
+    let start = file.start_offset_seconds.unwrap_or(0.0);
+    let end = match file.end_offset_seconds {
+        Some(end) => end,
+        None => get_video_duration(&file.path, &timeouts).await?,
+    };
+    // The clip is removed together with the temporary directory
+    let clip_dir = tempfile::tempdir()?;
+    let clip_path = trim_video(
+        &file.path,
+        file.start_offset_seconds,
+        Some(end.min(start + 30.0)),
+        clip_dir.path(),
+        &timeouts,
+    )
+    .await?;
+    let clip_path = clip_path.to_string_lossy().to_string();
+    let mime_type = detect_mime_type(&clip_path, &timeouts).await?;
+
+    let uri = upload_to_gemini_with_progress(
+        &clip_path,
+        &gemini,
+        settings.upload_chunk_size_mb.max(1) as usize * 1024 * 1024,
+        settings.upload_bandwidth_limit_kbps,
+        &settings.progress_verbosity,
+        &timeouts,
+        app,
+        0,
+        0,
+        Instant::now(),
+    )
+    .await?;
+    let classification = classify_video_type(
+        &GeminiFileData {
+            mime_type,
+            file_uri: uri.clone(),
+        },
+        &gemini,
+        &settings.gemini_model,
+        &settings.gemini_api_version,
+        &settings.safety_settings,
+    )
+    .await;
+
+    if !settings.keep_uploaded_files {
+        if let Err(e) = delete_uploaded_gemini_file(&uri, &gemini).await {
+            println!("⚠️ [BACKEND] Failed to delete classification clip: {}", e);
+        }
+    }
+    classification
+}
+
+/// Compares the first megabyte of a file with the hash taken when it was selected
+/// and aborts when the file has been changed, e.g. because it was still being copied
+async fn ensure_file_unchanged(file: &VideoFile, app: &tauri::AppHandle) -> Result<(), String> {
+    let Some(expected_hash) = file.sha256_first_mb.as_deref() else {
+        return Ok(());
+    };
+
+    let current_hash = hash_first_megabyte(&file.path)
+        .await
+        .map_err(|e| format!("Failed to read file {}: {}", file.name, e))?;
+
+    if current_hash != expected_hash {
+        println!(
+            "❌ [BACKEND] File was modified during processing: {}",
+            file.name
+        );
+        if let Err(e) = app.emit("file_modified_during_processing", &file.name) {
+            println!(
+                "❌ [EVENT] Failed to emit file_modified_during_processing: {}",
+                e
+            );
+        }
+        return Err(format!(
+            "File {} was modified during processing. Wait until the file has been completely written and select it again.",
+            file.name
+        ));
+    }
+
+    Ok(())
+}
+
+/// Generates a short executive summary of a document and emits it as `summary_ready`
+#[tauri::command]
+async fn generate_executive_summary(
+    document: String,
+    settings: AppSettings,
+    app: tauri::AppHandle,
+) -> Result<String, String> {
+    println!(
+        "📋 [SUMMARY] Generating executive summary ({} characters)",
+        document.len()
+    );
+    let summary = summarize_document(
+        &document,
+        &GeminiClient::from_settings(&settings),
+        settings.temperature,
+        &settings.gemini_model,
+        &settings.gemini_api_version,
+        &settings.safety_settings,
+    )
+    .await
+    .map_err(|e| format!("Failed to generate executive summary: {}", e))?;
+
+    if let Err(e) = app.emit("summary_ready", &summary) {
+        println!("❌ [EVENT] Failed to emit summary_ready: {}", e);
+    }
+
+    Ok(summary)
+}
+
+#[tauri::command]
+async fn get_video_audio_track_info(video_path: String) -> Result<Vec<AudioTrackInfo>, String> {
+    probe_audio_tracks(&video_path, &ProcessTimeouts::default())
+        .await
+        .map_err(|e| format!("Failed to get audio track info: {}", e))
+}
+
+#[tauri::command]
+async fn get_stream_list(video_path: String) -> Result<Vec<StreamInfo>, String> {
+    probe_streams(&video_path, &ProcessTimeouts::default())
+        .await
+        .map_err(|e| format!("Failed to get stream list: {}", e))
+}
+
+/// Checks that the selected video files are readable and not corrupted
+#[tauri::command]
+async fn validate_video_files(files: Vec<VideoFile>) -> Result<Vec<VideoValidationResult>, String> {
+    let mut results = Vec::new();
+    for file in &files {
+        let result = validate_video_file(file, &ProcessTimeouts::default()).await;
+        if let Some(error) = &result.error {
+            println!("❌ [VALIDATION] {}: {}", file.name, error);
+        }
+        results.push(result);
+    }
+    Ok(results)
+}
+
+/// Rejects sampling parameters that the Gemini API would refuse with a 400 error
+fn validate_settings(settings: &AppSettings) -> Result<(), String> {
+    if !(0.0..=2.0).contains(&settings.temperature) {
+        return Err("Temperature must be between 0.0 and 2.0".to_string());
+    }
+    if let Some(top_p) = settings.top_p {
+        if !(0.0..=1.0).contains(&top_p) {
+            return Err("Top-p must be between 0.0 and 1.0".to_string());
+        }
+    }
+    if let Some(top_k) = settings.top_k {
+        if !(1..=100).contains(&top_k) {
+            return Err("Top-k must be between 1 and 100".to_string());
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn save_settings(mut settings: AppSettings, app: tauri::AppHandle) -> Result<(), String> {
+    let _guard = SETTINGS_FILE_LOCK.lock().await;
+    // The prompt history is maintained by the backend, so a stale copy sent by the
+    // frontend must not drop prompts recorded while a generation was running
+    if let Ok(Some(stored)) = load_settings(app.clone()).await {
+        settings.recent_prompts = stored.recent_prompts;
+    }
+    write_settings(settings, &app)
+}
+
+/// Writes the settings file; callers hold `SETTINGS_FILE_LOCK`
+fn write_settings(settings: AppSettings, app: &tauri::AppHandle) -> Result<(), String> {
+    validate_settings(&settings)?;
+    if let Some(template_path) = settings
+        .html_report_template
+        .as_deref()
+        .filter(|path| !path.trim().is_empty())
+    {
+        validate_html_report_template(template_path)?;
+    }
+    // println!("save_settings called with: {:?}", settings);
+    let config_path = get_config_file_path(app)?;
+    // println!("Config path: {:?}", config_path);
+
+    // Ensure the parent directory exists
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    // Encrypt sensitive data before saving
+    let safe_settings = AppSettings {
+        gemini_api_key: encrypt_api_key(&settings.gemini_api_key),
+        language: settings.language,
+        temperature: settings.temperature,
+        custom_prompt: settings.custom_prompt,
+        gemini_model: settings.gemini_model,
+        embed_images: settings.embed_images,
+        image_embed_frequency: settings.image_embed_frequency,
+        video_quality: settings.video_quality,
+        hardware_encoding: settings.hardware_encoding,
+        enable_experimental_features: settings.enable_experimental_features,
+        frame_extraction_method: settings.frame_extraction_method,
+        post_generation_hook: settings.post_generation_hook,
+        max_upload_bytes: settings.max_upload_bytes,
+        audio_track_index: settings.audio_track_index,
+        structured_output: settings.structured_output,
+        video_watermark: settings.video_watermark,
+        translate_output_to: settings.translate_output_to,
+        split_mode: settings.split_mode,
+        thinking_budget: settings.thinking_budget,
+        reference_documents: settings.reference_documents,
+        output_encoding: settings.output_encoding,
+        stabilize_video: settings.stabilize_video,
+        split_output_per_section: settings.split_output_per_section,
+        two_pass_encoding: settings.two_pass_encoding,
+        enable_search_grounding: settings.enable_search_grounding,
+        format_preset: settings.format_preset,
+        subtitle_burn_in: settings.subtitle_burn_in,
+        save_intermediate_files: settings.save_intermediate_files,
+        concurrent_frame_extraction: settings.concurrent_frame_extraction,
+        max_concurrent_frame_extractions: settings.max_concurrent_frame_extractions,
+        document_watermark_text: settings.document_watermark_text,
+        document_watermark_position: settings.document_watermark_position,
+        auto_open_document: settings.auto_open_document,
+        use_ai_title: settings.use_ai_title,
+        gemini_api_version: settings.gemini_api_version,
+        auto_generate_summary: settings.auto_generate_summary,
+        frame_output_format: settings.frame_output_format,
+        ffmpeg_extra_input_args: settings.ffmpeg_extra_input_args,
+        ffmpeg_extra_output_args: settings.ffmpeg_extra_output_args,
+        output_dir_per_job: settings.output_dir_per_job,
+        enable_document_linting: settings.enable_document_linting,
+        video_stream_index: settings.video_stream_index,
+        recent_prompts: settings.recent_prompts,
+        upload_chunk_size_mb: settings.upload_chunk_size_mb,
+        generate_flashcards: settings.generate_flashcards,
+        ffprobe_timeout_seconds: settings.ffprobe_timeout_seconds,
+        ffmpeg_timeout_seconds: settings.ffmpeg_timeout_seconds,
+        slide_frame_rate: settings.slide_frame_rate,
+        safety_settings: settings.safety_settings,
+        generate_glossary: settings.generate_glossary,
+        max_document_size_chars: settings.max_document_size_chars,
+        truncate_at_limit: settings.truncate_at_limit,
+        embed_video_link: settings.embed_video_link,
+        local_file_base_url: settings.local_file_base_url,
+        top_p: settings.top_p,
+        top_k: settings.top_k,
+        document_numbering_style: settings.document_numbering_style,
+        video_sampling_fps: settings.video_sampling_fps,
+        ai_backend: settings.ai_backend,
+        claude_api_key: encrypt_api_key(&settings.claude_api_key),
+        claude_model: settings.claude_model,
+        integration_strategy: settings.integration_strategy,
+        keep_uploaded_files: settings.keep_uploaded_files,
+        notification_sound: settings.notification_sound,
+        chunk_long_prompts: settings.chunk_long_prompts,
+        max_prompt_tokens: settings.max_prompt_tokens,
+        image_grid_mode: settings.image_grid_mode,
+        image_grid_columns: settings.image_grid_columns,
+        auto_detect_document_type: settings.auto_detect_document_type,
+        max_heading_depth: settings.max_heading_depth,
+        strip_ai_preamble: settings.strip_ai_preamble,
+        preferred_code_languages: settings.preferred_code_languages,
+        progress_verbosity: settings.progress_verbosity,
+        add_utf8_bom: settings.add_utf8_bom,
+        gemini_request_timeout_seconds: settings.gemini_request_timeout_seconds,
+        gemini_generation_timeout_seconds: settings.gemini_generation_timeout_seconds,
+        enable_telemetry: settings.enable_telemetry,
+        telemetry_endpoint: settings.telemetry_endpoint,
+        telemetry_prompt_shown: settings.telemetry_prompt_shown,
+        generate_image_captions: settings.generate_image_captions,
+        html_report_template: settings.html_report_template,
+        keyframe_aligned_extraction: settings.keyframe_aligned_extraction,
+        model_fallback_chain: settings.model_fallback_chain,
+        segment_overlap_seconds: settings.segment_overlap_seconds,
+        prevent_duplicate_presets: settings.prevent_duplicate_presets,
+        progress_event_debounce_ms: settings.progress_event_debounce_ms,
+        enable_context_cache: settings.enable_context_cache,
+        upload_bandwidth_limit_kbps: settings.upload_bandwidth_limit_kbps,
+        chapter_split_min_duration_seconds: settings.chapter_split_min_duration_seconds,
+        line_ending: settings.line_ending,
+    };
+
+    let config_json = serde_json::to_string_pretty(&safe_settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+    fs::write(&config_path, config_json)
+        .map_err(|e| format!("Failed to write settings file: {}", e))?;
+
+    // println!("Settings saved successfully to: {:?}", config_path);
+    Ok(())
+}
 
 #[tauri::command]
 async fn load_settings(app: tauri::AppHandle) -> Result<Option<AppSettings>, String> {
@@ -437,16 +1924,145 @@ async fn load_settings(app: tauri::AppHandle) -> Result<Option<AppSettings>, Str
     let config_content = fs::read_to_string(&config_path)
         .map_err(|e| format!("Failed to read settings file: {}", e))?;
 
-    let mut settings: AppSettings = serde_json::from_str(&config_content)
+    let raw_settings: serde_json::Value = serde_json::from_str(&config_content)
+        .map_err(|e| format!("Failed to parse settings file: {}", e))?;
+    let migrations = detect_settings_migrations(&raw_settings);
+
+    let mut settings: AppSettings = serde_json::from_value(raw_settings.clone())
         .map_err(|e| format!("Failed to parse settings file: {}", e))?;
 
+    for migration in &migrations {
+        println!(
+            "🔄 [SETTINGS] Migrated legacy value {} for {}",
+            migration.original_value, migration.field
+        );
+        if let Err(e) = app.emit("settings_migrated", migration) {
+            println!("❌ [EVENT] Failed to emit settings_migrated: {}", e);
+        }
+    }
+
     // Decrypt sensitive data after loading
-    settings.gemini_api_key = decrypt_api_key(&settings.gemini_api_key);
+    let (gemini_api_key, stored_in_plaintext) =
+        load_stored_gemini_api_key(&settings.gemini_api_key);
+    settings.gemini_api_key = gemini_api_key;
+    settings.claude_api_key = decrypt_api_key(&settings.claude_api_key);
+
+    // Plaintext keys from older versions are written back encrypted
+    if stored_in_plaintext {
+        println!("🔐 [SETTINGS] Encrypting Gemini API key stored in plaintext");
+        let mut migrated_settings = raw_settings;
+        migrated_settings["gemini_api_key"] =
+            serde_json::Value::String(encrypt_api_key(&settings.gemini_api_key));
+        let json = serde_json::to_string_pretty(&migrated_settings)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        fs::write(&config_path, json)
+            .map_err(|e| format!("Failed to write settings file: {}", e))?;
+        if let Err(e) = app.emit("settings_migration_performed", "gemini_api_key") {
+            println!(
+                "❌ [EVENT] Failed to emit settings_migration_performed: {}",
+                e
+            );
+        }
+    }
 
     // println!("Loaded and decrypted settings: {:?}", settings);
     Ok(Some(settings))
 }
 
+/// Restores the default settings, keeping the API key unless `clear_api_key` is set
+#[tauri::command]
+async fn reset_settings_to_defaults(
+    clear_api_key: bool,
+    app: tauri::AppHandle,
+) -> Result<AppSettings, String> {
+    let _guard = SETTINGS_FILE_LOCK.lock().await;
+    let gemini_api_key = if clear_api_key {
+        String::new()
+    } else {
+        load_settings(app.clone())
+            .await?
+            .map(|settings| settings.gemini_api_key)
+            .unwrap_or_default()
+    };
+
+    let defaults = AppSettings::defaults_with_api_key(gemini_api_key);
+    write_settings(defaults.clone(), &app)?;
+    println!("🔄 [BACKEND] Settings reset to defaults");
+
+    if let Err(e) = app.emit("settings_reset", "settings") {
+        println!("❌ [EVENT] Failed to emit settings_reset: {}", e);
+    }
+
+    Ok(defaults)
+}
+
+#[tauri::command]
+async fn add_reference_document(path: String, app: tauri::AppHandle) -> Result<(), String> {
+    let file_path = Path::new(&path);
+    if !file_path.is_file() {
+        return Err(format!("Reference document not found: {}", path));
+    }
+
+    let extension = file_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    if !SUPPORTED_REFERENCE_EXTENSIONS.contains(&extension.as_str()) {
+        return Err(format!(
+            "Unsupported reference document type: {} (supported: {})",
+            path,
+            SUPPORTED_REFERENCE_EXTENSIONS.join(", ")
+        ));
+    }
+
+    let _guard = SETTINGS_FILE_LOCK.lock().await;
+    let mut settings = load_settings(app.clone())
+        .await?
+        .ok_or_else(|| "Settings have not been saved yet".to_string())?;
+
+    if !settings.reference_documents.contains(&path) {
+        settings.reference_documents.push(path);
+        write_settings(settings, &app)?;
+    }
+
+    Ok(())
+}
+
+/// Moves the prompt to the top of the saved prompt history
+async fn record_recent_prompt(prompt: &str, app: &tauri::AppHandle) -> Result<(), String> {
+    if prompt.trim().is_empty() {
+        return Ok(());
+    }
+    let _guard = SETTINGS_FILE_LOCK.lock().await;
+    let Some(mut settings) = load_settings(app.clone()).await? else {
+        return Ok(());
+    };
+
+    settings.recent_prompts.retain(|p| p != prompt);
+    settings.recent_prompts.insert(0, prompt.to_string());
+    settings.recent_prompts.truncate(MAX_RECENT_PROMPTS);
+    write_settings(settings, app)
+}
+
+#[tauri::command]
+async fn get_recent_prompts(app: tauri::AppHandle) -> Result<Vec<String>, String> {
+    Ok(load_settings(app)
+        .await?
+        .map(|settings| settings.recent_prompts)
+        .unwrap_or_default())
+}
+
+#[tauri::command]
+async fn clear_recent_prompts(app: tauri::AppHandle) -> Result<(), String> {
+    let _guard = SETTINGS_FILE_LOCK.lock().await;
+    if let Some(mut settings) = load_settings(app.clone()).await? {
+        settings.recent_prompts.clear();
+        write_settings(settings, &app)?;
+    }
+    Ok(())
+}
+
 fn get_config_file_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     let app_dir = app
         .path()
@@ -456,6 +2072,15 @@ fn get_config_file_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     Ok(app_dir.join("settings.json"))
 }
 
+fn get_model_cache_file_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let app_dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to get app config directory: {}", e))?;
+
+    Ok(app_dir.join("model_cache.json"))
+}
+
 fn encrypt_api_key(api_key: &str) -> String {
     // Simple XOR encryption with a fixed key for demonstration
     // In production, use proper encryption like AES
@@ -492,6 +2117,56 @@ fn decrypt_api_key(encrypted_api_key: &str) -> String {
     }
 }
 
+/// Gemini API keys are 39 characters starting with "AIza"
+fn is_gemini_api_key_format(api_key: &str) -> bool {
+    api_key.starts_with("AIza") && api_key.len() == 39
+}
+
+/// Reads the stored Gemini API key, which older versions saved in plaintext.
+/// Returns the key and whether it was stored in plaintext and needs to be re-encrypted.
+fn load_stored_gemini_api_key(stored: &str) -> (String, bool) {
+    if stored.is_empty() {
+        return (String::new(), false);
+    }
+
+    use base64::{engine::general_purpose, Engine as _};
+    // Stored values that are not base64 can only be plaintext keys
+    let decrypted = general_purpose::STANDARD
+        .decode(stored)
+        .is_ok()
+        .then(|| decrypt_api_key(stored))
+        .filter(|key| !key.is_empty());
+    match decrypted {
+        Some(key) if is_gemini_api_key_format(&key) => (key, false),
+        // Keys in another format are still accepted as long as the stored value is not a plain key
+        Some(key) if !is_gemini_api_key_format(stored) => (key, false),
+        _ => (stored.to_string(), true),
+    }
+}
+
+/// Returns where the settings file is stored, for manual inspection or backups
+#[tauri::command]
+async fn get_settings_file_path(app: tauri::AppHandle) -> Result<String, String> {
+    Ok(get_config_file_path(&app)?.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+async fn get_presets_file_path(app: tauri::AppHandle) -> Result<String, String> {
+    Ok(get_prompt_presets_file_path(&app)?
+        .to_string_lossy()
+        .to_string())
+}
+
+#[tauri::command]
+async fn get_all_config_paths(app: tauri::AppHandle) -> Result<ConfigPaths, String> {
+    Ok(ConfigPaths {
+        settings_path: get_config_file_path(&app)?.to_string_lossy().to_string(),
+        presets_path: get_prompt_presets_file_path(&app)?
+            .to_string_lossy()
+            .to_string(),
+    })
+}
+
 fn get_prompt_presets_file_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     let app_dir = app
         .path()
@@ -501,172 +2176,300 @@ fn get_prompt_presets_file_path(app: &tauri::AppHandle) -> Result<PathBuf, Strin
     Ok(app_dir.join("prompt_presets.xml"))
 }
 
+fn get_preset_history_file_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let app_dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to get app config directory: {}", e))?;
+
+    Ok(app_dir.join("preset_history.json"))
+}
+
+fn prompt_sha256(prompt: &str) -> String {
+    format!("{:x}", Sha256::digest(prompt.as_bytes()))
+}
+
+/// Drops every preset whose prompt matches an earlier one in the list
+fn remove_duplicate_presets(presets: &mut Vec<PromptPreset>) -> Vec<DuplicatePresetWarning> {
+    let mut seen: HashMap<String, (String, String)> = HashMap::new();
+    let mut dropped = Vec::new();
+
+    presets.retain(|preset| match seen.get(&prompt_sha256(&preset.prompt)) {
+        Some((existing_id, existing_name)) => {
+            dropped.push(DuplicatePresetWarning {
+                existing_id: existing_id.clone(),
+                existing_name: existing_name.clone(),
+                dropped_id: preset.id.clone(),
+                dropped_name: preset.name.clone(),
+            });
+            false
+        }
+        None => {
+            seen.insert(
+                prompt_sha256(&preset.prompt),
+                (preset.id.clone(), preset.name.clone()),
+            );
+            true
+        }
+    });
+
+    dropped
+}
+
+async fn prevent_duplicate_presets_enabled(app: &tauri::AppHandle) -> Result<bool, String> {
+    Ok(load_settings(app.clone())
+        .await?
+        .is_none_or(|settings| settings.prevent_duplicate_presets))
+}
+
+fn load_preset_history(path: &Path) -> Result<Vec<PromptPresetRevision>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read preset history: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse preset history: {}", e))
+}
+
+/// Bumps the version of every preset whose prompt differs from the stored one
+/// and returns the revisions that should be appended to the history
+fn apply_preset_versions(
+    presets: &mut [PromptPreset],
+    previous: &[PromptPreset],
+) -> Vec<PromptPresetRevision> {
+    let saved_at = chrono::Local::now().to_rfc3339();
+    let mut revisions = Vec::new();
+
+    for preset in presets.iter_mut() {
+        let hash = prompt_sha256(&preset.prompt);
+        match previous.iter().find(|p| p.id == preset.id) {
+            Some(old) if prompt_sha256(&old.prompt) == hash => {
+                preset.version = old.version;
+                continue;
+            }
+            Some(old) => preset.version = old.version + 1,
+            None => {}
+        }
+        revisions.push(PromptPresetRevision {
+            preset_id: preset.id.clone(),
+            version: preset.version,
+            prompt: preset.prompt.clone(),
+            prompt_sha256: hash,
+            saved_at: saved_at.clone(),
+        });
+    }
+
+    revisions
+}
+
 #[tauri::command]
 async fn load_prompt_presets(app: tauri::AppHandle) -> Result<Vec<PromptPreset>, String> {
     let presets_path = get_prompt_presets_file_path(&app)?;
 
+    // Built-in presets always come from the app so that prompt updates reach every user
+    let mut presets = builtin_prompt_presets();
+
     if !presets_path.exists() {
-        // Create default presets if file doesn't exist
-        let default_presets = vec![
-            PromptPreset {
-                id: "default_manual".to_string(),
-                name: "デフォルト（マニュアル）".to_string(),
-                prompt: "この動画の内容を詳細に分析し、ユーザーマニュアルとして構成してください。操作手順、注意点、トラブルシューティングを含めて説明してください。".to_string(),
-                is_default: true,
-            },
-            PromptPreset {
-                id: "default_specification".to_string(),
-                name: "デフォルト（仕様書）".to_string(),
-                prompt: "この動画の内容を技術仕様書として構成してください。システムの概要、機能詳細、API仕様、データ構造を含めて説明してください。".to_string(),
-                is_default: true,
-            },
-        ];
-
-        save_prompt_presets_to_file(&default_presets, &presets_path)?;
-        return Ok(default_presets);
+        return Ok(presets);
     }
 
     let content = fs::read_to_string(&presets_path)
         .map_err(|e| format!("Failed to read presets file: {}", e))?;
 
-    let mut loaded_presets = parse_prompt_presets_xml(&content)?;
-
-    // Check if default presets exist and restore if missing
-    let _has_default_manual = loaded_presets
-        .iter()
-        .any(|p| p.id == "default_manual" && p.is_default);
-    let _has_default_specification = loaded_presets
-        .iter()
-        .any(|p| p.id == "default_specification" && p.is_default);
+    let loaded_presets = parse_prompt_presets(&content)?;
 
-    let mut needs_save = false;
+    // Older versions stored copies of the default presets in the file, ignore them
+    presets.extend(loaded_presets.into_iter().filter(|p| !p.is_default));
 
-    // Fix existing default presets that might have is_default=false
-    for preset in &mut loaded_presets {
-        if (preset.id == "default_manual" || preset.id == "default_specification")
-            && !preset.is_default
-        {
-            preset.is_default = true;
-            needs_save = true;
-            println!(
-                "🔧 [PRESETS] Fixed is_default flag for preset: {}",
-                preset.id
-            );
-        }
-    }
+    Ok(presets)
+}
 
-    // Re-check after fixing flags
-    let has_default_manual_after_fix = loaded_presets
-        .iter()
-        .any(|p| p.id == "default_manual" && p.is_default);
-    let has_default_specification_after_fix = loaded_presets
-        .iter()
-        .any(|p| p.id == "default_specification" && p.is_default);
-
-    if !has_default_manual_after_fix {
-        loaded_presets.push(PromptPreset {
-            id: "default_manual".to_string(),
-            name: "デフォルト（マニュアル）".to_string(),
-            prompt: "この動画の内容を詳細に分析し、ユーザーマニュアルとして構成してください。操作手順、注意点、トラブルシューティングを含めて説明してください。".to_string(),
-            is_default: true,
-        });
-        needs_save = true;
-        println!("🔧 [PRESETS] Restored missing default manual preset");
-    }
+/// Deletes all user presets and returns the built-in ones
+#[tauri::command]
+async fn reset_prompt_presets(app: tauri::AppHandle) -> Result<Vec<PromptPreset>, String> {
+    let presets_path = get_prompt_presets_file_path(&app)?;
 
-    if !has_default_specification_after_fix {
-        loaded_presets.push(PromptPreset {
-            id: "default_specification".to_string(),
-            name: "デフォルト（仕様書）".to_string(),
-            prompt: "この動画の内容を技術仕様書として構成してください。システムの概要、機能詳細、API仕様、データ構造を含めて説明してください。".to_string(),
-            is_default: true,
-        });
-        needs_save = true;
-        println!("🔧 [PRESETS] Restored missing default specification preset");
+    if presets_path.exists() {
+        fs::remove_file(&presets_path)
+            .map_err(|e| format!("Failed to delete presets file: {}", e))?;
     }
+    println!("🔄 [BACKEND] Prompt presets reset to defaults");
 
-    // Save the restored presets if any were missing
-    if needs_save {
-        if let Err(e) = save_prompt_presets_to_file(&loaded_presets, &presets_path) {
-            println!(
-                "⚠️ [PRESETS] Failed to save restored default presets: {}",
-                e
-            );
-        } else {
-            println!("✅ [PRESETS] Successfully saved restored default presets");
-        }
+    if let Err(e) = app.emit("settings_reset", "prompt_presets") {
+        println!("❌ [EVENT] Failed to emit settings_reset: {}", e);
     }
 
-    Ok(loaded_presets)
+    Ok(builtin_prompt_presets())
 }
 
 #[tauri::command]
 async fn save_prompt_presets(
-    presets: Vec<PromptPreset>,
+    mut presets: Vec<PromptPreset>,
     app: tauri::AppHandle,
-) -> Result<(), String> {
+) -> Result<Vec<DuplicatePresetWarning>, String> {
     let presets_path = get_prompt_presets_file_path(&app)?;
 
-    // Check if default presets are being preserved
-    let has_default_manual = presets
-        .iter()
-        .any(|p| p.id == "default_manual" && p.is_default);
-    let has_default_specification = presets
-        .iter()
-        .any(|p| p.id == "default_specification" && p.is_default);
-
-    let mut final_presets = presets;
-
-    // Ensure default presets are always present
-    if !has_default_manual {
-        final_presets.push(PromptPreset {
-            id: "default_manual".to_string(),
-            name: "デフォルト（マニュアル）".to_string(),
-            prompt: "この動画の内容を詳細に分析し、ユーザーマニュアルとして構成してください。操作手順、注意点、トラブルシューティングを含めて説明してください。".to_string(),
-            is_default: true,
-        });
+    let dropped = if prevent_duplicate_presets_enabled(&app).await? {
+        remove_duplicate_presets(&mut presets)
+    } else {
+        Vec::new()
+    };
+    for warning in &dropped {
+        println!(
+            "⚠️ [BACKEND] Preset '{}' dropped, same prompt as '{}'",
+            warning.dropped_name, warning.existing_name
+        );
     }
 
-    if !has_default_specification {
-        final_presets.push(PromptPreset {
-            id: "default_specification".to_string(),
-            name: "デフォルト（仕様書）".to_string(),
-            prompt: "この動画の内容を技術仕様書として構成してください。システムの概要、機能詳細、API仕様、データ構造を含めて説明してください。".to_string(),
-            is_default: true,
-        });
-    }
+    // Only user presets are stored, the built-in ones are provided by the app
+    let mut user_presets: Vec<PromptPreset> =
+        presets.into_iter().filter(|p| !p.is_default).collect();
 
     if let Some(parent) = presets_path.parent() {
         fs::create_dir_all(parent)
             .map_err(|e| format!("Failed to create config directory: {}", e))?;
     }
 
-    save_prompt_presets_to_file(&final_presets, &presets_path)
+    let previous_presets = if presets_path.exists() {
+        let content = fs::read_to_string(&presets_path)
+            .map_err(|e| format!("Failed to read presets file: {}", e))?;
+        parse_prompt_presets(&content)?
+    } else {
+        Vec::new()
+    };
+    let revisions = apply_preset_versions(&mut user_presets, &previous_presets);
+
+    save_prompt_presets_to_file(&user_presets, &presets_path, PresetFileFormat::Xml)?;
+
+    if !revisions.is_empty() {
+        let history_path = get_preset_history_file_path(&app)?;
+        let mut history = load_preset_history(&history_path)?;
+        history.extend(revisions);
+        let content = serde_json::to_string_pretty(&history)
+            .map_err(|e| format!("Failed to serialize preset history: {}", e))?;
+        fs::write(&history_path, content)
+            .map_err(|e| format!("Failed to write preset history: {}", e))?;
+    }
+
+    Ok(dropped)
+}
+
+/// Removes user presets whose prompt duplicates another preset and returns how many were removed
+#[tauri::command]
+async fn deduplicate_prompt_presets(app: tauri::AppHandle) -> Result<u32, String> {
+    let presets_path = get_prompt_presets_file_path(&app)?;
+    let mut presets = load_prompt_presets(app.clone()).await?;
+    let dropped = remove_duplicate_presets(&mut presets);
+
+    if !dropped.is_empty() {
+        let user_presets: Vec<PromptPreset> =
+            presets.into_iter().filter(|p| !p.is_default).collect();
+        save_prompt_presets_to_file(&user_presets, &presets_path, PresetFileFormat::Xml)?;
+    }
+    println!(
+        "🧹 [BACKEND] Removed {} duplicate prompt presets",
+        dropped.len()
+    );
+
+    Ok(dropped.len() as u32)
+}
+
+/// Returns every saved revision of a preset, oldest first
+#[tauri::command]
+async fn get_prompt_preset_history(
+    preset_id: String,
+    app: tauri::AppHandle,
+) -> Result<Vec<PromptPresetRevision>, String> {
+    let history_path = get_preset_history_file_path(&app)?;
+    let mut revisions: Vec<PromptPresetRevision> = load_preset_history(&history_path)?
+        .into_iter()
+        .filter(|revision| revision.preset_id == preset_id)
+        .collect();
+    revisions.sort_by_key(|revision| revision.version);
+    Ok(revisions)
 }
 
-fn save_prompt_presets_to_file(presets: &[PromptPreset], path: &Path) -> Result<(), String> {
+fn save_prompt_presets_to_file(
+    presets: &[PromptPreset],
+    path: &Path,
+    format: PresetFileFormat,
+) -> Result<(), String> {
     // Ensure the parent directory exists
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)
             .map_err(|e| format!("Failed to create config directory: {}", e))?;
     }
 
+    let content = match format {
+        PresetFileFormat::Xml => convert_presets_to_xml(presets),
+        PresetFileFormat::Json => convert_presets_to_json(presets)?,
+    };
+
+    fs::write(path, content).map_err(|e| format!("Failed to write presets file: {}", e))?;
+
+    Ok(())
+}
+
+fn convert_presets_to_xml(presets: &[PromptPreset]) -> String {
     let mut xml_content = String::new();
     xml_content.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
     xml_content.push_str("<prompt_presets>\n");
 
     for preset in presets {
+        let tags = if preset.tags.is_empty() {
+            String::new()
+        } else {
+            let tag_elements: String = preset
+                .tags
+                .iter()
+                .map(|tag| format!("<tag>{}</tag>", escape_xml_text(tag)))
+                .collect();
+            format!("    <tags>{}</tags>\n", tag_elements)
+        };
         xml_content.push_str(&format!(
-            "  <preset id=\"{}\" is_default=\"{}\">\n    <name>{}</name>\n    <prompt><![CDATA[{}]]></prompt>\n  </preset>\n",
-            preset.id, preset.is_default, preset.name, preset.prompt
+            "  <preset id=\"{}\" is_default=\"{}\" version=\"{}\">\n    <name>{}</name>\n    <prompt><![CDATA[{}]]></prompt>\n{}  </preset>\n",
+            preset.id, preset.is_default, preset.version, preset.name, preset.prompt, tags
         ));
     }
 
     xml_content.push_str("</prompt_presets>\n");
+    xml_content
+}
 
-    fs::write(path, xml_content).map_err(|e| format!("Failed to write presets file: {}", e))?;
+/// Escapes the characters that are not allowed in XML element text
+fn escape_xml_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
 
-    Ok(())
+/// Reverses `escape_xml_text`, `&amp;` last so escaped entities are not decoded twice
+fn unescape_xml_text(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn convert_presets_to_json(presets: &[PromptPreset]) -> Result<String, String> {
+    serde_json::to_string_pretty(presets)
+        .map_err(|e| format!("Failed to serialize presets to JSON: {}", e))
+}
+
+/// Parses a presets file, trying the JSON format first and falling back to XML
+fn parse_prompt_presets(content: &str) -> Result<Vec<PromptPreset>, String> {
+    match parse_prompt_presets_json(content) {
+        Ok(presets) => Ok(presets),
+        Err(_) => parse_prompt_presets_xml(content),
+    }
+}
+
+fn parse_prompt_presets_json(json_content: &str) -> Result<Vec<PromptPreset>, String> {
+    serde_json::from_str(json_content.trim_start_matches('\u{feff}'))
+        .map_err(|e| format!("Failed to parse JSON presets: {}", e))
 }
 
 fn parse_prompt_presets_xml(xml_content: &str) -> Result<Vec<PromptPreset>, String> {
@@ -693,6 +2496,17 @@ fn parse_prompt_presets_xml(xml_content: &str) -> Result<Vec<PromptPreset>, Stri
                 }
             }
 
+            // Files written before presets were versioned have no version attribute
+            let opening_tag_end = xml_content[absolute_start..]
+                .find('>')
+                .map(|end| absolute_start + end)
+                .unwrap_or(xml_content.len());
+            let version = xml_content[absolute_start..opening_tag_end]
+                .split_once("version=\"")
+                .and_then(|(_, rest)| rest.split_once('"'))
+                .and_then(|(value, _)| value.parse().ok())
+                .unwrap_or_else(default_prompt_preset_version);
+
             // Find name
             if let Some(name_start) = xml_content[absolute_start..].find("<name>") {
                 let name_start = absolute_start + name_start + 6; // length of "<name>"
@@ -706,11 +2520,23 @@ fn parse_prompt_presets_xml(xml_content: &str) -> Result<Vec<PromptPreset>, Stri
                             let prompt =
                                 xml_content[prompt_start..prompt_start + prompt_end].to_string();
 
+                            // Tags are optional and only looked up inside this preset block
+                            let preset_end = xml_content[absolute_start..]
+                                .find("</preset>")
+                                .map(|end| absolute_start + end)
+                                .unwrap_or(xml_content.len());
+                            let tags = parse_preset_tags_xml(
+                                &xml_content[prompt_start + prompt_end..preset_end],
+                            );
+
                             presets.push(PromptPreset {
                                 id,
                                 name,
                                 prompt,
                                 is_default,
+                                format_preset: None,
+                                tags,
+                                version,
                             });
                         }
                     }
@@ -724,6 +2550,85 @@ fn parse_prompt_presets_xml(xml_content: &str) -> Result<Vec<PromptPreset>, Stri
     Ok(presets)
 }
 
+fn parse_preset_tags_xml(preset_xml: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    let Some(tags_start) = preset_xml.find("<tags>") else {
+        return tags;
+    };
+    let tags_end = preset_xml[tags_start..]
+        .find("</tags>")
+        .map(|end| tags_start + end)
+        .unwrap_or(preset_xml.len());
+    let tags_xml = &preset_xml[tags_start..tags_end];
+
+    let mut current_pos = 0;
+    while let Some(tag_start) = tags_xml[current_pos..].find("<tag>") {
+        let tag_start = current_pos + tag_start + 5; // length of "<tag>"
+        match tags_xml[tag_start..].find("</tag>") {
+            Some(tag_end) => {
+                let tag = unescape_xml_text(tags_xml[tag_start..tag_start + tag_end].trim());
+                if !tag.is_empty() {
+                    tags.push(tag);
+                }
+                current_pos = tag_start + tag_end;
+            }
+            None => break,
+        }
+    }
+
+    tags
+}
+
+/// Returns the presets that have the given tag (case-insensitive)
+fn filter_presets_by_tag<'a>(presets: &'a [PromptPreset], tag: &str) -> Vec<&'a PromptPreset> {
+    presets
+        .iter()
+        .filter(|preset| {
+            preset
+                .tags
+                .iter()
+                .any(|t| t.eq_ignore_ascii_case(tag.trim()))
+        })
+        .collect()
+}
+
+/// Returns the presets whose name or tags contain the query (case-insensitive)
+fn search_presets<'a>(presets: &'a [PromptPreset], query: &str) -> Vec<&'a PromptPreset> {
+    let query = query.trim().to_lowercase();
+    presets
+        .iter()
+        .filter(|preset| {
+            preset.name.to_lowercase().contains(&query)
+                || preset
+                    .tags
+                    .iter()
+                    .any(|tag| tag.to_lowercase().contains(&query))
+        })
+        .collect()
+}
+
+#[tauri::command]
+async fn filter_prompt_presets_by_tag(
+    presets: Vec<PromptPreset>,
+    tag: String,
+) -> Result<Vec<PromptPreset>, String> {
+    Ok(filter_presets_by_tag(&presets, &tag)
+        .into_iter()
+        .cloned()
+        .collect())
+}
+
+#[tauri::command]
+async fn search_prompt_presets(
+    presets: Vec<PromptPreset>,
+    query: String,
+) -> Result<Vec<PromptPreset>, String> {
+    Ok(search_presets(&presets, &query)
+        .into_iter()
+        .cloned()
+        .collect())
+}
+
 #[tauri::command]
 async fn import_prompt_presets_from_file(
     app: tauri::AppHandle,
@@ -733,22 +2638,41 @@ async fn import_prompt_presets_from_file(
     let file_path = app
         .dialog()
         .file()
-        .add_filter("XML files", &["xml"])
+        .add_filter("Preset files", &["xml", "json"])
         .blocking_pick_file();
 
     match file_path {
         Some(path) => {
-            let content = fs::read_to_string(path.as_path().unwrap())
-                .map_err(|e| format!("Failed to read XML file: {}", e))?;
+            let path = path.as_path().unwrap();
+            let content =
+                read_text_file(path).map_err(|e| format!("Failed to read presets file: {}", e))?;
 
-            let imported_presets = parse_prompt_presets_xml(&content)?;
+            let imported_presets = match PresetFileFormat::from_path(path) {
+                PresetFileFormat::Json => parse_prompt_presets_json(&content)?,
+                PresetFileFormat::Xml => parse_prompt_presets_xml(&content)?,
+            };
 
             // Merge with existing presets and save
             let existing_presets = load_prompt_presets(app.clone()).await?;
             let mut all_presets = existing_presets;
+            let prevent_duplicates = prevent_duplicate_presets_enabled(&app).await?;
 
             // Add imported presets with unique IDs
             for mut preset in imported_presets {
+                if prevent_duplicates {
+                    let hash = prompt_sha256(&preset.prompt);
+                    if let Some(existing) = all_presets
+                        .iter()
+                        .find(|p| prompt_sha256(&p.prompt) == hash)
+                    {
+                        println!(
+                            "⚠️ [BACKEND] Skipping imported preset '{}', same prompt as '{}'",
+                            preset.name, existing.name
+                        );
+                        continue;
+                    }
+                }
+
                 // Generate unique ID if it already exists
                 let mut new_id = preset.id.clone();
                 let mut counter = 1;
@@ -780,34 +2704,99 @@ async fn export_prompt_presets_to_file(
         .dialog()
         .file()
         .add_filter("XML files", &["xml"])
+        .add_filter("JSON files", &["json"])
         .set_file_name("prompt_presets.xml")
         .blocking_save_file();
 
     match file_path {
         Some(path) => {
-            save_prompt_presets_to_file(&presets, path.as_path().unwrap())?;
+            let path = path.as_path().unwrap();
+            save_prompt_presets_to_file(&presets, path, PresetFileFormat::from_path(path))?;
             Ok(())
         }
         None => Err("No file selected".to_string()),
     }
 }
 
+/// Converts a presets file to the other format and writes it next to the original.
+/// Returns the path of the converted file.
+#[tauri::command]
+async fn convert_preset_file_format(
+    source_path: String,
+    format: PresetFileFormat,
+) -> Result<String, String> {
+    let source_path = PathBuf::from(source_path);
+    let content =
+        read_text_file(&source_path).map_err(|e| format!("Failed to read presets file: {}", e))?;
+
+    let presets = match PresetFileFormat::from_path(&source_path) {
+        PresetFileFormat::Json => parse_prompt_presets_json(&content)?,
+        PresetFileFormat::Xml => parse_prompt_presets_xml(&content)?,
+    };
+
+    let target_path = source_path.with_extension(format.extension());
+    if target_path == source_path {
+        return Err("The presets file is already in the requested format".to_string());
+    }
+
+    save_prompt_presets_to_file(&presets, &target_path, format)?;
+    println!(
+        "✅ [PRESETS] Converted {} presets to {}",
+        presets.len(),
+        target_path.display()
+    );
+
+    Ok(target_path.to_string_lossy().to_string())
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
         .invoke_handler(tauri::generate_handler![
             select_video_files,
+            select_audio_files,
+            get_audio_duration,
+            split_audio_file_at_chapters,
             generate_document,
+            generate_document_from_screen_recording,
+            generate_document_from_image_sequence,
+            generate_document_diff_from_versions,
+            generate_document_from_uri,
+            select_image_sequence_dir,
+            check_disk_space,
+            get_video_audio_track_info,
+            get_stream_list,
+            validate_video_files,
+            generate_executive_summary,
+            delete_all_uploaded_files,
+            clean_stale_intermediate_files,
+            count_tokens_for_files,
             save_settings,
             load_settings,
+            reset_settings_to_defaults,
+            add_reference_document,
+            get_recent_prompts,
+            get_settings_file_path,
+            get_presets_file_path,
+            get_all_config_paths,
+            clear_recent_prompts,
             select_save_directory,
             save_document_to_file,
+            save_document_with_metadata_sidecar,
+            save_document_as_html,
             load_prompt_presets,
             save_prompt_presets,
+            get_prompt_preset_history,
+            reset_prompt_presets,
+            filter_prompt_presets_by_tag,
+            search_prompt_presets,
             import_prompt_presets_from_file,
-            export_prompt_presets_to_file
+            deduplicate_prompt_presets,
+            export_prompt_presets_to_file,
+            convert_preset_file_format
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
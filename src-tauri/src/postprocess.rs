@@ -0,0 +1,366 @@
+use crate::types::{LintIssue, NumberingStyle};
+use regex::Regex;
+
+/// Applies a subset of markdownlint rules to the generated document
+/// Returns the fixed content and every issue found, including the ones that could not be fixed
+pub fn lint_markdown(content: &str) -> (String, Vec<LintIssue>) {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut output: Vec<String> = Vec::new();
+    let mut issues = Vec::new();
+    let mut in_code_block = false;
+
+    // MD041: the first line should be a top-level heading (not auto-fixed)
+    if let Some((index, first_line)) = lines
+        .iter()
+        .enumerate()
+        .find(|(_, line)| !line.trim().is_empty())
+    {
+        if !first_line.starts_with("# ") {
+            issues.push(LintIssue {
+                rule: "MD041".to_string(),
+                line: index + 1,
+                message: "First line should be a top-level heading".to_string(),
+                auto_fixed: false,
+            });
+        }
+    }
+
+    for (index, raw_line) in lines.iter().enumerate() {
+        let line_number = index + 1;
+        let is_fence = is_code_fence(raw_line);
+        let mut line = raw_line.to_string();
+
+        // MD009: trailing spaces (two spaces are a hard line break and are allowed)
+        if !in_code_block && !is_fence {
+            let trimmed = line.trim_end();
+            let trailing = line.len() - trimmed.len();
+            let is_line_break = trailing == 2 && line.ends_with("  ") && !trimmed.is_empty();
+            if trailing > 0 && !is_line_break {
+                issues.push(LintIssue {
+                    rule: "MD009".to_string(),
+                    line: line_number,
+                    message: "Trailing spaces".to_string(),
+                    auto_fixed: true,
+                });
+                line = trimmed.to_string();
+            }
+        }
+
+        let is_heading = !in_code_block && is_atx_heading(&line);
+        let opens_code_block = is_fence && !in_code_block;
+        let closes_code_block = is_fence && in_code_block;
+        let rule = if is_heading { "MD022" } else { "MD031" };
+        let subject = if is_heading {
+            "heading"
+        } else {
+            "fenced code block"
+        };
+
+        // MD022 / MD031: blank line before headings and code blocks
+        if (is_heading || opens_code_block) && output.last().is_some_and(|l| !l.trim().is_empty()) {
+            issues.push(LintIssue {
+                rule: rule.to_string(),
+                line: line_number,
+                message: format!("Missing blank line before {}", subject),
+                auto_fixed: true,
+            });
+            output.push(String::new());
+        }
+
+        output.push(line);
+
+        // MD022 / MD031: blank line after headings and code blocks
+        if is_heading || closes_code_block {
+            if let Some(next_line) = lines.get(index + 1) {
+                if !next_line.trim().is_empty() {
+                    issues.push(LintIssue {
+                        rule: rule.to_string(),
+                        line: line_number,
+                        message: format!("Missing blank line after {}", subject),
+                        auto_fixed: true,
+                    });
+                    output.push(String::new());
+                }
+            }
+        }
+
+        if is_fence {
+            in_code_block = !in_code_block;
+        }
+    }
+
+    let mut fixed = output.join("\n");
+    if content.ends_with('\n') {
+        fixed.push('\n');
+    }
+    (fixed, issues)
+}
+
+/// Cuts the document at the last section heading that starts within `limit` characters
+/// Falls back to the last line break before the limit when no heading fits
+pub fn truncate_at_section_boundary(content: &str, limit: usize) -> String {
+    if content.chars().count() <= limit {
+        return content.to_string();
+    }
+
+    // Byte offset of the character at the limit
+    let limit_byte = content
+        .char_indices()
+        .nth(limit)
+        .map(|(i, _)| i)
+        .unwrap_or(content.len());
+
+    let mut last_heading = None;
+    let mut in_code_block = false;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        if offset > limit_byte {
+            break;
+        }
+        if is_code_fence(line) {
+            in_code_block = !in_code_block;
+        } else if !in_code_block && offset > 0 && is_atx_heading(line.trim_end()) {
+            last_heading = Some(offset);
+        }
+        offset += line.len();
+    }
+
+    let cut = last_heading
+        .or_else(|| content[..limit_byte].rfind('\n'))
+        .unwrap_or(limit_byte);
+
+    format!(
+        "{}\n\n> *Document truncated at {} characters*\n",
+        content[..cut].trim_end(),
+        limit
+    )
+}
+
+/// Rewrites ordered list markers (1. / a) / iv. ...) to the given numbering style
+/// Each nesting level is counted separately and a paragraph or heading starts a new list
+pub fn normalize_list_numbering(content: &str, style: &NumberingStyle) -> String {
+    if style.format_number(1).is_none() {
+        return content.to_string();
+    }
+
+    let marker_regex =
+        Regex::new(r"^(\s*)(\d{1,9}|[ivxlcdm]{1,7}|[IVXLCDM]{1,7}|[a-zA-Z])([.)])(\s+)").unwrap();
+    let mut output: Vec<String> = Vec::new();
+    let mut counters: Vec<(usize, usize)> = Vec::new(); // (indent, count)
+    let mut in_code_block = false;
+
+    for line in content.lines() {
+        if is_code_fence(line) {
+            in_code_block = !in_code_block;
+            output.push(line.to_string());
+            continue;
+        }
+        if in_code_block || line.trim().is_empty() {
+            output.push(line.to_string());
+            continue;
+        }
+
+        match marker_regex.captures(line) {
+            Some(captures) => {
+                let indent = captures[1].len();
+                counters.retain(|(level, _)| *level <= indent);
+                let number = match counters.last_mut() {
+                    Some((level, count)) if *level == indent => {
+                        *count += 1;
+                        *count
+                    }
+                    _ => {
+                        counters.push((indent, 1));
+                        1
+                    }
+                };
+                let marker = style.format_number(number).unwrap_or_default();
+                let rest = &line[captures[0].len()..];
+                output.push(format!(
+                    "{}{}{}{}{}",
+                    &captures[1], marker, &captures[3], &captures[4], rest
+                ));
+            }
+            None => {
+                // Indented lines continue the current list item, anything else ends the list
+                let indent = line.len() - line.trim_start().len();
+                if indent == 0 || is_atx_heading(line) {
+                    counters.clear();
+                }
+                output.push(line.to_string());
+            }
+        }
+    }
+
+    let mut normalized = output.join("\n");
+    if content.ends_with('\n') {
+        normalized.push('\n');
+    }
+    normalized
+}
+
+/// Lowers headings deeper than `max_depth` to that level, or turns every heading
+/// into a paragraph when `max_depth` is 0
+pub fn cap_heading_depth(content: &str, max_depth: u8) -> String {
+    if max_depth >= 6 {
+        return content.to_string();
+    }
+
+    let mut output: Vec<String> = Vec::new();
+    let mut in_code_block = false;
+    for line in content.lines() {
+        if is_code_fence(line) {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block || !is_atx_heading(line) {
+            output.push(line.to_string());
+            continue;
+        }
+
+        let trimmed = line.trim_start();
+        let indent = &line[..line.len() - trimmed.len()];
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+        let text = &trimmed[level..];
+        if max_depth == 0 {
+            output.push(text.trim().trim_end_matches('#').trim_end().to_string());
+        } else if level > max_depth as usize {
+            output.push(format!(
+                "{}{}{}",
+                indent,
+                "#".repeat(max_depth as usize),
+                text
+            ));
+        } else {
+            output.push(line.to_string());
+        }
+    }
+
+    let mut capped = output.join("\n");
+    if content.ends_with('\n') {
+        capped.push('\n');
+    }
+    capped
+}
+
+/// Removes a conversational opening ("Certainly! Here is the document:") and a closing
+/// offer ("Let me know if you need any changes.") that Gemini sometimes wraps around the document
+pub fn strip_ai_preamble(content: &str) -> String {
+    let preamble_regex = Regex::new(
+        r"(?i)^(?:Certainly|Of course|Sure|Here is|Here's|Absolutely|Great)[^\n#]{0,200}?:[ \t]*\n+",
+    )
+    .unwrap();
+    let closing_regex = Regex::new(
+        r"(?i)^(?:Let me know|Please let me know|Feel free|I hope this|Hope this|If you (?:need|have|would like|want))[^\n]{0,200}$",
+    )
+    .unwrap();
+
+    let trimmed = content.trim_start();
+    let mut body = match preamble_regex.find(trimmed) {
+        Some(preamble) => &trimmed[preamble.end()..],
+        None => trimmed,
+    };
+
+    let without_trailing = body.trim_end();
+    if let Some(last_paragraph_start) = without_trailing.rfind("\n\n").map(|i| i + 2) {
+        if closing_regex.is_match(without_trailing[last_paragraph_start..].trim()) {
+            body = &without_trailing[..last_paragraph_start];
+        }
+    }
+
+    if body.len() == content.len() {
+        return content.to_string();
+    }
+    let mut stripped = body.trim_end().to_string();
+    if content.ends_with('\n') {
+        stripped.push('\n');
+    }
+    stripped
+}
+
+/// Lowercases fenced code block language identifiers, resolves common aliases (py, js, sh ...)
+/// and drops identifiers that are not in `preferred_languages` (an empty list keeps all of them)
+pub fn normalise_code_blocks(content: &str, preferred_languages: &[String]) -> String {
+    let preferred: Vec<String> = preferred_languages
+        .iter()
+        .map(|language| code_language_alias(&language.trim().to_lowercase()).to_string())
+        .collect();
+
+    let mut output: Vec<String> = Vec::new();
+    let mut open_fence: Option<String> = None;
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        let indent = &line[..line.len() - trimmed.len()];
+
+        if let Some(fence) = &open_fence {
+            let closing = trimmed.trim_end();
+            if closing.starts_with(fence.as_str())
+                && closing
+                    .chars()
+                    .all(|c| c == fence.chars().next().unwrap_or('`'))
+            {
+                open_fence = None;
+            }
+            output.push(line.to_string());
+            continue;
+        }
+        if !is_code_fence(line) {
+            output.push(line.to_string());
+            continue;
+        }
+
+        let marker_char = trimmed.chars().next().unwrap_or('`');
+        let fence: String = trimmed.chars().take_while(|c| *c == marker_char).collect();
+        let language = trimmed[fence.len()..]
+            .split_whitespace()
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+        let language = code_language_alias(&language);
+        let language = if preferred.is_empty() || preferred.iter().any(|p| p == language) {
+            language
+        } else {
+            ""
+        };
+        output.push(format!("{}{}{}", indent, fence, language));
+        open_fence = Some(fence);
+    }
+
+    let mut normalised = output.join("\n");
+    if content.ends_with('\n') {
+        normalised.push('\n');
+    }
+    normalised
+}
+
+fn code_language_alias(language: &str) -> &str {
+    match language {
+        "py" | "python3" => "python",
+        "js" | "node" => "javascript",
+        "ts" => "typescript",
+        "sh" | "shell" | "zsh" => "bash",
+        "ps" | "ps1" | "pwsh" => "powershell",
+        "yml" => "yaml",
+        "rb" => "ruby",
+        "rs" => "rust",
+        "md" => "markdown",
+        other => other,
+    }
+}
+
+fn is_code_fence(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.starts_with("```") || trimmed.starts_with("~~~")
+}
+
+fn is_atx_heading(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    if line.len() - trimmed.len() > 3 {
+        return false;
+    }
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    (1..=6).contains(&level)
+        && trimmed[level..]
+            .chars()
+            .next()
+            .is_none_or(|c| c == ' ' || c == '\t')
+}
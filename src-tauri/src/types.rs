@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, PartialEq)]
 pub enum VideoQuality {
     NoConversion,
     #[serde(rename = "1080p")]
@@ -11,7 +12,43 @@ pub enum VideoQuality {
     Quality480p,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+impl VideoQuality {
+    /// Target (width, height), or `None` for `NoConversion`
+    pub fn target_resolution(&self) -> Option<(u32, u32)> {
+        match self {
+            VideoQuality::Quality1080p => Some((1920, 1080)),
+            VideoQuality::Quality720p => Some((1280, 720)),
+            VideoQuality::Quality480p => Some((854, 480)),
+            VideoQuality::NoConversion => None,
+        }
+    }
+
+    /// Whether a video of this size has to be scaled down to reach the target quality.
+    /// Videos are never scaled up.
+    pub fn needs_downscale(&self, width: u32, height: u32) -> bool {
+        let Some((target_width, target_height)) = self.target_resolution() else {
+            return false;
+        };
+        if height < target_height && width < target_width {
+            return false;
+        }
+        height > target_height || (height == target_height && width > target_width)
+    }
+
+    /// Output size for a video that needs to be scaled down, fitted into the target resolution
+    /// without changing the aspect ratio. Both sides are rounded to even numbers for the encoder.
+    pub fn downscaled_size(&self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if !self.needs_downscale(width, height) {
+            return None;
+        }
+        let (target_width, target_height) = self.target_resolution()?;
+        let factor = (target_width as f64 / width as f64).min(target_height as f64 / height as f64);
+        let even = |side: u32| ((side as f64 * factor / 2.0).round() as u32 * 2).max(2);
+        Some((even(width), even(height)))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
 pub enum ImageEmbedFrequency {
     #[serde(rename = "minimal")]
     Minimal, // 最小限（重要なポイントのみ）
@@ -21,7 +58,18 @@ pub enum ImageEmbedFrequency {
     Detailed, // 詳細（多め）
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+impl ImageEmbedFrequency {
+    /// Minimum time between two embedded screenshots, closer ones are dropped
+    pub fn min_screenshot_spacing_seconds(&self) -> f64 {
+        match self {
+            ImageEmbedFrequency::Minimal => 30.0,
+            ImageEmbedFrequency::Moderate => 5.0,
+            ImageEmbedFrequency::Detailed => 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
 pub enum FrameExtractionMethod {
     #[serde(rename = "standard")]
     Standard, // 標準の extract_frame_from_video
@@ -31,18 +79,621 @@ pub enum FrameExtractionMethod {
     Multiple, // 複数同時 extract_multiple_frames_from_video
 }
 
+/// Settings enums whose serialisation changed between versions.
+/// The current value names are tried first, then the names written by older versions.
+trait LegacySettingValue: Sized {
+    const FIELD: &'static str;
+    const VARIANTS: &'static [&'static str];
+
+    fn from_current(value: &str) -> Option<Self>;
+    fn from_legacy(value: Option<&str>) -> Option<Self>;
+}
+
+fn deserialize_setting_value<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: LegacySettingValue,
+{
+    let value = Option::<String>::deserialize(deserializer)?;
+    if let Some(current) = value.as_deref().and_then(T::from_current) {
+        return Ok(current);
+    }
+    T::from_legacy(value.as_deref()).ok_or_else(|| {
+        serde::de::Error::unknown_variant(value.as_deref().unwrap_or("null"), T::VARIANTS)
+    })
+}
+
+/// Records the field when `settings` holds a legacy value that can be migrated
+fn detect_legacy_value<T: LegacySettingValue>(
+    settings: &serde_json::Value,
+    migrations: &mut Vec<SettingsMigration>,
+) {
+    let Some(original) = settings.get(T::FIELD) else {
+        return;
+    };
+    let value = original.as_str();
+    if value.and_then(T::from_current).is_none() && T::from_legacy(value).is_some() {
+        migrations.push(SettingsMigration {
+            field: T::FIELD.to_string(),
+            original_value: original.clone(),
+        });
+    }
+}
+
+/// Legacy enum values in a settings file, checked before it is deserialised
+pub fn detect_settings_migrations(settings: &serde_json::Value) -> Vec<SettingsMigration> {
+    let mut migrations = Vec::new();
+    detect_legacy_value::<VideoQuality>(settings, &mut migrations);
+    detect_legacy_value::<ImageEmbedFrequency>(settings, &mut migrations);
+    detect_legacy_value::<FrameExtractionMethod>(settings, &mut migrations);
+    migrations
+}
+
+impl LegacySettingValue for VideoQuality {
+    const FIELD: &'static str = "video_quality";
+    const VARIANTS: &'static [&'static str] = &["NoConversion", "1080p", "720p", "480p"];
+
+    fn from_current(value: &str) -> Option<Self> {
+        match value {
+            "NoConversion" => Some(VideoQuality::NoConversion),
+            "1080p" => Some(VideoQuality::Quality1080p),
+            "720p" => Some(VideoQuality::Quality720p),
+            "480p" => Some(VideoQuality::Quality480p),
+            _ => None,
+        }
+    }
+
+    fn from_legacy(value: Option<&str>) -> Option<Self> {
+        match value {
+            None | Some("no_conversion") | Some("original") => Some(VideoQuality::NoConversion),
+            Some("Quality1080p") | Some("quality_1080p") => Some(VideoQuality::Quality1080p),
+            Some("Quality720p") | Some("quality_720p") => Some(VideoQuality::Quality720p),
+            Some("Quality480p") | Some("quality_480p") => Some(VideoQuality::Quality480p),
+            _ => None,
+        }
+    }
+}
+
+impl LegacySettingValue for ImageEmbedFrequency {
+    const FIELD: &'static str = "image_embed_frequency";
+    const VARIANTS: &'static [&'static str] = &["minimal", "moderate", "detailed"];
+
+    fn from_current(value: &str) -> Option<Self> {
+        match value {
+            "minimal" => Some(ImageEmbedFrequency::Minimal),
+            "moderate" => Some(ImageEmbedFrequency::Moderate),
+            "detailed" => Some(ImageEmbedFrequency::Detailed),
+            _ => None,
+        }
+    }
+
+    fn from_legacy(value: Option<&str>) -> Option<Self> {
+        match value {
+            Some("Minimal") => Some(ImageEmbedFrequency::Minimal),
+            None | Some("Moderate") | Some("normal") => Some(ImageEmbedFrequency::Moderate),
+            Some("Detailed") => Some(ImageEmbedFrequency::Detailed),
+            _ => None,
+        }
+    }
+}
+
+impl LegacySettingValue for FrameExtractionMethod {
+    const FIELD: &'static str = "frame_extraction_method";
+    const VARIANTS: &'static [&'static str] = &["standard", "fast", "multiple"];
+
+    fn from_current(value: &str) -> Option<Self> {
+        match value {
+            "standard" => Some(FrameExtractionMethod::Standard),
+            "fast" => Some(FrameExtractionMethod::Fast),
+            "multiple" => Some(FrameExtractionMethod::Multiple),
+            _ => None,
+        }
+    }
+
+    fn from_legacy(value: Option<&str>) -> Option<Self> {
+        match value {
+            None | Some("Standard") => Some(FrameExtractionMethod::Standard),
+            Some("Fast") => Some(FrameExtractionMethod::Fast),
+            Some("Multiple") => Some(FrameExtractionMethod::Multiple),
+            _ => None,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for VideoQuality {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_setting_value(deserializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ImageEmbedFrequency {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_setting_value(deserializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FrameExtractionMethod {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_setting_value(deserializer)
+    }
+}
+
+/// A legacy settings value that was converted while loading (payload of `settings_migrated`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsMigration {
+    pub field: String,
+    pub original_value: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum FrameOutputFormat {
+    #[serde(rename = "png")]
+    Png,
+    #[serde(rename = "jpeg")]
+    Jpeg { quality: u8 },
+    #[serde(rename = "webp")]
+    Webp { quality: u8 },
+}
+
+impl FrameOutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FrameOutputFormat::Png => "png",
+            FrameOutputFormat::Jpeg { .. } => "jpg",
+            FrameOutputFormat::Webp { .. } => "webp",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum NumberingStyle {
+    #[serde(rename = "automatic")]
+    Automatic, // Gemini に任せる
+    #[serde(rename = "arabic")]
+    Arabic, // 1, 2, 3
+    #[serde(rename = "alpha_lower")]
+    AlphaLower, // a, b, c
+    #[serde(rename = "alpha_upper")]
+    AlphaUpper, // A, B, C
+    #[serde(rename = "roman_lower")]
+    RomanLower, // i, ii, iii
+    #[serde(rename = "roman_upper")]
+    RomanUpper, // I, II, III
+    #[serde(rename = "none")]
+    None_, // 番号なし
+}
+
+impl NumberingStyle {
+    /// Formats a 1-based list position in this style (`None` for Automatic and None_)
+    pub fn format_number(&self, number: usize) -> Option<String> {
+        match self {
+            NumberingStyle::Arabic => Some(number.to_string()),
+            NumberingStyle::AlphaLower => Some(to_alpha(number)),
+            NumberingStyle::AlphaUpper => Some(to_alpha(number).to_uppercase()),
+            NumberingStyle::RomanLower => Some(to_roman(number).to_lowercase()),
+            NumberingStyle::RomanUpper => Some(to_roman(number)),
+            NumberingStyle::Automatic | NumberingStyle::None_ => None,
+        }
+    }
+}
+
+// 1 -> a, 26 -> z, 27 -> aa
+fn to_alpha(mut number: usize) -> String {
+    let mut letters = Vec::new();
+    while number > 0 {
+        number -= 1;
+        letters.push((b'a' + (number % 26) as u8) as char);
+        number /= 26;
+    }
+    letters.iter().rev().collect()
+}
+
+fn to_roman(mut number: usize) -> String {
+    const NUMERALS: [(usize, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut roman = String::new();
+    for (value, numeral) in NUMERALS {
+        while number >= value {
+            roman.push_str(numeral);
+            number -= value;
+        }
+    }
+    roman
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum OutputEncoding {
+    #[serde(rename = "utf8")]
+    Utf8,
+    #[serde(rename = "utf8_bom")]
+    Utf8Bom,
+    #[serde(rename = "shift_jis")]
+    ShiftJis,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum LineEnding {
+    #[serde(rename = "platform")]
+    Platform, // 生成された改行をそのまま保存
+    #[serde(rename = "lf")]
+    Lf,
+    #[serde(rename = "crlf")]
+    Crlf,
+}
+
+impl LineEnding {
+    /// Converts every line break of the document to this line ending
+    pub fn apply(&self, content: &str) -> String {
+        match self {
+            LineEnding::Platform => content.to_string(),
+            LineEnding::Lf => content.replace("\r\n", "\n"),
+            LineEnding::Crlf => content.replace("\r\n", "\n").replace('\n', "\r\n"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum DocumentFormatPreset {
+    #[serde(rename = "user_manual")]
+    UserManual,
+    #[serde(rename = "technical_spec")]
+    TechnicalSpec,
+    #[serde(rename = "meeting_notes")]
+    MeetingNotes,
+    #[serde(rename = "tutorial_guide")]
+    TutorialGuide,
+    #[serde(rename = "lecture_notes")]
+    LectureNotes,
+    #[serde(rename = "custom")]
+    Custom, // custom_prompt を使用
+}
+
+impl DocumentFormatPreset {
+    /// Preset for a one-word video classification (manual, specification, tutorial, lecture, meeting, demo)
+    pub fn from_classification(word: &str) -> Option<Self> {
+        let word = word
+            .trim()
+            .trim_matches(|c: char| !c.is_alphanumeric())
+            .to_lowercase();
+        match word.as_str() {
+            "manual" => Some(DocumentFormatPreset::UserManual),
+            "specification" => Some(DocumentFormatPreset::TechnicalSpec),
+            "tutorial" | "demo" => Some(DocumentFormatPreset::TutorialGuide),
+            "lecture" => Some(DocumentFormatPreset::LectureNotes),
+            "meeting" => Some(DocumentFormatPreset::MeetingNotes),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentFormatPreset::UserManual => "user_manual",
+            DocumentFormatPreset::TechnicalSpec => "technical_spec",
+            DocumentFormatPreset::MeetingNotes => "meeting_notes",
+            DocumentFormatPreset::TutorialGuide => "tutorial_guide",
+            DocumentFormatPreset::LectureNotes => "lecture_notes",
+            DocumentFormatPreset::Custom => "custom",
+        }
+    }
+}
+
+/// Payload of `document_type_detected`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentTypeDetection {
+    pub detected: String,
+    pub preset: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum NotificationSound {
+    #[serde(rename = "none")]
+    None, // 通知しない
+    #[serde(rename = "system")]
+    System, // システム通知とデフォルトの通知音
+    #[serde(rename = "custom")]
+    Custom(String), // 指定した音声ファイルを再生
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ProgressVerbosity {
+    #[serde(rename = "minimal")]
+    Minimal, // ステップの切り替わりのみ通知
+    #[serde(rename = "normal")]
+    Normal, // ステップ内の詳細メッセージも通知
+    #[serde(rename = "verbose")]
+    Verbose, // 状態確認ごとのメッセージと転送バイト数も通知
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum IntegrationStrategy {
+    #[serde(rename = "sequential")]
+    Sequential, // 全ドキュメントを一度に統合
+    #[serde(rename = "hierarchical")]
+    Hierarchical, // 2つずつ段階的に統合
+    #[serde(rename = "summary_first")]
+    SummaryFirst, // 要約から構成を作成し、セクションごとに展開
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum AiBackend {
+    #[serde(rename = "gemini")]
+    Gemini,
+    #[serde(rename = "claude")]
+    Claude, // キーフレーム画像を Claude Messages API に送信
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum GeminiApiVersion {
+    #[serde(rename = "v1beta")]
+    V1Beta,
+    #[serde(rename = "v1")]
+    V1,
+}
+
+impl GeminiApiVersion {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GeminiApiVersion::V1Beta => "v1beta",
+            GeminiApiVersion::V1 => "v1",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum SplitMode {
+    #[serde(rename = "duration")]
+    Duration, // 1時間ごとに分割
+    #[serde(rename = "silence_detect")]
+    SilenceDetect {
+        silence_threshold_db: f32,
+        min_silence_duration_secs: f32,
+    }, // 1時間付近の無音区間で分割
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum WatermarkPosition {
+    #[serde(rename = "top_left")]
+    TopLeft,
+    #[serde(rename = "top_right")]
+    TopRight,
+    #[serde(rename = "bottom_left")]
+    BottomLeft,
+    #[serde(rename = "bottom_right")]
+    BottomRight,
+    #[serde(rename = "center")]
+    Center,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum DocumentWatermarkPosition {
+    #[serde(rename = "top")]
+    Top,
+    #[serde(rename = "bottom")]
+    Bottom,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatermarkSettings {
+    pub text: String,
+    pub position: WatermarkPosition,
+    pub font_size: u32,
+    pub opacity: f64,
+    #[serde(default)]
+    pub font_path: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VideoFile {
     pub path: String,
     pub name: String,
     pub size: u64,
+    #[serde(default)]
+    pub subtitle_path: Option<String>,
+    #[serde(default)]
+    pub start_offset_seconds: Option<f64>,
+    #[serde(default)]
+    pub end_offset_seconds: Option<f64>,
+    #[serde(default)]
+    pub custom_prompt_override: Option<String>,
+    #[serde(default)]
+    pub sha256_first_mb: Option<String>, // 選択時に計算し、処理中の書き換えを検出する
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioFile {
+    pub path: String,
+    pub name: String,
+    pub size: u64,
+    #[serde(default)]
+    pub duration: Option<f64>, // get_audio_duration で必要な時に取得する
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioChapter {
+    pub path: String,
+    pub title: String,
+}
+
+/// Start and end time of one chapter marker, in seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChapterSpan {
+    pub start: f64,
+    pub end: f64,
+    pub title: String,
+}
+
+impl ChapterSpan {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// Reads the chapters from `ffprobe -show_chapters -of json` output
+pub fn parse_ffprobe_chapters(probe: &serde_json::Value) -> Result<Vec<ChapterSpan>, String> {
+    let chapters = probe["chapters"].as_array().cloned().unwrap_or_default();
+    chapters
+        .iter()
+        .enumerate()
+        .map(|(chapter_index, chapter)| {
+            // ffprobe reports chapter times as decimal strings
+            let start = chapter["start_time"]
+                .as_str()
+                .and_then(|s| s.parse::<f64>().ok())
+                .ok_or_else(|| format!("Missing start time for chapter {}", chapter_index + 1))?;
+            let end = chapter["end_time"]
+                .as_str()
+                .and_then(|s| s.parse::<f64>().ok())
+                .ok_or_else(|| format!("Missing end time for chapter {}", chapter_index + 1))?;
+            let title = chapter["tags"]["title"]
+                .as_str()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| format!("Chapter {}", chapter_index + 1));
+            Ok(ChapterSpan { start, end, title })
+        })
+        .collect()
+}
+
+/// Merges every chapter shorter than `min_duration_seconds` into the preceding one,
+/// or into the following one when the short chapter comes first
+pub fn merge_short_chapters(
+    chapters: Vec<ChapterSpan>,
+    min_duration_seconds: f64,
+) -> Vec<ChapterSpan> {
+    let mut merged: Vec<ChapterSpan> = Vec::new();
+    for chapter in chapters {
+        match merged.last_mut() {
+            // Only the leading segment can still be short, later short chapters are absorbed
+            Some(last)
+                if chapter.duration() < min_duration_seconds
+                    || last.duration() < min_duration_seconds =>
+            {
+                last.end = chapter.end;
+                last.title = format!("{} + {}", last.title, chapter.title);
+            }
+            _ => merged.push(chapter),
+        }
+    }
+    merged
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamInfo {
+    pub index: u32, // 同じ種類のストリーム内での番号（-map 0:v:{index} に対応）
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub codec: String,
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioTrackInfo {
+    pub index: u32, // 音声ストリーム内での番号（-map 0:a:{index} に対応）
+    pub language: Option<String>,
+    pub codec: String,
+    pub channels: u32,
+    pub sample_rate: u32,
+    pub bit_rate_kbps: Option<u32>,
+    pub title: Option<String>,
+}
+
+// 設定ファイルでは小文字の文字列として保存する（旧形式の自由入力文字列もそのまま読み込める）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(from = "String", into = "String")]
+pub enum DocumentLanguage {
+    Japanese,
+    English,
+    ChineseSimplified,
+    ChineseTraditional,
+    Korean,
+    Spanish,
+    French,
+    German,
+    Portuguese,
+    Custom(String), // 上記以外の言語名
+}
+
+impl DocumentLanguage {
+    pub fn as_str(&self) -> &str {
+        match self {
+            DocumentLanguage::Japanese => "japanese",
+            DocumentLanguage::English => "english",
+            DocumentLanguage::ChineseSimplified => "chinese_simplified",
+            DocumentLanguage::ChineseTraditional => "chinese_traditional",
+            DocumentLanguage::Korean => "korean",
+            DocumentLanguage::Spanish => "spanish",
+            DocumentLanguage::French => "french",
+            DocumentLanguage::German => "german",
+            DocumentLanguage::Portuguese => "portuguese",
+            DocumentLanguage::Custom(name) => name,
+        }
+    }
+
+    /// Language name used in prompts
+    pub fn display_name(&self) -> &str {
+        match self {
+            DocumentLanguage::Japanese => "Japanese",
+            DocumentLanguage::English => "English",
+            DocumentLanguage::ChineseSimplified => "Simplified Chinese",
+            DocumentLanguage::ChineseTraditional => "Traditional Chinese",
+            DocumentLanguage::Korean => "Korean",
+            DocumentLanguage::Spanish => "Spanish",
+            DocumentLanguage::French => "French",
+            DocumentLanguage::German => "German",
+            DocumentLanguage::Portuguese => "Portuguese",
+            DocumentLanguage::Custom(name) => name,
+        }
+    }
+}
+
+impl From<String> for DocumentLanguage {
+    // Known names are matched case-insensitively so that "English" no longer falls back to Japanese
+    fn from(value: String) -> Self {
+        match value
+            .trim()
+            .to_lowercase()
+            .replace([' ', '-'], "_")
+            .as_str()
+        {
+            "japanese" => DocumentLanguage::Japanese,
+            "english" => DocumentLanguage::English,
+            "chinese_simplified" => DocumentLanguage::ChineseSimplified,
+            "chinese_traditional" => DocumentLanguage::ChineseTraditional,
+            "korean" => DocumentLanguage::Korean,
+            "spanish" => DocumentLanguage::Spanish,
+            "french" => DocumentLanguage::French,
+            "german" => DocumentLanguage::German,
+            "portuguese" => DocumentLanguage::Portuguese,
+            "" => default_language(),
+            _ => DocumentLanguage::Custom(value.trim().to_string()),
+        }
+    }
+}
+
+impl From<DocumentLanguage> for String {
+    fn from(language: DocumentLanguage) -> Self {
+        language.as_str().to_string()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppSettings {
     pub gemini_api_key: String,
     #[serde(default = "default_language")]
-    pub language: String,
+    pub language: DocumentLanguage,
     #[serde(default = "default_temperature")]
     pub temperature: f64,
     #[serde(default)]
@@ -62,6 +713,364 @@ pub struct AppSettings {
     pub enable_experimental_features: bool,
     #[serde(default = "default_frame_extraction_method")]
     pub frame_extraction_method: FrameExtractionMethod,
+    #[serde(default)]
+    pub post_generation_hook: Option<String>,
+    #[serde(default = "default_max_upload_bytes")]
+    pub max_upload_bytes: u64,
+    #[serde(default)]
+    pub audio_track_index: Option<u32>,
+    #[serde(default)]
+    pub structured_output: bool,
+    #[serde(default)]
+    pub video_watermark: Option<WatermarkSettings>,
+    #[serde(default)]
+    pub translate_output_to: Option<String>,
+    #[serde(default = "default_split_mode")]
+    pub split_mode: SplitMode,
+    #[serde(default)]
+    pub thinking_budget: Option<u32>,
+    #[serde(default)]
+    pub reference_documents: Vec<String>,
+    #[serde(default = "default_output_encoding")]
+    pub output_encoding: OutputEncoding,
+    #[serde(default)]
+    pub stabilize_video: bool,
+    #[serde(default)]
+    pub split_output_per_section: bool,
+    #[serde(default)]
+    pub two_pass_encoding: bool,
+    #[serde(default)]
+    pub enable_search_grounding: bool,
+    #[serde(default = "default_format_preset")]
+    pub format_preset: DocumentFormatPreset,
+    #[serde(default)]
+    pub subtitle_burn_in: Option<String>,
+    #[serde(default)]
+    pub save_intermediate_files: bool,
+    #[serde(default)]
+    pub concurrent_frame_extraction: bool,
+    #[serde(default = "default_max_concurrent_frame_extractions")]
+    pub max_concurrent_frame_extractions: usize,
+    #[serde(default)]
+    pub document_watermark_text: Option<String>,
+    #[serde(default = "default_document_watermark_position")]
+    pub document_watermark_position: DocumentWatermarkPosition,
+    #[serde(default)]
+    pub auto_open_document: bool,
+    #[serde(default)]
+    pub use_ai_title: bool,
+    #[serde(default = "default_gemini_api_version")]
+    pub gemini_api_version: GeminiApiVersion,
+    #[serde(default)]
+    pub auto_generate_summary: bool,
+    #[serde(default = "default_frame_output_format")]
+    pub frame_output_format: FrameOutputFormat,
+    #[serde(default)]
+    pub ffmpeg_extra_input_args: Vec<String>,
+    #[serde(default)]
+    pub ffmpeg_extra_output_args: Vec<String>,
+    #[serde(default)]
+    pub output_dir_per_job: bool,
+    #[serde(default)]
+    pub enable_document_linting: bool,
+    #[serde(default)]
+    pub video_stream_index: u32,
+    #[serde(default)]
+    pub recent_prompts: Vec<String>, // 新しい順、最大20件
+    #[serde(default = "default_upload_chunk_size_mb")]
+    pub upload_chunk_size_mb: u32, // 最小1MB
+    #[serde(default)]
+    pub generate_flashcards: bool,
+    #[serde(default = "default_ffprobe_timeout_seconds")]
+    pub ffprobe_timeout_seconds: u64,
+    #[serde(default = "default_ffmpeg_timeout_seconds")]
+    pub ffmpeg_timeout_seconds: u64,
+    #[serde(default = "default_slide_frame_rate")]
+    pub slide_frame_rate: f64, // 0.5 = 1枚あたり2秒
+    #[serde(default)]
+    pub safety_settings: Vec<GeminiSafetySetting>, // 空の場合はAPIのデフォルト
+    #[serde(default)]
+    pub generate_glossary: bool,
+    #[serde(default)]
+    pub max_document_size_chars: Option<usize>, // 未設定の場合は制限なし
+    #[serde(default)]
+    pub truncate_at_limit: bool,
+    #[serde(default = "default_embed_video_link")]
+    pub embed_video_link: bool, // ローカルファイルは local_file_base_url が設定されている場合のみ
+    #[serde(default)]
+    pub local_file_base_url: Option<String>, // 例: SharePoint のドキュメントライブラリのURL
+    #[serde(default)]
+    pub top_p: Option<f64>, // 0.0〜1.0
+    #[serde(default)]
+    pub top_k: Option<u32>, // 1〜100
+    #[serde(default = "default_document_numbering_style")]
+    pub document_numbering_style: NumberingStyle,
+    #[serde(default)]
+    pub video_sampling_fps: Option<f64>, // 実験用機能: 動画の代わりに抽出したフレームを送信
+    #[serde(default = "default_ai_backend")]
+    pub ai_backend: AiBackend,
+    #[serde(default)]
+    pub claude_api_key: String,
+    #[serde(default = "default_claude_model")]
+    pub claude_model: String,
+    #[serde(default = "default_integration_strategy")]
+    pub integration_strategy: IntegrationStrategy,
+    #[serde(default)]
+    pub keep_uploaded_files: bool, // falseの場合は生成成功後にGeminiからファイルを削除
+    #[serde(default = "default_notification_sound")]
+    pub notification_sound: NotificationSound,
+    #[serde(default)]
+    pub chunk_long_prompts: bool, // 長すぎるプロンプトを参照ドキュメントごとに分割
+    #[serde(default = "default_max_prompt_tokens")]
+    pub max_prompt_tokens: u32,
+    #[serde(default)]
+    pub image_grid_mode: bool, // セクションごとのスクリーンショットを1枚のグリッド画像にまとめる
+    #[serde(default = "default_image_grid_columns")]
+    pub image_grid_columns: u32,
+    #[serde(default)]
+    pub auto_detect_document_type: bool, // 動画の冒頭からフォーマットプリセットを自動判定
+    #[serde(default = "default_max_heading_depth")]
+    pub max_heading_depth: u8, // 0の場合は見出しを段落に変換
+    #[serde(default = "default_strip_ai_preamble")]
+    pub strip_ai_preamble: bool, // 「Certainly! Here is...」などの前置きを削除
+    #[serde(default)]
+    pub preferred_code_languages: Vec<String>, // 空の場合はコードブロックを変更しない
+    #[serde(default = "default_progress_verbosity")]
+    pub progress_verbosity: ProgressVerbosity,
+    #[serde(default)]
+    pub add_utf8_bom: bool, // output_encoding が utf8 の場合に BOM を付けて保存（古いメモ帳向け）
+    #[serde(default = "default_gemini_request_timeout_seconds")]
+    pub gemini_request_timeout_seconds: u64,
+    #[serde(default = "default_gemini_generation_timeout_seconds")]
+    pub gemini_generation_timeout_seconds: u64, // 長い動画の生成は数分かかる
+    #[serde(default)]
+    pub enable_telemetry: bool, // オプトイン。匿名の利用統計のみ送信
+    #[serde(default)]
+    pub telemetry_endpoint: String,
+    #[serde(default)]
+    pub telemetry_prompt_shown: bool, // 初回起動時の同意ダイアログを表示済みか
+    #[serde(default)]
+    pub generate_image_captions: bool, // スクリーンショットの代替テキストをAIで生成
+    #[serde(default)]
+    pub html_report_template: Option<String>, // {{content}} を含むHTMLテンプレートのパス
+    #[serde(default)]
+    pub keyframe_aligned_extraction: bool, // 標準のフレーム抽出で最も近いキーフレームを使用
+    #[serde(default)]
+    pub model_fallback_chain: Vec<String>, // 429/503/404 の場合に順に試すモデル
+    #[serde(default)]
+    pub segment_overlap_seconds: f64, // 分割した2つ目以降のセグメントを前のセグメントと重ねる秒数
+    #[serde(default = "default_prevent_duplicate_presets")]
+    pub prevent_duplicate_presets: bool, // プロンプト本文が既存と同じプリセットを作成しない
+    #[serde(default)]
+    pub upload_bandwidth_limit_kbps: Option<u64>, // None = 無制限、実際の速度は1チャンク分まで超えることがある
+    #[serde(default = "default_chapter_split_min_duration_seconds")]
+    pub chapter_split_min_duration_seconds: f64, // これより短いチャプターは前後のチャプターと結合
+    #[serde(default = "default_line_ending")]
+    pub line_ending: LineEnding, // 保存するドキュメントの改行コード
+    #[serde(default = "default_progress_event_debounce_ms")]
+    pub progress_event_debounce_ms: u64, // エンコード中の進捗イベントの最小送信間隔（0で無効）
+    #[serde(default)]
+    pub enable_context_cache: bool, // プロンプトを分割して同じ動画に複数回問い合わせる場合に動画をキャッシュ
+}
+
+impl AppSettings {
+    /// Settings with every field at its default value except the API key.
+    /// Built through serde so the `default_*` functions above stay the single source of defaults.
+    pub fn defaults_with_api_key(gemini_api_key: String) -> Self {
+        serde_json::from_value(serde_json::json!({ "gemini_api_key": gemini_api_key }))
+            .expect("every AppSettings field except gemini_api_key has a default")
+    }
+}
+
+/// Timeouts for the ffprobe metadata queries and ffmpeg operations of one job
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessTimeouts {
+    pub ffprobe_secs: u64,
+    pub ffmpeg_secs: u64,
+}
+
+impl ProcessTimeouts {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        ProcessTimeouts {
+            ffprobe_secs: settings.ffprobe_timeout_seconds.max(1),
+            ffmpeg_secs: settings.ffmpeg_timeout_seconds.max(1),
+        }
+    }
+}
+
+impl Default for ProcessTimeouts {
+    fn default() -> Self {
+        ProcessTimeouts {
+            ffprobe_secs: default_ffprobe_timeout_seconds(),
+            ffmpeg_secs: default_ffmpeg_timeout_seconds(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ValidationError {
+    EmptyFile { name: String },
+    ExceedsUploadLimit { name: String, size: u64, limit: u64 },
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::EmptyFile { name } => write!(f, "{}: File is empty", name),
+            ValidationError::ExceedsUploadLimit { name, size, limit } => write!(
+                f,
+                "{}: File size ({:.1} MB) exceeds the upload limit ({:.1} MB). Consider enabling video quality downscaling",
+                name,
+                *size as f64 / 1_000_000.0,
+                *limit as f64 / 1_000_000.0
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoValidationResult {
+    pub file_name: String,
+    pub valid: bool,
+    pub duration_seconds: Option<f64>,
+    pub error: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateFilesError {
+    pub pairs: Vec<(String, String)>,
+}
+
+impl std::fmt::Display for DuplicateFilesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Duplicate input files detected:")?;
+        for (first, second) in &self.pairs {
+            write!(f, "\n{} and {} are the same video", first, second)?;
+        }
+        Ok(())
+    }
+}
+
+/// Error body returned by the Gemini API, see `gemini::parse_gemini_error`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeminiApiError {
+    pub code: u32, // 0 if the body was not a Gemini error object
+    pub message: String,
+    pub status: String,
+    pub user_facing_message: String,
+}
+
+impl std::fmt::Display for GeminiApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.user_facing_message)
+    }
+}
+
+impl std::error::Error for GeminiApiError {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelCache {
+    pub fetched_at: i64, // UNIX時刻（秒）
+    pub models: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigPaths {
+    pub settings_path: String,
+    pub presets_path: String,
+}
+
+/// Anonymous usage statistics sent after a successful generation when telemetry is enabled.
+/// Never contains file paths, API keys or document content.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetryPayload {
+    pub model_name_hash: String,
+    pub language: String,
+    pub file_count: u32,
+    pub total_duration_seconds: f64,
+    pub has_custom_prompt: bool,
+    pub app_version: String,
+}
+
+/// Result of `clean_stale_intermediate_files`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CleanupReport {
+    pub files_found: Vec<String>,
+    pub total_bytes_found: u64,
+    pub files_deleted: Vec<String>,
+    pub total_bytes_freed: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskSpaceCheck {
+    pub required_bytes: u64,
+    pub available_bytes: u64,
+    pub sufficient: bool,
+    pub recommendation: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookOutput {
+    pub command: String,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LintIssue {
+    pub rule: String,
+    pub line: usize,
+    pub message: String,
+    pub auto_fixed: bool,
+}
+
+/// Contents of the `.meta.json` file written next to a saved document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationSidecar {
+    pub generated_at: String,
+    pub model: String,
+    pub temperature: f64,
+    pub language: String,
+    pub source_files: Vec<String>,
+    pub prompt_preset_id: Option<String>,
+    #[serde(default)]
+    pub prompt_preset_version: Option<u32>,
+    pub gemini_file_uris: Vec<String>,
+}
+
+/// Payload of `model_fallback`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelFallback {
+    pub original_model: String,
+    pub fallback_model: String,
+    pub reason: String,
+}
+
+/// Payload of `prompt_chunking_applied`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptChunking {
+    pub original_tokens: u64,
+    pub chunks: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentSizeWarning {
+    pub actual: usize,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenFailure {
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookFailure {
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub message: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -69,10 +1078,38 @@ pub struct ProgressUpdate {
     pub message: String,
     pub step: usize,
     pub total_steps: usize,
+    pub elapsed_seconds: f64,
+    pub estimated_remaining_seconds: Option<f64>,
+}
+
+impl ProgressUpdate {
+    /// Creates a progress update with the elapsed time since the job started.
+    /// The remaining time is estimated once at least 10% of the steps are done.
+    pub fn new(message: String, step: usize, total_steps: usize, started_at: Instant) -> Self {
+        let elapsed_seconds = started_at.elapsed().as_secs_f64();
+        let ratio = if total_steps > 0 {
+            step as f64 / total_steps as f64
+        } else {
+            0.0
+        };
+        let estimated_remaining_seconds = if ratio >= 0.1 {
+            Some((elapsed_seconds / ratio - elapsed_seconds).max(0.0))
+        } else {
+            None
+        };
+
+        ProgressUpdate {
+            message,
+            step,
+            total_steps,
+            elapsed_seconds,
+            estimated_remaining_seconds,
+        }
+    }
 }
 
-pub fn default_language() -> String {
-    "japanese".to_string()
+pub fn default_language() -> DocumentLanguage {
+    DocumentLanguage::Japanese
 }
 
 pub fn default_temperature() -> f64 {
@@ -83,6 +1120,78 @@ pub fn default_gemini_model() -> String {
     "gemini-2.5-pro".to_string()
 }
 
+pub fn default_max_upload_bytes() -> u64 {
+    2 * 1024 * 1024 * 1024 // Gemini File API limit (2 GB)
+}
+
+pub fn default_output_encoding() -> OutputEncoding {
+    OutputEncoding::Utf8
+}
+
+pub fn default_progress_event_debounce_ms() -> u64 {
+    200
+}
+
+pub fn default_line_ending() -> LineEnding {
+    LineEnding::Lf
+}
+
+pub fn default_max_concurrent_frame_extractions() -> usize {
+    4
+}
+
+pub fn default_document_watermark_position() -> DocumentWatermarkPosition {
+    DocumentWatermarkPosition::Top
+}
+
+pub fn default_gemini_api_version() -> GeminiApiVersion {
+    GeminiApiVersion::V1Beta
+}
+
+pub fn default_embed_video_link() -> bool {
+    true
+}
+
+pub fn default_slide_frame_rate() -> f64 {
+    0.5
+}
+
+pub fn default_ffprobe_timeout_seconds() -> u64 {
+    30
+}
+
+pub fn default_ffmpeg_timeout_seconds() -> u64 {
+    600
+}
+
+pub fn default_gemini_request_timeout_seconds() -> u64 {
+    300
+}
+
+pub fn default_gemini_generation_timeout_seconds() -> u64 {
+    600
+}
+
+pub fn default_upload_chunk_size_mb() -> u32 {
+    8
+}
+
+pub fn default_frame_output_format() -> FrameOutputFormat {
+    FrameOutputFormat::Png
+}
+
+pub fn default_prompt_preset_version() -> u32 {
+    1
+}
+
+pub fn default_format_preset() -> DocumentFormatPreset {
+    DocumentFormatPreset::UserManual
+}
+
+pub fn default_split_mode() -> SplitMode {
+    SplitMode::Duration
+}
+
 pub fn default_video_quality() -> VideoQuality {
     VideoQuality::NoConversion
 }
@@ -91,6 +1200,54 @@ pub fn default_image_embed_frequency() -> ImageEmbedFrequency {
     ImageEmbedFrequency::Moderate
 }
 
+pub fn default_strip_ai_preamble() -> bool {
+    true
+}
+
+pub fn default_prevent_duplicate_presets() -> bool {
+    true
+}
+
+pub fn default_chapter_split_min_duration_seconds() -> f64 {
+    60.0
+}
+
+pub fn default_max_heading_depth() -> u8 {
+    4
+}
+
+pub fn default_image_grid_columns() -> u32 {
+    4
+}
+
+pub fn default_max_prompt_tokens() -> u32 {
+    100_000
+}
+
+pub fn default_progress_verbosity() -> ProgressVerbosity {
+    ProgressVerbosity::Normal
+}
+
+pub fn default_notification_sound() -> NotificationSound {
+    NotificationSound::None
+}
+
+pub fn default_integration_strategy() -> IntegrationStrategy {
+    IntegrationStrategy::Sequential
+}
+
+pub fn default_ai_backend() -> AiBackend {
+    AiBackend::Gemini
+}
+
+pub fn default_claude_model() -> String {
+    "claude-sonnet-4-5".to_string()
+}
+
+pub fn default_document_numbering_style() -> NumberingStyle {
+    NumberingStyle::Automatic
+}
+
 pub fn default_frame_extraction_method() -> FrameExtractionMethod {
     FrameExtractionMethod::Standard
 }
@@ -102,6 +1259,56 @@ pub struct PromptPreset {
     pub prompt: String,
     #[serde(default)]
     pub is_default: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format_preset: Option<DocumentFormatPreset>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default = "default_prompt_preset_version")]
+    pub version: u32, // プロンプト本文が変更されて保存されるたびに増える
+}
+
+/// One entry of `preset_history.json`, recorded whenever a preset's prompt changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptPresetRevision {
+    pub preset_id: String,
+    pub version: u32,
+    pub prompt: String,
+    pub prompt_sha256: String,
+    pub saved_at: String,
+}
+
+/// A preset that was not saved because another preset already has the same prompt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicatePresetWarning {
+    pub existing_id: String,
+    pub existing_name: String,
+    pub dropped_id: String,
+    pub dropped_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum PresetFileFormat {
+    #[serde(rename = "xml")]
+    Xml,
+    #[serde(rename = "json")]
+    Json,
+}
+
+impl PresetFileFormat {
+    /// Determines the format from the file extension, defaulting to XML
+    pub fn from_path(path: &std::path::Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => PresetFileFormat::Json,
+            _ => PresetFileFormat::Xml,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PresetFileFormat::Xml => "xml",
+            PresetFileFormat::Json => "json",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -114,12 +1321,93 @@ pub struct GeminiRequest {
     pub contents: Vec<GeminiContent>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub generation_config: Option<GeminiGenerationConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<serde_json::Value>>,
+    #[serde(rename = "safetySettings", skip_serializing_if = "Option::is_none")]
+    pub safety_settings: Option<Vec<GeminiSafetySetting>>,
+    #[serde(rename = "cachedContent", skip_serializing_if = "Option::is_none")]
+    pub cached_content: Option<String>, // create_gemini_cache で作成したキャッシュ名
+}
+
+impl GeminiRequest {
+    /// Refers to the files through a cache created by `gemini::create_gemini_cache`
+    /// instead of sending their `file_data` parts again
+    pub fn with_cached_content(mut self, cache_name: String) -> Self {
+        for content in &mut self.contents {
+            content
+                .parts
+                .retain(|part| !matches!(part, GeminiPart::FileData { .. }));
+        }
+        self.cached_content = Some(cache_name);
+        self
+    }
+}
+
+// Gemini safety setting categories
+pub const HARM_CATEGORY_HARASSMENT: &str = "HARM_CATEGORY_HARASSMENT";
+pub const HARM_CATEGORY_HATE_SPEECH: &str = "HARM_CATEGORY_HATE_SPEECH";
+pub const HARM_CATEGORY_SEXUALLY_EXPLICIT: &str = "HARM_CATEGORY_SEXUALLY_EXPLICIT";
+pub const HARM_CATEGORY_DANGEROUS_CONTENT: &str = "HARM_CATEGORY_DANGEROUS_CONTENT";
+pub const HARM_CATEGORY_CIVIC_INTEGRITY: &str = "HARM_CATEGORY_CIVIC_INTEGRITY";
+
+// Gemini safety setting thresholds
+pub const HARM_BLOCK_THRESHOLD_UNSPECIFIED: &str = "HARM_BLOCK_THRESHOLD_UNSPECIFIED";
+pub const BLOCK_LOW_AND_ABOVE: &str = "BLOCK_LOW_AND_ABOVE";
+pub const BLOCK_MEDIUM_AND_ABOVE: &str = "BLOCK_MEDIUM_AND_ABOVE";
+pub const BLOCK_ONLY_HIGH: &str = "BLOCK_ONLY_HIGH";
+pub const BLOCK_NONE: &str = "BLOCK_NONE";
+pub const OFF: &str = "OFF";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GeminiSafetySetting {
+    pub category: String,  // HARM_CATEGORY_* のいずれか
+    pub threshold: String, // BLOCK_* または OFF
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeminiGenerationConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_mime_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_schema: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking_config: Option<GeminiThinkingConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeminiThinkingConfig {
+    pub thinking_budget: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Flashcard {
+    pub front: String,
+    pub back: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructuredDocument {
+    pub title: String,
+    #[serde(default)]
+    pub sections: Vec<StructuredSection>,
+    #[serde(default)]
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructuredSection {
+    pub heading: String,
+    pub content: String,
+    #[serde(default)]
+    pub screenshots: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -132,6 +1420,19 @@ pub struct GeminiContent {
 pub enum GeminiPart {
     Text { text: String },
     FileData { file_data: GeminiFileData },
+    InlineData { inline_data: GeminiInlineData },
+}
+
+#[derive(Debug, Clone)]
+pub struct SampledFrame {
+    pub path: std::path::PathBuf,
+    pub timestamp_seconds: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeminiInlineData {
+    pub mime_type: String,
+    pub data: String, // base64
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -140,14 +1441,86 @@ pub struct GeminiFileData {
     pub file_uri: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaudeRequest {
+    pub model: String,
+    pub max_tokens: u32,
+    pub messages: Vec<ClaudeMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaudeMessage {
+    pub role: String,
+    pub content: Vec<ClaudeContentBlock>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ClaudeContentBlock {
+    #[serde(rename = "text")]
+    Text { text: String },
+    #[serde(rename = "image")]
+    Image { source: ClaudeImageSource },
+    #[serde(other)]
+    Other, // 使用しないブロック（thinking など）
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaudeImageSource {
+    #[serde(rename = "type")]
+    pub source_type: String, // "base64"
+    pub media_type: String,
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaudeResponse {
+    pub content: Vec<ClaudeContentBlock>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeminiResponse {
     pub candidates: Vec<GeminiCandidate>,
+    #[serde(default, rename = "usageMetadata")]
+    pub usage_metadata: Option<GeminiUsageMetadata>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiUsageMetadata {
+    #[serde(default)]
+    pub prompt_token_count: Option<u64>,
+    #[serde(default)]
+    pub candidates_token_count: Option<u64>,
+    #[serde(default)]
+    pub total_token_count: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeminiCandidate {
     pub content: GeminiContent,
+    #[serde(default, rename = "groundingMetadata")]
+    pub grounding_metadata: Option<GeminiGroundingMetadata>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiGroundingMetadata {
+    #[serde(default)]
+    pub grounding_chunks: Vec<GeminiGroundingChunk>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeminiGroundingChunk {
+    #[serde(default)]
+    pub web: Option<GeminiWebSource>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeminiWebSource {
+    pub uri: String,
+    #[serde(default)]
+    pub title: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
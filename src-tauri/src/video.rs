@@ -1,14 +1,29 @@
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
-use std::io::{BufRead, BufReader};
+use std::process::{Command, Output, Stdio};
+use std::io::{BufRead, BufReader, Read};
+use std::thread;
+use std::time::{Duration, Instant};
 
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 
 use anyhow::{anyhow, Result};
 use log::debug;
+use regex::Regex;
+use sha2::{Digest, Sha256};
 
-use crate::types::VideoQuality;
+use crate::types::{
+    merge_short_chapters, parse_ffprobe_chapters, AudioTrackInfo, ChapterSpan, FrameOutputFormat, ProcessTimeouts, SplitMode, StreamInfo, VideoFile, VideoQuality, VideoValidationResult, WatermarkPosition,
+    WatermarkSettings,
+};
+
+const SEGMENT_DURATION_SECS: f64 = 3600.0;
+// How far before the 1 hour limit a silence may be used as the split point
+const SILENCE_SEARCH_WINDOW_SECS: f64 = 300.0;
+const VALIDATION_READ_SECONDS: u32 = 10;
+const MIN_EXPECTED_DURATION_SECS: f64 = 5.0;
+const HIGH_BITRATE_THRESHOLD: u64 = 50_000_000; // 50 Mbps
 
 #[derive(Debug, Clone)]
 pub struct VideoResolution {
@@ -17,21 +32,108 @@ pub struct VideoResolution {
 }
 // Removed deprecated tauri::api::process::Command import
 
-fn find_executable(name: &str) -> Result<PathBuf> {
-    // First, check common paths for Homebrew and system installations
-    let common_paths = [
-        "/opt/homebrew/bin",      // Homebrew on Apple Silicon
-        "/usr/local/bin",         // Homebrew on Intel Mac / general Unix
-        "/usr/bin",               // System binaries
-        "/bin",                   // Core system binaries
-        "/opt/local/bin",         // MacPorts
-        "/sw/bin",                // Fink
-        "/usr/local/opt/ffmpeg/bin", // Homebrew ffmpeg formula specific
-        "/opt/homebrew/opt/ffmpeg/bin", // Homebrew ffmpeg on Apple Silicon
-    ];
-    
+/// Runs the command like `Command::output()`, killing it if it does not finish within the timeout
+/// Corrupt input files can make ffprobe and ffmpeg hang forever
+pub fn run_with_timeout(command: &mut Command, timeout_secs: u64) -> Result<Output> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Drain the pipes on separate threads so the process never blocks on a full pipe
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let stdout_reader = thread::spawn(move || read_pipe(stdout));
+    let stderr_reader = thread::spawn(move || read_pipe(stderr));
+
+    let deadline = Instant::now() + Duration::from_secs(timeout_secs);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(anyhow!(
+                "{} timed out after {} seconds",
+                command.get_program().to_string_lossy(),
+                timeout_secs
+            ));
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    Ok(Output {
+        status,
+        stdout: stdout_reader.join().unwrap_or_default(),
+        stderr: stderr_reader.join().unwrap_or_default(),
+    })
+}
+
+fn read_pipe<R: Read>(pipe: Option<R>) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    if let Some(mut pipe) = pipe {
+        let _ = pipe.read_to_end(&mut buffer);
+    }
+    buffer
+}
+
+/// Directories checked for an executable before falling back to PATH, in search order
+fn executable_search_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+
+    // Homebrew and system installations
+    #[cfg(unix)]
+    dirs.extend(
+        [
+            "/opt/homebrew/bin",            // Homebrew on Apple Silicon
+            "/usr/local/bin",               // Homebrew on Intel Mac / general Unix
+            "/usr/bin",                     // System binaries
+            "/bin",                         // Core system binaries
+            "/opt/local/bin",               // MacPorts
+            "/sw/bin",                      // Fink
+            "/usr/local/opt/ffmpeg/bin",    // Homebrew ffmpeg formula specific
+            "/opt/homebrew/opt/ffmpeg/bin", // Homebrew ffmpeg on Apple Silicon
+        ]
+        .iter()
+        .map(PathBuf::from),
+    );
+
+    // Manual installs, then Scoop and Chocolatey locations from their environment variables,
+    // then the default per-user Scoop directory
+    #[cfg(windows)]
+    {
+        let env_dir = |name: &str| std::env::var_os(name).map(PathBuf::from);
+
+        dirs.push(PathBuf::from(r"C:\ffmpeg\bin"));
+        dirs.push(PathBuf::from(r"C:\Program Files\ffmpeg\bin"));
+        if let Some(program_files) = env_dir("ProgramFiles") {
+            dirs.push(program_files.join("ffmpeg").join("bin"));
+        }
+        for scoop_root in ["SCOOP", "SCOOP_GLOBAL"].into_iter().filter_map(env_dir) {
+            dirs.push(scoop_root.join(r"apps\ffmpeg\current\bin"));
+            dirs.push(scoop_root.join("shims"));
+        }
+        if let Some(chocolatey) = env_dir("ChocolateyInstall") {
+            dirs.push(chocolatey.join("bin"));
+        }
+        if let Some(user_profile) = env_dir("USERPROFILE") {
+            dirs.push(user_profile.join(r"scoop\apps\ffmpeg\current\bin"));
+            dirs.push(user_profile.join(r"scoop\shims"));
+        }
+    }
+
+    dirs
+}
+
+pub(crate) fn find_executable(name: &str) -> Result<PathBuf> {
+    // First, check the common installation directories
+    let common_paths = executable_search_dirs();
+    let file_name = format!("{}{}", name, std::env::consts::EXE_SUFFIX);
+
     for path in common_paths.iter() {
-        let executable_path = Path::new(path).join(name);
+        let executable_path = path.join(&file_name);
         if executable_path.is_file() {
             debug!("Found {} at: {:?}", name, executable_path);
             return Ok(executable_path);
@@ -54,17 +156,18 @@ fn find_executable(name: &str) -> Result<PathBuf> {
     })
 }
 
-/// Gets the resolution of a video file using ffprobe
-pub async fn get_video_resolution(video_path: &str) -> Result<VideoResolution> {
+/// Gets the resolution of a video stream using ffprobe
+pub async fn get_video_resolution(video_path: &str, video_stream_index: u32, timeouts: &ProcessTimeouts) -> Result<VideoResolution> {
     debug!("Getting video resolution for: {}", video_path);
     let ffprobe_path = find_executable("ffprobe")?;
+    let stream_selector = format!("v:{}", video_stream_index);
 
     let mut command = Command::new(&ffprobe_path);
     command.args([
         "-v",
         "error",
         "-select_streams",
-        "v:0",
+        &stream_selector,
         "-show_entries",
         "stream=width,height",
         "-of",
@@ -75,7 +178,7 @@ pub async fn get_video_resolution(video_path: &str) -> Result<VideoResolution> {
     #[cfg(target_os = "windows")]
     command.creation_flags(0x08000000); // CREATE_NO_WINDOW
 
-    let output = command.output()?;
+    let output = run_with_timeout(&mut command, timeouts.ffprobe_secs)?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -101,8 +204,100 @@ pub async fn get_video_resolution(video_path: &str) -> Result<VideoResolution> {
     Ok(VideoResolution { width, height })
 }
 
+/// Gets the codec name of the first video stream using ffprobe
+pub async fn get_video_codec(video_path: &str, timeouts: &ProcessTimeouts) -> Result<String> {
+    debug!("Getting video codec for: {}", video_path);
+    let ffprobe_path = find_executable("ffprobe")?;
+
+    let mut command = Command::new(&ffprobe_path);
+    command.args([
+        "-v",
+        "quiet",
+        "-show_entries",
+        "stream=codec_name",
+        "-select_streams",
+        "v:0",
+        "-of",
+        "default=noprint_wrappers=1:nokey=1",
+        video_path,
+    ]);
+
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let output = run_with_timeout(&mut command, timeouts.ffprobe_secs)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("ffprobe failed: {}", stderr));
+    }
+
+    let codec = String::from_utf8(output.stdout)?.trim().to_string();
+    debug!("Got codec: {}", codec);
+
+    if codec.is_empty() {
+        return Err(anyhow!("No video stream found in {}", video_path));
+    }
+
+    Ok(codec)
+}
+
+/// Gets the timestamps of all keyframes of the first video stream using ffprobe
+pub async fn get_video_keyframe_timestamps(video_path: &str, timeouts: &ProcessTimeouts) -> Result<Vec<f64>> {
+    debug!("Getting keyframe timestamps for: {}", video_path);
+    let ffprobe_path = find_executable("ffprobe")?;
+
+    let mut command = Command::new(&ffprobe_path);
+    command.args([
+        "-v",
+        "error",
+        "-select_streams",
+        "v:0",
+        "-show_entries",
+        "packet=pts_time,flags",
+        "-of",
+        "csv",
+        video_path,
+    ]);
+
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let output = run_with_timeout(&mut command, timeouts.ffprobe_secs)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("ffprobe failed: {}", stderr));
+    }
+
+    // Each line looks like `packet,12.345000,K__`
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut keyframes: Vec<f64> = stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.trim().split(',').skip(1);
+            let pts_time = fields.next()?.parse::<f64>().ok()?;
+            let flags = fields.next()?;
+            flags.contains('K').then_some(pts_time)
+        })
+        .collect();
+    keyframes.sort_by(|a, b| a.total_cmp(b));
+    debug!("Found {} keyframes", keyframes.len());
+
+    Ok(keyframes)
+}
+
+/// Returns the keyframe closest to the timestamp, or the timestamp itself when there are none
+pub fn nearest_keyframe(ts: f64, keyframes: &[f64]) -> f64 {
+    keyframes
+        .iter()
+        .copied()
+        .min_by(|a, b| (a - ts).abs().total_cmp(&(b - ts).abs()))
+        .unwrap_or(ts)
+}
+
 /// Gets the duration of a video file in seconds using ffprobe
-pub async fn get_video_duration(video_path: &str) -> Result<f64> {
+pub async fn get_video_duration(video_path: &str, timeouts: &ProcessTimeouts) -> Result<f64> {
     debug!("Getting video duration for: {}", video_path);
     let ffprobe_path = find_executable("ffprobe")?;
 
@@ -120,43 +315,738 @@ pub async fn get_video_duration(video_path: &str) -> Result<f64> {
     #[cfg(target_os = "windows")]
     command.creation_flags(0x08000000); // CREATE_NO_WINDOW
 
-    let output = command.output()?;
+    let output = run_with_timeout(&mut command, timeouts.ffprobe_secs)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("ffprobe failed: {}", stderr));
+    }
+
+    let duration_str = String::from_utf8(output.stdout)?.trim().to_string();
+    debug!("Got duration: {}", duration_str);
+
+    duration_str.parse::<f64>().map_err(|e| {
+        anyhow!(
+            "Failed to parse ffprobe duration output '{}': {}",
+            duration_str,
+            e
+        )
+    })
+}
+
+/// Computes a fast fingerprint of a video from its size, duration, encoder tag and first frame.
+/// Much cheaper than hashing the whole file, but still distinguishes different recordings in practice.
+pub async fn compute_video_fingerprint(video_path: &str, timeouts: &ProcessTimeouts) -> Result<String> {
+    debug!("Computing video fingerprint for: {}", video_path);
+    let ffprobe_path = find_executable("ffprobe")?;
+
+    let mut command = Command::new(&ffprobe_path);
+    command.args([
+        "-v",
+        "error",
+        "-show_entries",
+        "format_tags=encoder",
+        "-show_entries",
+        "format=size,duration",
+        "-of",
+        "json",
+        video_path,
+    ]);
+
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let output = run_with_timeout(&mut command, timeouts.ffprobe_secs)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("ffprobe failed: {}", stderr));
+    }
+
+    let probe: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let format = &probe["format"];
+    let file_size = format["size"].as_str().unwrap_or_default();
+    let duration_seconds = format["duration"].as_str().unwrap_or_default();
+    let encoder = format["tags"]["encoder"].as_str().unwrap_or_default();
+
+    // Decode the first frame as PNG directly to stdout
+    let ffmpeg_path = find_executable("ffmpeg")?;
+    let mut command = Command::new(&ffmpeg_path);
+    command.args([
+        "-v",
+        "error",
+        "-i",
+        video_path,
+        "-frames:v",
+        "1",
+        "-f",
+        "image2pipe",
+        "-vcodec",
+        "png",
+        "-",
+    ]);
+
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let output = run_with_timeout(&mut command, timeouts.ffmpeg_secs)?;
+
+    if !output.status.success() || output.stdout.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to extract first frame: {}", stderr));
+    }
+
+    let first_frame_hash = Sha256::digest(&output.stdout);
+
+    let mut hasher = Sha256::new();
+    hasher.update(file_size.as_bytes());
+    hasher.update(b"|");
+    hasher.update(duration_seconds.as_bytes());
+    hasher.update(b"|");
+    hasher.update(encoder.as_bytes());
+    hasher.update(b"|");
+    hasher.update(first_frame_hash);
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Hashes the first megabyte of a file so that a file still being written can be detected.
+/// The read runs on the blocking thread pool to keep the async runtime responsive.
+pub async fn hash_first_megabyte(path: &str) -> Result<String> {
+    let path = path.to_string();
+    tokio::task::spawn_blocking(move || -> Result<String> {
+        let file = std::fs::File::open(&path)?;
+        let mut buffer = Vec::with_capacity(1024 * 1024);
+        file.take(1024 * 1024).read_to_end(&mut buffer)?;
+        Ok(format!("{:x}", Sha256::digest(&buffer)))
+    })
+    .await
+    .map_err(|e| anyhow!("Hash task failed: {}", e))?
+}
+
+/// Checks that a video file is complete and readable before it is queued.
+/// Only the first seconds are demuxed so the check stays fast for large files.
+pub async fn validate_video_file(file: &VideoFile, timeouts: &ProcessTimeouts) -> VideoValidationResult {
+    let mut result = VideoValidationResult {
+        file_name: file.name.clone(),
+        valid: false,
+        duration_seconds: None,
+        error: None,
+        warnings: Vec::new(),
+    };
+
+    match check_video_file(file, &mut result.warnings, timeouts).await {
+        Ok(duration) => {
+            result.valid = true;
+            result.duration_seconds = duration;
+        }
+        Err(e) => result.error = Some(e.to_string()),
+    }
+
+    result
+}
+
+async fn check_video_file(file: &VideoFile, warnings: &mut Vec<String>, timeouts: &ProcessTimeouts) -> Result<Option<f64>> {
+    debug!("Validating video file: {}", file.path);
+
+    // A size mismatch usually means the file was still being downloaded or copied
+    let metadata = std::fs::metadata(&file.path)
+        .map_err(|e| anyhow!("File cannot be read: {}", e))?;
+    if metadata.len() != file.size {
+        return Err(anyhow!(
+            "File size changed ({} bytes expected, {} bytes on disk). The file may be incomplete",
+            file.size,
+            metadata.len()
+        ));
+    }
+
+    let ffprobe_path = find_executable("ffprobe")?;
+
+    // Demux the first seconds to detect container errors
+    let mut command = Command::new(&ffprobe_path);
+    command.args([
+        "-v",
+        "error",
+        "-read_intervals",
+        &format!("%+{}", VALIDATION_READ_SECONDS),
+        "-show_entries",
+        "packet=pts_time",
+        "-of",
+        "csv=p=0",
+        &file.path,
+    ]);
+
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let output = run_with_timeout(&mut command, timeouts.ffprobe_secs)?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() || !stderr.trim().is_empty() {
+        return Err(anyhow!("The file appears to be corrupted: {}", stderr.trim()));
+    }
+
+    let mut command = Command::new(&ffprobe_path);
+    command.args([
+        "-v",
+        "error",
+        "-show_entries",
+        "stream=codec_type:format=duration,bit_rate",
+        "-of",
+        "json",
+        &file.path,
+    ]);
+
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let output = run_with_timeout(&mut command, timeouts.ffprobe_secs)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("ffprobe failed: {}", stderr));
+    }
+
+    let probe: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| anyhow!("Failed to parse ffprobe output: {}", e))?;
+
+    let streams = probe["streams"].as_array().cloned().unwrap_or_default();
+    let has_stream = |codec_type: &str| {
+        streams
+            .iter()
+            .any(|stream| stream["codec_type"].as_str() == Some(codec_type))
+    };
+    if !has_stream("video") {
+        return Err(anyhow!("No video stream found"));
+    }
+    if !has_stream("audio") {
+        warnings.push("音声トラックがありません".to_string());
+    }
+
+    let duration = probe["format"]["duration"]
+        .as_str()
+        .and_then(|d| d.parse::<f64>().ok());
+    if let Some(duration) = duration {
+        if duration < MIN_EXPECTED_DURATION_SECS {
+            warnings.push(format!("動画が非常に短いです ({:.1}秒)", duration));
+        }
+    }
+
+    let bit_rate = probe["format"]["bit_rate"]
+        .as_str()
+        .and_then(|b| b.parse::<u64>().ok());
+    if let Some(bit_rate) = bit_rate {
+        if bit_rate > HIGH_BITRATE_THRESHOLD {
+            warnings.push(format!(
+                "ビットレートが非常に高いです ({:.1} Mbps)。低容量化を検討してください",
+                bit_rate as f64 / 1_000_000.0
+            ));
+        }
+    }
+
+    Ok(duration)
+}
+
+/// Cuts the range between the start and end offsets out of a video without re-encoding.
+/// Returns the path of the trimmed file in the output directory.
+pub async fn trim_video(
+    video_path: &str,
+    start_offset_seconds: Option<f64>,
+    end_offset_seconds: Option<f64>,
+    output_dir: &Path,
+    timeouts: &ProcessTimeouts,
+) -> Result<PathBuf> {
+    debug!(
+        "Trimming video {} from {:?} to {:?}",
+        video_path, start_offset_seconds, end_offset_seconds
+    );
+    let duration = get_video_duration(video_path, timeouts).await?;
+    let start = start_offset_seconds.unwrap_or(0.0);
+    let end = end_offset_seconds.unwrap_or(duration);
+
+    if start < 0.0 || start >= duration {
+        return Err(anyhow!(
+            "Start offset {:.1}s is outside the video (duration {:.1}s)",
+            start,
+            duration
+        ));
+    }
+    if end <= start {
+        return Err(anyhow!(
+            "End offset {:.1}s must be after the start offset {:.1}s",
+            end,
+            start
+        ));
+    }
+    if end > duration {
+        return Err(anyhow!(
+            "End offset {:.1}s exceeds the video duration {:.1}s",
+            end,
+            duration
+        ));
+    }
+
+    let input_path = Path::new(video_path);
+    let filename = input_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| anyhow!("Invalid video file name"))?;
+    let extension = input_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("mp4");
+    let output_path = output_dir.join(format!(
+        "{}_trimmed_{}-{}.{}",
+        filename, start as u64, end as u64, extension
+    ));
+
+    let ffmpeg_path = find_executable("ffmpeg")?;
+    let start_str = start.to_string();
+    let end_str = end.to_string();
+
+    let mut command = Command::new(&ffmpeg_path);
+    command.args([
+        "-i",
+        video_path,
+        "-ss",
+        &start_str,
+        "-to",
+        &end_str,
+        "-c",
+        "copy",
+        "-y",
+        output_path.to_str().unwrap(),
+    ]);
+
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let output = run_with_timeout(&mut command, timeouts.ffmpeg_secs)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to trim video: {}", stderr));
+    }
+
+    debug!("Trimmed video saved to: {:?}", output_path);
+    Ok(output_path)
+}
+
+/// Assembles slide images into an MP4 where every slide is shown for `1 / frame_rate` seconds
+pub async fn assemble_image_sequence(
+    image_paths: &[PathBuf],
+    frame_rate: f64,
+    output_path: &Path,
+    timeouts: &ProcessTimeouts,
+) -> Result<()> {
+    let last_image = image_paths
+        .last()
+        .ok_or_else(|| anyhow!("No images to assemble"))?;
+    if frame_rate <= 0.0 {
+        return Err(anyhow!("Slide frame rate must be positive: {}", frame_rate));
+    }
+    debug!(
+        "Assembling {} images into {:?} at {} fps",
+        image_paths.len(),
+        output_path,
+        frame_rate
+    );
+
+    // The concat demuxer handles mixed file names and extensions, unlike the image2 pattern input
+    let slide_duration = 1.0 / frame_rate;
+    let mut concat_list = String::new();
+    for image_path in image_paths {
+        concat_list.push_str(&format!(
+            "file '{}'\nduration {}\n",
+            escape_concat_path(image_path),
+            slide_duration
+        ));
+    }
+    // The last entry has to be repeated, otherwise its duration is ignored
+    concat_list.push_str(&format!("file '{}'\n", escape_concat_path(last_image)));
+
+    let list_file = tempfile::Builder::new().suffix(".txt").tempfile()?;
+    std::fs::write(list_file.path(), concat_list)?;
+
+    let ffmpeg_path = find_executable("ffmpeg")?;
+    let frame_rate_str = frame_rate.to_string();
+
+    let mut command = Command::new(&ffmpeg_path);
+    command.args([
+        "-f",
+        "concat",
+        "-safe",
+        "0",
+        "-i",
+        list_file.path().to_str().unwrap(),
+        // libx264 needs even dimensions and yuv420p for broad player support
+        "-vf",
+        "scale=trunc(iw/2)*2:trunc(ih/2)*2,format=yuv420p",
+        "-r",
+        &frame_rate_str,
+        "-c:v",
+        "libx264",
+        "-y",
+        output_path.to_str().unwrap(),
+    ]);
+
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let output = run_with_timeout(&mut command, timeouts.ffmpeg_secs)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to assemble image sequence: {}", stderr));
+    }
+
+    debug!("Image sequence assembled into: {:?}", output_path);
+    Ok(())
+}
+
+fn escape_concat_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/").replace('\'', "'\\''")
+}
+
+/// Subtitle file extensions that can be burned into the video
+pub const SUPPORTED_SUBTITLE_EXTENSIONS: [&str; 2] = ["srt", "ass"];
+
+/// Looks for a subtitle file with the same name as the video in the same directory
+pub fn find_companion_subtitle(video_path: &str) -> Option<String> {
+    let video_path = Path::new(video_path);
+    SUPPORTED_SUBTITLE_EXTENSIONS
+        .iter()
+        .map(|extension| video_path.with_extension(extension))
+        .find(|path| path.is_file())
+        .map(|path| path.to_string_lossy().to_string())
+}
+
+/// Checks that a subtitle file exists and has a supported extension
+pub fn validate_subtitle_file(subtitle_path: &str) -> Result<()> {
+    let path = Path::new(subtitle_path);
+    if !path.is_file() {
+        return Err(anyhow!("Subtitle file not found: {}", subtitle_path));
+    }
+
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default();
+    if !SUPPORTED_SUBTITLE_EXTENSIONS.contains(&extension.as_str()) {
+        return Err(anyhow!(
+            "Unsupported subtitle format: {} (supported: {})",
+            subtitle_path,
+            SUPPORTED_SUBTITLE_EXTENSIONS.join(", ")
+        ));
+    }
+
+    Ok(())
+}
+
+/// Gets the audio tracks of a video file using ffprobe
+pub async fn probe_audio_tracks(video_path: &str, timeouts: &ProcessTimeouts) -> Result<Vec<AudioTrackInfo>> {
+    debug!("Getting audio track info for: {}", video_path);
+    let ffprobe_path = find_executable("ffprobe")?;
+
+    let mut command = Command::new(&ffprobe_path);
+    command.args([
+        "-v",
+        "error",
+        "-select_streams",
+        "a",
+        "-show_streams",
+        "-of",
+        "json",
+        video_path,
+    ]);
+
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let output = run_with_timeout(&mut command, timeouts.ffprobe_secs)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("ffprobe failed: {}", stderr));
+    }
+
+    let probe: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| anyhow!("Failed to parse ffprobe output: {}", e))?;
+
+    let streams = probe["streams"].as_array().cloned().unwrap_or_default();
+    let tracks = streams
+        .iter()
+        .enumerate()
+        .map(|(i, stream)| {
+            // ffprobe reports some numeric values as strings
+            let as_u32 = |value: &serde_json::Value| {
+                value
+                    .as_u64()
+                    .map(|v| v as u32)
+                    .or_else(|| value.as_str().and_then(|v| v.parse::<u32>().ok()))
+            };
+            AudioTrackInfo {
+                index: i as u32,
+                language: stream["tags"]["language"].as_str().map(|s| s.to_string()),
+                codec: stream["codec_name"].as_str().unwrap_or("unknown").to_string(),
+                channels: as_u32(&stream["channels"]).unwrap_or(0),
+                sample_rate: as_u32(&stream["sample_rate"]).unwrap_or(0),
+                bit_rate_kbps: as_u32(&stream["bit_rate"]).map(|b| b / 1000),
+                title: stream["tags"]["title"].as_str().map(|s| s.to_string()),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    debug!("Found {} audio tracks", tracks.len());
+    Ok(tracks)
+}
+
+/// Lists the video, audio and subtitle streams of a file
+/// `index` is the position within streams of the same type (matches `-map 0:v:{index}`)
+pub async fn probe_streams(video_path: &str, timeouts: &ProcessTimeouts) -> Result<Vec<StreamInfo>> {
+    debug!("Getting stream list for: {}", video_path);
+    let ffprobe_path = find_executable("ffprobe")?;
+
+    let mut command = Command::new(&ffprobe_path);
+    command.args(["-v", "error", "-show_streams", "-of", "json", video_path]);
+
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let output = run_with_timeout(&mut command, timeouts.ffprobe_secs)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("ffprobe failed: {}", stderr));
+    }
+
+    let probe: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| anyhow!("Failed to parse ffprobe output: {}", e))?;
+
+    let mut type_counts: HashMap<String, u32> = HashMap::new();
+    let streams = probe["streams"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default()
+        .iter()
+        .map(|stream| {
+            let type_ = stream["codec_type"].as_str().unwrap_or("unknown").to_string();
+            let count = type_counts.entry(type_.clone()).or_insert(0);
+            let index = *count;
+            *count += 1;
+            StreamInfo {
+                index,
+                type_,
+                codec: stream["codec_name"].as_str().unwrap_or("unknown").to_string(),
+                label: stream["tags"]["title"]
+                    .as_str()
+                    .or_else(|| stream["tags"]["language"].as_str())
+                    .map(|s| s.to_string()),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    debug!("Found {} streams", streams.len());
+    Ok(streams)
+}
+
+/// Copies the selected video stream and audio track into a new file without re-encoding
+async fn select_streams(
+    video_path: &str,
+    video_stream_index: u32,
+    audio_track_index: Option<u32>,
+    output_dir: &Path,
+    timeouts: &ProcessTimeouts,
+) -> Result<PathBuf> {
+    let input_path = Path::new(video_path);
+    let filename = input_path.file_stem()
+        .ok_or_else(|| anyhow!("Invalid video file name"))?
+        .to_str()
+        .ok_or_else(|| anyhow!("Invalid video file name encoding"))?;
+    let output_path = output_dir.join(format!(
+        "{}_v{}_audio{}.mp4",
+        filename,
+        video_stream_index,
+        audio_track_index.unwrap_or(0)
+    ));
+
+    let ffmpeg_path = find_executable("ffmpeg")?;
+    let video_map = format!("0:v:{}", video_stream_index);
+    let audio_map = audio_map_for(audio_track_index);
+
+    let mut command = Command::new(&ffmpeg_path);
+    command.args([
+        "-i",
+        video_path,
+        "-map",
+        &video_map,
+        "-map",
+        &audio_map,
+        "-c",
+        "copy",
+        "-y",
+        output_path.to_str().unwrap(),
+    ])
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped());
+
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let output = run_with_timeout(&mut command, timeouts.ffmpeg_secs)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(
+            "Failed to select video stream {} and audio track {:?}: {}",
+            video_stream_index,
+            audio_track_index,
+            stderr
+        ));
+    }
+
+    debug!(
+        "Selected video stream {} and audio track {:?} into: {:?}",
+        video_stream_index, audio_track_index, output_path
+    );
+    Ok(output_path)
+}
+
+/// The `-map` target for the audio track, the first track is optional so silent videos still work
+fn audio_map_for(audio_track_index: Option<u32>) -> String {
+    match audio_track_index {
+        Some(index) => format!("0:a:{}", index),
+        None => "0:a:0?".to_string(),
+    }
+}
+
+/// Detects silent sections in the audio track using ffmpeg's silencedetect filter
+/// Returns the midpoint (in seconds) of each detected silence
+pub async fn detect_silence_boundaries(
+    video_path: &str,
+    threshold_db: f32,
+    min_duration_secs: f32,
+    timeouts: &ProcessTimeouts,
+) -> Result<Vec<f64>> {
+    debug!(
+        "Detecting silence in: {} (threshold: {}dB, min duration: {}s)",
+        video_path, threshold_db, min_duration_secs
+    );
+    let ffmpeg_path = find_executable("ffmpeg")?;
+
+    let silence_filter = format!("silencedetect=noise={}dB:d={}", threshold_db, min_duration_secs);
+    let mut command = Command::new(&ffmpeg_path);
+    command.args([
+        "-i",
+        video_path,
+        "-af",
+        &silence_filter,
+        "-f",
+        "null",
+        "-",
+    ]);
+
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let output = run_with_timeout(&mut command, timeouts.ffmpeg_secs)?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("ffprobe failed: {}", stderr));
+        return Err(anyhow!("ffmpeg silence detection failed: {}", stderr));
     }
 
-    let duration_str = String::from_utf8(output.stdout)?.trim().to_string();
-    debug!("Got duration: {}", duration_str);
+    // silencedetect writes its results to stderr
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let start_re = Regex::new(r"silence_start:\s*(-?\d+(?:\.\d+)?)").unwrap();
+    let end_re = Regex::new(r"silence_end:\s*(\d+(?:\.\d+)?)").unwrap();
 
-    duration_str.parse::<f64>().map_err(|e| {
-        anyhow!(
-            "Failed to parse ffprobe duration output '{}': {}",
-            duration_str,
-            e
-        )
-    })
+    let mut midpoints = Vec::new();
+    let mut current_start: Option<f64> = None;
+    for line in stderr.lines() {
+        if let Some(caps) = start_re.captures(line) {
+            current_start = caps[1].parse::<f64>().ok();
+        } else if let Some(caps) = end_re.captures(line) {
+            if let (Some(start), Ok(end)) = (current_start.take(), caps[1].parse::<f64>()) {
+                midpoints.push((start.max(0.0) + end) / 2.0);
+            }
+        }
+    }
+
+    debug!("Detected {} silence boundaries", midpoints.len());
+    Ok(midpoints)
+}
+
+/// Picks split points so that no segment is longer than 1 hour, cutting at the latest
+/// silence within `SILENCE_SEARCH_WINDOW_SECS` before each limit, or at the limit itself
+fn silence_split_points(silences: &[f64], duration: f64) -> Vec<f64> {
+    let mut points: Vec<f64> = Vec::new();
+    let mut previous = 0.0;
+    while duration - previous > SEGMENT_DURATION_SECS {
+        let limit = previous + SEGMENT_DURATION_SECS;
+        let point = silences
+            .iter()
+            .copied()
+            .filter(|s| *s >= limit - SILENCE_SEARCH_WINDOW_SECS && *s <= limit)
+            .max_by(|a, b| a.total_cmp(b))
+            .unwrap_or(limit);
+        points.push(point);
+        previous = point;
+    }
+    points
 }
 
 /// Splits a video file into segments if it's longer than 1 hour
 /// Returns a vector of file paths for the segments (or the original file if no split needed)
-pub async fn split_video_if_needed(video_path: &Path) -> Result<Vec<PathBuf>> {
-    let duration = get_video_duration(video_path.to_str().unwrap()).await?;
+/// Segments after the first start `overlap_seconds` before their split point
+/// so that no sentence or topic is cut at the boundary
+pub async fn split_video_if_needed(
+    video_path: &Path,
+    split_mode: &SplitMode,
+    overlap_seconds: f64,
+    timeouts: &ProcessTimeouts,
+) -> Result<Vec<PathBuf>> {
+    let duration = get_video_duration(video_path.to_str().unwrap(), timeouts).await?;
     debug!("Video duration: {} seconds", duration);
 
-    if duration <= 3600.0 {
+    if duration <= SEGMENT_DURATION_SECS {
         return Ok(vec![video_path.to_path_buf()]);
     }
 
     debug!("Video is longer than 1 hour, splitting...");
     let ffmpeg_path = find_executable("ffmpeg")?;
 
+    // Fixed split points at every hour mark
+    let hour_marks: Vec<f64> = (1..)
+        .map(|i| i as f64 * SEGMENT_DURATION_SECS)
+        .take_while(|mark| *mark < duration)
+        .collect();
+
+    let split_points = match split_mode {
+        SplitMode::Duration => hour_marks,
+        SplitMode::SilenceDetect {
+            silence_threshold_db,
+            min_silence_duration_secs,
+        } => {
+            let silences = detect_silence_boundaries(
+                video_path.to_str().unwrap(),
+                *silence_threshold_db,
+                *min_silence_duration_secs,
+                timeouts,
+            )
+            .await?;
+            silence_split_points(&silences, duration)
+        }
+    };
+    debug!("Split points: {:?}", split_points);
+
     let mut segment_paths = Vec::new();
-    let mut current_pos = 0.0;
-    let mut segment_index = 0;
+    let mut segment_starts = vec![0.0];
+    segment_starts.extend(split_points.iter().copied());
 
-    while current_pos < duration {
+    for (segment_index, &split_point) in segment_starts.iter().enumerate() {
+        let end = segment_starts.get(segment_index + 1).copied().unwrap_or(duration);
+        let start = (split_point - overlap_seconds.max(0.0)).max(0.0);
         let segment_filename = format!(
             "{}_segment_{}.mp4",
             video_path.file_stem().unwrap().to_str().unwrap(),
@@ -169,9 +1059,9 @@ pub async fn split_video_if_needed(video_path: &Path) -> Result<Vec<PathBuf>> {
             "-i",
             video_path.to_str().unwrap(),
             "-ss",
-            &current_pos.to_string(),
+            &start.to_string(),
             "-t",
-            "3600",
+            &(end - start).to_string(),
             "-c",
             "copy",
             segment_path.to_str().unwrap(),
@@ -182,26 +1072,124 @@ pub async fn split_video_if_needed(video_path: &Path) -> Result<Vec<PathBuf>> {
         #[cfg(target_os = "windows")]
         command.creation_flags(0x08000000); // CREATE_NO_WINDOW
 
-        let status = command.status()?;
+        let status = run_with_timeout(&mut command, timeouts.ffmpeg_secs)?.status;
 
         if !status.success() {
             return Err(anyhow!("ffmpeg split failed for segment {}", segment_index));
         }
 
         segment_paths.push(segment_path);
-        current_pos += 3600.0;
-        segment_index += 1;
     }
 
     Ok(segment_paths)
 }
 
+/// Splits an audio file at its embedded chapter markers without re-encoding
+/// Returns each segment path with its chapter title (or the original file if it has no chapters).
+/// Chapters shorter than `min_duration_seconds` are merged into a neighbouring one.
+/// Chapter files are written to `output_dir`, never next to the source file.
+pub async fn split_audio_at_chapters(
+    audio_path: &Path,
+    output_dir: &Path,
+    min_duration_seconds: f64,
+    timeouts: &ProcessTimeouts,
+) -> Result<Vec<(PathBuf, String)>> {
+    let audio_str = audio_path
+        .to_str()
+        .ok_or_else(|| anyhow!("Invalid audio file path encoding"))?;
+    let stem = audio_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| anyhow!("Invalid audio file name"))?;
+    let extension = audio_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("m4a");
+
+    debug!("Getting chapters for: {}", audio_str);
+    let ffprobe_path = find_executable("ffprobe")?;
+
+    let mut command = Command::new(&ffprobe_path);
+    command.args(["-v", "error", "-show_chapters", "-of", "json", audio_str]);
+
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let output = run_with_timeout(&mut command, timeouts.ffprobe_secs)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("ffprobe failed: {}", stderr));
+    }
+
+    let probe: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| anyhow!("Failed to parse ffprobe output: {}", e))?;
+    let chapters = parse_ffprobe_chapters(&probe).map_err(|e| anyhow!(e))?;
+    debug!("Found {} chapters", chapters.len());
+    let chapters = merge_short_chapters(chapters, min_duration_seconds);
+
+    if chapters.is_empty() {
+        return Ok(vec![(audio_path.to_path_buf(), stem.to_string())]);
+    }
+
+    let ffmpeg_path = find_executable("ffmpeg")?;
+    let mut segments = Vec::new();
+
+    for (chapter_index, chapter) in chapters.into_iter().enumerate() {
+        let ChapterSpan { start, end, title } = chapter;
+
+        let segment_path = output_dir.join(format!(
+            "{}_chapter_{:02}.{}",
+            stem,
+            chapter_index + 1,
+            extension
+        ));
+
+        let mut command = Command::new(&ffmpeg_path);
+        command.args([
+            "-y",
+            "-i",
+            audio_str,
+            "-ss",
+            &start.to_string(),
+            "-to",
+            &end.to_string(),
+            "-vn",
+            "-c:a",
+            "copy",
+            segment_path.to_str().unwrap(),
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+        #[cfg(target_os = "windows")]
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+        let output = run_with_timeout(&mut command, timeouts.ffmpeg_secs)?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!(
+                "ffmpeg split failed for chapter {}: {}",
+                chapter_index + 1,
+                stderr
+            ));
+        }
+
+        segments.push((segment_path, title));
+    }
+
+    Ok(segments)
+}
+
 /// Extracts a frame from a video at the specified timestamp and saves it as an image
 /// Optimized for speed by placing -ss before -i (input seeking)
 pub async fn extract_frame_from_video(
     video_path: &str,
     timestamp: f64,
     output_path: &str,
+    format: &FrameOutputFormat,
+    timeouts: &ProcessTimeouts,
 ) -> Result<()> {
     debug!("Extracting frame from video: {} at timestamp: {}s", video_path, timestamp);
     
@@ -217,18 +1205,16 @@ pub async fn extract_frame_from_video(
         video_path,
         "-vframes",
         "1",
-        "-q:v",
-        "2",
-        "-y",
-        output_path,
     ])
+    .args(frame_output_args(format))
+    .args(["-y", output_path])
     .stdout(Stdio::piped())
     .stderr(Stdio::piped());
 
     #[cfg(target_os = "windows")]
     command.creation_flags(0x08000000); // CREATE_NO_WINDOW
 
-    let status = command.status()?;
+    let status = run_with_timeout(&mut command, timeouts.ffmpeg_secs)?.status;
     
     if !status.success() {
         return Err(anyhow!("Failed to extract frame from video at timestamp {}s", timestamp));
@@ -238,6 +1224,114 @@ pub async fn extract_frame_from_video(
     Ok(())
 }
 
+/// Samples frames at a fixed rate into numbered JPEG files (frame_00001.jpg, ...)
+/// Returns the frame paths in playback order
+pub async fn extract_frames_at_rate(video_path: &str, fps: f64, output_dir: &Path, timeouts: &ProcessTimeouts) -> Result<Vec<PathBuf>> {
+    debug!("Sampling frames at {} fps from: {}", fps, video_path);
+    if fps <= 0.0 {
+        return Err(anyhow!("Frame sampling rate must be greater than 0"));
+    }
+
+    let ffmpeg_path = find_executable("ffmpeg")?;
+    let output_pattern = output_dir.join("frame_%05d.jpg");
+
+    let mut command = Command::new(&ffmpeg_path);
+    command.args([
+        "-i",
+        video_path,
+        "-vf",
+        &format!("fps={}", fps),
+        "-q:v",
+        "5",
+        "-y",
+        output_pattern.to_str().unwrap(),
+    ])
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped());
+
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let output = run_with_timeout(&mut command, timeouts.ffmpeg_secs)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to sample frames: {}", stderr));
+    }
+
+    let mut frames: Vec<PathBuf> = std::fs::read_dir(output_dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("frame_") && n.ends_with(".jpg"))
+        })
+        .collect();
+    frames.sort();
+
+    debug!("Sampled {} frames", frames.len());
+    Ok(frames)
+}
+
+/// ffmpeg options that could read other inputs, replace the filter graph or write extra files
+const BLOCKED_FFMPEG_ARGS: &[&str] = &[
+    "-i",
+    "-vf",
+    "-af",
+    "-filter",
+    "-filter:v",
+    "-filter:a",
+    "-filter_complex",
+    "-filter_complex_script",
+    "-filter_script",
+    "-lavfi",
+    "-attach",
+    "-dump_attachment",
+    "-script_files",
+    "-progress",
+    "-pass",
+    "-passlogfile",
+];
+
+/// Validates user supplied ffmpeg arguments before they are passed to the encoder
+/// Each element must be a single token, so values with whitespace are rejected
+pub fn validate_ffmpeg_extra_args(args: &[String]) -> Result<()> {
+    for arg in args {
+        if arg.is_empty() || arg.chars().any(char::is_whitespace) {
+            return Err(anyhow!(
+                "ffmpeg argument '{}' must be a single non-empty token",
+                arg
+            ));
+        }
+        let option = arg.split(':').next().unwrap_or(arg);
+        if BLOCKED_FFMPEG_ARGS.contains(&arg.as_str()) || BLOCKED_FFMPEG_ARGS.contains(&option) {
+            return Err(anyhow!("ffmpeg argument '{}' is not allowed", arg));
+        }
+        if arg.starts_with("pipe:") || arg == "-" || arg.starts_with("/dev/") {
+            return Err(anyhow!("ffmpeg argument '{}' is not allowed", arg));
+        }
+    }
+    Ok(())
+}
+
+/// ffmpeg output options for the screenshot image format
+fn frame_output_args(format: &FrameOutputFormat) -> Vec<String> {
+    match format {
+        FrameOutputFormat::Png => vec!["-q:v".to_string(), "2".to_string()],
+        FrameOutputFormat::Jpeg { quality } => {
+            // ffmpeg's JPEG scale goes from 1 (best) to 31 (worst)
+            let quality_scaled = (100 - (*quality).min(100) as u32) / 11 + 1;
+            vec!["-q:v".to_string(), quality_scaled.to_string()]
+        }
+        FrameOutputFormat::Webp { quality } => vec![
+            "-c:v".to_string(),
+            "libwebp".to_string(),
+            "-quality".to_string(),
+            (*quality).min(100).to_string(),
+        ],
+    }
+}
+
 /// Extracts multiple frames from a video at specified timestamps efficiently
 /// This is much faster than calling extract_frame_from_video multiple times
 pub async fn extract_multiple_frames_from_video(
@@ -245,6 +1339,8 @@ pub async fn extract_multiple_frames_from_video(
     timestamps: &[f64],
     output_dir: &str,
     base_filename: &str,
+    format: &FrameOutputFormat,
+    timeouts: &ProcessTimeouts,
 ) -> Result<Vec<String>> {
     debug!("Extracting {} frames from video: {}", timestamps.len(), video_path);
     
@@ -267,14 +1363,10 @@ pub async fn extract_multiple_frames_from_video(
         
         // 各出力を追加
         for (i, &_timestamp) in timestamps.iter().enumerate() {
-            let output_path = format!("{}/{}_frame_{:03}.jpg", output_dir, base_filename, i + 1);
-            args.extend_from_slice(&[
-                "-map".to_string(),
-                format!("[out{}]", i),
-                "-q:v".to_string(),
-                "2".to_string(),
-                output_path.clone(),
-            ]);
+            let output_path = format!("{}/{}_frame_{:03}.{}", output_dir, base_filename, i + 1, format.extension());
+            args.extend_from_slice(&["-map".to_string(), format!("[out{}]", i)]);
+            args.extend(frame_output_args(format));
+            args.push(output_path.clone());
             output_paths.push(output_path);
         }
         
@@ -289,15 +1381,15 @@ pub async fn extract_multiple_frames_from_video(
         #[cfg(target_os = "windows")]
         command.creation_flags(0x08000000); // CREATE_NO_WINDOW
 
-        let status = command.status()?;
+        let status = run_with_timeout(&mut command, timeouts.ffmpeg_secs)?.status;
         
         if !status.success() {
             return Err(anyhow!("Failed to extract multiple frames from video"));
         }
     } else if let Some(&timestamp) = timestamps.first() {
         // 単一フレームの場合は既存の最適化された方法を使用
-        let output_path = format!("{}/{}_frame_001.jpg", output_dir, base_filename);
-        extract_frame_from_video(video_path, timestamp, &output_path).await?;
+        let output_path = format!("{}/{}_frame_001.{}", output_dir, base_filename, format.extension());
+        extract_frame_from_video(video_path, timestamp, &output_path, format, timeouts).await?;
         output_paths.push(output_path);
     }
     
@@ -311,6 +1403,8 @@ pub async fn extract_frame_fast(
     video_path: &str,
     timestamp: f64,
     output_path: &str,
+    format: &FrameOutputFormat,
+    timeouts: &ProcessTimeouts,
 ) -> Result<()> {
     debug!("Fast extracting frame from video: {} at timestamp: {}s", video_path, timestamp);
     
@@ -329,8 +1423,9 @@ pub async fn extract_frame_fast(
         video_path,
         "-vframes",
         "1",
-        "-q:v",
-        "2",
+    ])
+    .args(frame_output_args(format))
+    .args([
         "-f",
         "image2",
         "-seek2any",
@@ -344,7 +1439,7 @@ pub async fn extract_frame_fast(
     #[cfg(target_os = "windows")]
     command.creation_flags(0x08000000); // CREATE_NO_WINDOW
 
-    let status = command.status()?;
+    let status = run_with_timeout(&mut command, timeouts.ffmpeg_secs)?.status;
     
     if !status.success() {
         return Err(anyhow!("Failed to fast extract frame from video at timestamp {}s", timestamp));
@@ -362,35 +1457,48 @@ pub async fn encode_video_if_needed<F>(
     output_dir: &Path,
     progress_callback: F,
     hardware_encoding: bool,
+    audio_track_index: Option<u32>,
+    watermark: Option<&WatermarkSettings>,
+    stabilize: bool,
+    two_pass: bool,
+    subtitle_path: Option<&str>,
+    extra_input_args: &[String],
+    extra_output_args: &[String],
+    video_stream_index: u32,
+    timeouts: &ProcessTimeouts,
 ) -> Result<PathBuf>
 where
     F: Fn(String),
 {
     debug!("Checking if video encoding is needed for: {}", video_path);
     
-    // If no conversion is requested, return original path
-    if *target_quality == VideoQuality::NoConversion {
-        return Ok(PathBuf::from(video_path));
-    }
-    
-    // Get current resolution
-    let current_resolution = get_video_resolution(video_path).await?;
-    debug!("Current resolution: {}x{}", current_resolution.width, current_resolution.height);
-    
-    // Determine target resolution
-    let (target_width, target_height) = match target_quality {
-        VideoQuality::Quality1080p => (1920, 1080),
-        VideoQuality::Quality720p => (1280, 720),
-        VideoQuality::Quality480p => (854, 480),
-        VideoQuality::NoConversion => unreachable!(),
+    // Determine the output size, fitted into the target resolution (None when no downscaling is needed)
+    let scale_target = match target_quality.target_resolution() {
+        Some(_) => {
+            // Get current resolution
+            let current_resolution = get_video_resolution(video_path, video_stream_index, timeouts).await?;
+            debug!("Current resolution: {}x{}", current_resolution.width, current_resolution.height);
+
+            // Only ever scale down, a smaller source would be upscaled by the scale filter
+            let scaled_size = target_quality.downscaled_size(current_resolution.width, current_resolution.height);
+            if scaled_size.is_none() {
+                debug!("Video already at or below target quality, no scaling needed");
+            }
+            scaled_size
+        }
+        None => None,
     };
-    
-    // Check if encoding is needed
-    let needs_encoding = current_resolution.height > target_height 
-        || (current_resolution.height == target_height && current_resolution.width > target_width);
-    
-    if !needs_encoding {
-        debug!("Video already at or below target quality, no encoding needed");
+
+    // A watermark, stabilization or subtitles always require re-encoding, even without scaling
+    if scale_target.is_none() && watermark.is_none() && !stabilize && subtitle_path.is_none() {
+        if let Some(index) = audio_track_index {
+            progress_callback(format!("音声トラック {} を選択中...", index));
+            return select_streams(video_path, video_stream_index, Some(index), output_dir, timeouts).await;
+        }
+        if video_stream_index != 0 {
+            progress_callback(format!("映像ストリーム {} を選択中...", video_stream_index));
+            return select_streams(video_path, video_stream_index, None, output_dir, timeouts).await;
+        }
         return Ok(PathBuf::from(video_path));
     }
     
@@ -410,17 +1518,17 @@ where
     let ffmpeg_path = find_executable("ffmpeg")?;
     
     // Get video duration for progress calculation
-    let duration = get_video_duration(video_path).await?;
+    let duration = get_video_duration(video_path, timeouts).await?;
     
     // Choose video encoder based on hardware encoding setting
     let video_encoder = if hardware_encoding {
-        match get_best_hardware_encoder().await {
+        match get_best_hardware_encoder(timeouts).await {
             Some(encoder) => {
                 debug!("Using hardware encoder: {}", encoder);
                 progress_callback(format!("ハードウェアエンコーダーを使用します: {}", encoder));
                 
                 // Test if hardware encoder is actually working
-                if let Err(e) = test_hardware_encoder(&encoder).await {
+                if let Err(e) = test_hardware_encoder(&encoder, timeouts).await {
                     debug!("Hardware encoder test failed: {}, falling back to software encoder", e);
                     progress_callback("ハードウェアエンコーダーのテストに失敗しました。ソフトウェアエンコーダーを使用します...".to_string());
                     "libx264".to_string()
@@ -439,33 +1547,162 @@ where
         "libx264".to_string()
     };
     
+    // Build video filters (stabilization first, subtitles and the watermark must be drawn before scaling)
+    let mut video_filters = Vec::new();
+    let mut stabilization_dir = None;
+    if stabilize {
+        let temp_dir = tempfile::tempdir()?;
+        let transforms_path = temp_dir.path().join("transforms.trf");
+        progress_callback("手ブレ補正の解析中 (1/2)...".to_string());
+        detect_stabilization_transforms(video_path, &transforms_path, timeouts).await?;
+        video_filters.push(format!(
+            "vidstabtransform=input='{}':zoom=1:smoothing=10,unsharp=5:5:0.8:3:3:0.4",
+            escape_filter_path(&transforms_path)
+        ));
+        stabilization_dir = Some(temp_dir);
+    }
+    if let Some(subtitle_path) = subtitle_path {
+        video_filters.push(format!(
+            "subtitles='{}'",
+            escape_filter_path(Path::new(subtitle_path))
+        ));
+    }
+    let mut watermark_text_file = None;
+    if let Some(watermark) = watermark {
+        let text_file = tempfile::NamedTempFile::new()?;
+        std::fs::write(text_file.path(), &watermark.text)?;
+        video_filters.push(build_watermark_filter(watermark, text_file.path())?);
+        watermark_text_file = Some(text_file);
+    }
+    if let Some((target_width, target_height)) = scale_target {
+        video_filters.push(format!("scale={}:{}", target_width, target_height));
+    }
+    let video_filter = video_filters.join(",");
+
     // Build ffmpeg command arguments
-    let scale_filter = format!("scale={}:{}", target_width, target_height);
-    let mut args = vec![
-        "-i", video_path,
-        "-vf", &scale_filter,
-        "-c:v", &video_encoder,
-        "-c:a", "aac",
-    ];
-    
-    // Add quality settings based on encoder type
-    if video_encoder == "libx264" {
-        // Software encoding quality settings
-        args.extend_from_slice(&["-crf", "23"]);
+    // Every path is its own argument, Command quotes spaces itself on Windows
+    let input_arg = ffmpeg_file_arg(Path::new(video_path))?;
+    let output_arg = ffmpeg_file_arg(&output_path)?;
+    let video_map = format!("0:v:{}", video_stream_index);
+    let audio_map = audio_map_for(audio_track_index);
+    let mut args = encode_base_args(
+        &input_arg,
+        &video_map,
+        &audio_map,
+        &video_filter,
+        &video_encoder,
+        extra_input_args,
+        extra_output_args,
+    );
+
+    // Hardware encoders do not support ffmpeg's two-pass mode
+    let use_two_pass = two_pass && video_encoder == "libx264";
+    if two_pass && !use_two_pass {
+        debug!("Two-pass encoding is only supported with libx264, using single pass");
+    }
+
+    if use_two_pass {
+        let bitrate = target_bitrate(target_quality);
+        let passlog = PassLogGuard::new(&output_path);
+        let passlog_path = passlog.prefix.to_string_lossy().to_string();
+        let null_output = if cfg!(target_os = "windows") { "NUL" } else { "/dev/null" };
+
+        // Pass 1: analyze only, no audio and no output file
+        progress_callback("2パスエンコード (1/2) を実行中...".to_string());
+        let mut first_pass_args = args.clone();
+        first_pass_args.extend_from_slice(&[
+            "-b:v", bitrate,
+            "-pass", "1",
+            "-passlogfile", &passlog_path,
+            "-an",
+            "-f", "null",
+            "-progress", "pipe:1",
+            "-y",
+            null_output,
+        ]);
+        run_ffmpeg_with_progress(&ffmpeg_path, &first_pass_args, duration, 0.0, 50.0, &progress_callback)?;
+
+        // Pass 2: encode with the statistics collected in pass 1
+        progress_callback("2パスエンコード (2/2) を実行中...".to_string());
+        let mut second_pass_args = args;
+        second_pass_args.extend_from_slice(&[
+            "-c:a", "aac",
+            "-b:v", bitrate,
+            "-pass", "2",
+            "-passlogfile", &passlog_path,
+            "-progress", "pipe:1",
+            "-y",
+            &output_arg,
+        ]);
+        run_ffmpeg_with_progress(&ffmpeg_path, &second_pass_args, duration, 50.0, 100.0, &progress_callback)?;
     } else {
-        // Hardware encoding quality settings
-        args.extend_from_slice(&["-b:v", "5M"]); // 5 Mbps bitrate for hardware encoding
+        args.extend_from_slice(&["-c:a", "aac"]);
+
+        // Add quality settings based on encoder type
+        if video_encoder == "libx264" {
+            // Software encoding quality settings
+            args.extend_from_slice(&["-crf", "23"]);
+        } else {
+            // Hardware encoding quality settings
+            args.extend_from_slice(&["-b:v", "5M"]); // 5 Mbps bitrate for hardware encoding
+        }
+
+        // Add progress and output settings
+        args.extend_from_slice(&[
+            "-progress", "pipe:1",
+            "-y",
+            &output_arg,
+        ]);
+
+        run_ffmpeg_with_progress(&ffmpeg_path, &args, duration, 0.0, 100.0, &progress_callback)?;
     }
+
+    // The temporary watermark text file and stabilization transforms are no longer needed
+    drop(watermark_text_file);
+    drop(stabilization_dir);
+
+    progress_callback("エンコードが完了しました".to_string());
+    debug!("Video encoding completed: {:?}", output_path);
     
-    // Add progress and output settings
-    args.extend_from_slice(&[
-        "-progress", "pipe:1",
-        "-y",
-        output_path.to_str().unwrap(),
-    ]);
-    
+    Ok(output_path)
+}
+
+/// Arguments shared by every encode pass, up to and including the video encoder
+fn encode_base_args<'a>(
+    input_arg: &'a str,
+    video_map: &'a str,
+    audio_map: &'a str,
+    video_filter: &'a str,
+    video_encoder: &'a str,
+    extra_input_args: &'a [String],
+    extra_output_args: &'a [String],
+) -> Vec<&'a str> {
+    let mut args: Vec<&str> = extra_input_args.iter().map(String::as_str).collect();
+    args.extend_from_slice(&["-i", input_arg]);
+
+    // Only keep the selected video stream and audio track
+    args.extend_from_slice(&["-map", video_map, "-map", audio_map]);
+
+    args.extend_from_slice(&["-vf", video_filter]);
+    args.extend(extra_output_args.iter().map(String::as_str));
+    args.extend_from_slice(&["-c:v", video_encoder]);
+    args
+}
+
+/// Runs an ffmpeg encode and maps its progress onto the given percentage range
+fn run_ffmpeg_with_progress<F>(
+    ffmpeg_path: &Path,
+    args: &[&str],
+    duration: f64,
+    progress_start: f64,
+    progress_end: f64,
+    progress_callback: &F,
+) -> Result<()>
+where
+    F: Fn(String),
+{
     debug!("Executing ffmpeg command: {:?} {:?}", ffmpeg_path, args);
-    let mut command_builder = Command::new(&ffmpeg_path);
+    let mut command_builder = Command::new(ffmpeg_path);
     command_builder.args(args)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
@@ -504,7 +1741,9 @@ where
                     if line.starts_with("out_time_ms=") {
                         if let Ok(time_ms) = line[12..].parse::<f64>() {
                             let current_time = time_ms / 1_000_000.0; // Convert microseconds to seconds
-                            let progress_percent = ((current_time / duration) * 100.0).min(100.0);
+                            let pass_percent = ((current_time / duration) * 100.0).min(100.0);
+                            let progress_percent = progress_start
+                                + (progress_end - progress_start) * pass_percent / 100.0;
                             progress_callback(format!("エンコード中... {:.1}%", progress_percent));
                         }
                     }
@@ -527,11 +1766,184 @@ where
         debug!("ffmpeg stderr: {}", stderr_output);
         return Err(anyhow!("Video encoding failed: {}", stderr_output));
     }
-    
-    progress_callback("エンコードが完了しました".to_string());
-    debug!("Video encoding completed: {:?}", output_path);
-    
-    Ok(output_path)
+
+    Ok(())
+}
+
+/// Removes the ffmpeg two-pass log files when dropped, whether or not encoding succeeded
+struct PassLogGuard {
+    prefix: PathBuf,
+}
+
+impl PassLogGuard {
+    fn new(output_path: &Path) -> Self {
+        let mut prefix = output_path.as_os_str().to_owned();
+        prefix.push("_passlog");
+        Self {
+            prefix: PathBuf::from(prefix),
+        }
+    }
+}
+
+impl Drop for PassLogGuard {
+    fn drop(&mut self) {
+        // libx264 writes <prefix>-0.log and <prefix>-0.log.mbtree
+        for suffix in ["-0.log", "-0.log.mbtree", "-0.log.temp", "-0.log.mbtree.temp"] {
+            let mut path = self.prefix.as_os_str().to_owned();
+            path.push(suffix);
+            let path = PathBuf::from(path);
+            if path.exists() {
+                if let Err(e) = std::fs::remove_file(&path) {
+                    debug!("Failed to remove pass log file {:?}: {}", path, e);
+                }
+            }
+        }
+    }
+}
+
+/// Checks whether the installed ffmpeg supports the vidstab filters
+pub async fn is_vidstab_available(timeouts: &ProcessTimeouts) -> bool {
+    let ffmpeg_path = match find_executable("ffmpeg") {
+        Ok(path) => path,
+        Err(_) => return false,
+    };
+
+    let mut command = Command::new(&ffmpeg_path);
+    command.args(["-hide_banner", "-filters"]);
+
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    match run_with_timeout(&mut command, timeouts.ffprobe_secs) {
+        Ok(output) if output.status.success() => {
+            let filters = String::from_utf8_lossy(&output.stdout);
+            filters.contains("vidstabdetect") && filters.contains("vidstabtransform")
+        }
+        _ => false,
+    }
+}
+
+/// Runs the first vidstab pass that writes the camera motion transforms
+async fn detect_stabilization_transforms(video_path: &str, transforms_path: &Path, timeouts: &ProcessTimeouts) -> Result<()> {
+    debug!("Detecting stabilization transforms for: {}", video_path);
+    let ffmpeg_path = find_executable("ffmpeg")?;
+
+    let detect_filter = format!(
+        "vidstabdetect=stepsize=6:shakiness=8:result='{}'",
+        escape_filter_path(transforms_path)
+    );
+    let mut command = Command::new(&ffmpeg_path);
+    command.args([
+        "-i",
+        video_path,
+        "-vf",
+        &detect_filter,
+        "-f",
+        "null",
+        "-",
+    ]);
+
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let output = run_with_timeout(&mut command, timeouts.ffmpeg_secs)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Stabilization analysis failed: {}", stderr));
+    }
+
+    Ok(())
+}
+
+/// Builds the drawtext filter for the watermark overlay.
+/// The text is read from a file to avoid escaping issues in the filter graph.
+fn build_watermark_filter(watermark: &WatermarkSettings, text_file: &Path) -> Result<String> {
+    let (x, y) = match watermark.position {
+        WatermarkPosition::TopLeft => ("10", "10"),
+        WatermarkPosition::TopRight => ("w-tw-10", "10"),
+        WatermarkPosition::BottomLeft => ("10", "h-th-10"),
+        WatermarkPosition::BottomRight => ("w-tw-10", "h-th-10"),
+        WatermarkPosition::Center => ("(w-tw)/2", "(h-th)/2"),
+    };
+
+    let mut filter = format!(
+        "drawtext=textfile='{}':x={}:y={}:fontsize={}:fontcolor=white@{}",
+        escape_filter_path(text_file),
+        x,
+        y,
+        watermark.font_size,
+        watermark.opacity.clamp(0.0, 1.0)
+    );
+
+    // Non-ASCII text (e.g. Japanese) needs a font that contains the glyphs
+    let font_file = match &watermark.font_path {
+        Some(font_path) => Some(PathBuf::from(font_path)),
+        None if !watermark.text.is_ascii() => {
+            let font = find_unicode_font();
+            if font.is_none() {
+                return Err(anyhow!(
+                    "No Unicode font found for the watermark text. Please specify a font path"
+                ));
+            }
+            font
+        }
+        None => None,
+    };
+    if let Some(font_file) = font_file {
+        filter.push_str(&format!(":fontfile='{}'", escape_filter_path(&font_file)));
+    }
+
+    Ok(filter)
+}
+
+/// Path argument for ffmpeg's `-i` or output file.
+/// Quoting is left to `Command` (adding quotes would make them part of the file name),
+/// instead paths containing `=` get the `file:` protocol prefix on Windows so ffmpeg
+/// never interprets them as anything but a local file.
+fn ffmpeg_file_arg(path: &Path) -> Result<String> {
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| anyhow!("Path is not valid UTF-8: {}", path.display()))?;
+    if cfg!(not(unix)) && path_str.contains('=') {
+        return Ok(format!("file:{}", path_str));
+    }
+    Ok(path_str.to_string())
+}
+
+/// Escapes a path for use inside an ffmpeg filter graph option
+fn escape_filter_path(path: &Path) -> String {
+    path.to_string_lossy()
+        .replace('\\', "/")
+        .replace(':', "\\:")
+        .replace('\'', "\\'")
+}
+
+/// Finds a system font that can render Japanese text
+fn find_unicode_font() -> Option<PathBuf> {
+    let candidates = [
+        "/System/Library/Fonts/ヒラギノ角ゴシック W3.ttc", // macOS
+        "/System/Library/Fonts/Hiragino Sans GB.ttc",      // macOS
+        "C:\\Windows\\Fonts\\meiryo.ttc",                 // Windows
+        "C:\\Windows\\Fonts\\msgothic.ttc",               // Windows
+        "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc", // Linux
+        "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",      // Linux
+    ];
+
+    candidates
+        .iter()
+        .map(PathBuf::from)
+        .find(|path| path.is_file())
+}
+
+/// Target video bitrate used for two-pass encoding
+fn target_bitrate(quality: &VideoQuality) -> &str {
+    match quality {
+        VideoQuality::Quality1080p => "5M",
+        VideoQuality::Quality720p => "2500k",
+        VideoQuality::Quality480p => "1M",
+        VideoQuality::NoConversion => "8M",
+    }
 }
 
 fn target_quality_string(quality: &VideoQuality) -> &str {
@@ -545,7 +1957,7 @@ fn target_quality_string(quality: &VideoQuality) -> &str {
 
 
 /// Tests if a hardware encoder is actually working
-async fn test_hardware_encoder(encoder: &str) -> Result<()> {
+async fn test_hardware_encoder(encoder: &str, timeouts: &ProcessTimeouts) -> Result<()> {
     debug!("Testing hardware encoder: {}", encoder);
     
     let ffmpeg_path = find_executable("ffmpeg")?;
@@ -564,7 +1976,7 @@ async fn test_hardware_encoder(encoder: &str) -> Result<()> {
     #[cfg(target_os = "windows")]
     command.creation_flags(0x08000000); // CREATE_NO_WINDOW
 
-    let output = command.output()?;
+    let output = run_with_timeout(&mut command, timeouts.ffprobe_secs)?;
     
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -577,7 +1989,7 @@ async fn test_hardware_encoder(encoder: &str) -> Result<()> {
 }
 
 /// Gets the best available hardware encoder for the current system
-pub async fn get_best_hardware_encoder() -> Option<String> {
+pub async fn get_best_hardware_encoder(timeouts: &ProcessTimeouts) -> Option<String> {
     let ffmpeg_path = match find_executable("ffmpeg") {
         Ok(path) => path,
         Err(_) => return None,
@@ -590,7 +2002,7 @@ pub async fn get_best_hardware_encoder() -> Option<String> {
     #[cfg(target_os = "windows")]
     command.creation_flags(0x08000000); // CREATE_NO_WINDOW
 
-    let output = match command.output() {
+    let output = match run_with_timeout(&mut command, timeouts.ffprobe_secs) {
         Ok(output) => output,
         Err(_) => return None,
     };
@@ -620,3 +2032,110 @@ pub async fn get_best_hardware_encoder() -> Option<String> {
     
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn silence_split_uses_the_latest_silence_before_the_limit() {
+        let silences = [1200.0, 3400.0, 3550.0, 3700.0, 7000.0];
+        assert_eq!(silence_split_points(&silences, 9000.0), vec![3550.0, 7000.0]);
+    }
+
+    #[test]
+    fn silence_split_ignores_silences_outside_the_window() {
+        // Neither silence is close enough to the limit, so the segment is cut at 1 hour
+        let silences = [1800.0, 5000.0];
+        assert_eq!(silence_split_points(&silences, 5000.0), vec![3600.0]);
+    }
+
+    #[test]
+    fn silence_split_never_creates_segments_longer_than_an_hour() {
+        let silences = [3300.0, 6950.0];
+        let points = silence_split_points(&silences, 11000.0);
+        let mut starts = vec![0.0];
+        starts.extend(points.iter().copied());
+        starts.push(11000.0);
+        assert!(starts
+            .windows(2)
+            .all(|pair| pair[1] - pair[0] <= SEGMENT_DURATION_SECS));
+    }
+
+    #[test]
+    fn paths_with_spaces_stay_single_arguments() {
+        let dir = tempfile::Builder::new()
+            .prefix("Video Folder ")
+            .tempdir()
+            .unwrap();
+        let input = dir.path().join("my video.mkv");
+        let input_arg = ffmpeg_file_arg(&input).unwrap();
+        assert_eq!(input_arg, input.to_str().unwrap());
+
+        let extra_input_args = vec!["-ss".to_string(), "10".to_string()];
+        let args = encode_base_args(
+            &input_arg,
+            "0:v:0",
+            "0:a:0",
+            "scale=1280:720",
+            "libx264",
+            &extra_input_args,
+            &[],
+        );
+        assert_eq!(
+            args,
+            vec![
+                "-ss", "10",
+                "-i", input_arg.as_str(),
+                "-map", "0:v:0",
+                "-map", "0:a:0",
+                "-vf", "scale=1280:720",
+                "-c:v", "libx264",
+            ]
+        );
+    }
+
+    #[test]
+    fn paths_with_equals_signs_get_the_file_protocol_on_windows() {
+        let dir = tempfile::Builder::new()
+            .prefix("Video Folder ")
+            .tempdir()
+            .unwrap();
+        let input = dir.path().join("clip=1.mp4");
+        let expected = if cfg!(unix) {
+            input.to_str().unwrap().to_string()
+        } else {
+            format!("file:{}", input.to_str().unwrap())
+        };
+        assert_eq!(ffmpeg_file_arg(&input).unwrap(), expected);
+    }
+
+    #[test]
+    fn filter_paths_escape_colons_and_quotes() {
+        assert_eq!(
+            escape_filter_path(Path::new("C:\\Users\\User Name\\it's.srt")),
+            "C\\:/Users/User Name/it\\'s.srt"
+        );
+    }
+
+    #[test]
+    fn timestamps_before_the_first_keyframe_snap_to_it() {
+        assert_eq!(nearest_keyframe(0.5, &[2.0, 4.0, 6.0]), 2.0);
+    }
+
+    #[test]
+    fn timestamps_after_the_last_keyframe_snap_to_it() {
+        assert_eq!(nearest_keyframe(9.0, &[2.0, 4.0, 6.0]), 6.0);
+    }
+
+    #[test]
+    fn timestamps_between_keyframes_snap_to_the_closer_one() {
+        assert_eq!(nearest_keyframe(4.9, &[2.0, 4.0, 6.0]), 4.0);
+        assert_eq!(nearest_keyframe(5.1, &[2.0, 4.0, 6.0]), 6.0);
+    }
+
+    #[test]
+    fn timestamps_are_kept_without_keyframes() {
+        assert_eq!(nearest_keyframe(3.5, &[]), 3.5);
+    }
+}
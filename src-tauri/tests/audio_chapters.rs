@@ -0,0 +1,94 @@
+use document_encoder_lib::types::{merge_short_chapters, parse_ffprobe_chapters, ChapterSpan};
+use serde_json::json;
+
+fn ffprobe_chapters(chapters: &[(&str, &str, &str)]) -> serde_json::Value {
+    json!({
+        "chapters": chapters
+            .iter()
+            .enumerate()
+            .map(|(id, (start, end, title))| json!({
+                "id": id,
+                "time_base": "1/1000",
+                "start_time": start,
+                "end_time": end,
+                "tags": { "title": title }
+            }))
+            .collect::<Vec<_>>()
+    })
+}
+
+fn merged(chapters: &[(&str, &str, &str)]) -> Vec<(f64, f64, String)> {
+    let chapters = parse_ffprobe_chapters(&ffprobe_chapters(chapters)).unwrap();
+    merge_short_chapters(chapters, 60.0)
+        .into_iter()
+        .map(|ChapterSpan { start, end, title }| (start, end, title))
+        .collect()
+}
+
+#[test]
+fn short_first_chapter_is_merged_into_the_following_one() {
+    let chapters = merged(&[
+        ("0.000000", "30.000000", "Introduction"),
+        ("30.000000", "600.000000", "Overview"),
+        ("600.000000", "1200.000000", "Details"),
+    ]);
+
+    assert_eq!(
+        chapters,
+        vec![
+            (0.0, 600.0, "Introduction + Overview".to_string()),
+            (600.0, 1200.0, "Details".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn short_middle_chapter_is_merged_into_the_preceding_one() {
+    let chapters = merged(&[
+        ("0.000000", "600.000000", "Setup"),
+        ("600.000000", "645.500000", "Break"),
+        ("645.500000", "1200.000000", "Usage"),
+    ]);
+
+    assert_eq!(
+        chapters,
+        vec![
+            (0.0, 645.5, "Setup + Break".to_string()),
+            (645.5, 1200.0, "Usage".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn short_last_chapter_is_merged_into_the_preceding_one() {
+    let chapters = merged(&[
+        ("0.000000", "600.000000", "Setup"),
+        ("600.000000", "1200.000000", "Usage"),
+        ("1200.000000", "1215.000000", "Outro"),
+    ]);
+
+    assert_eq!(
+        chapters,
+        vec![
+            (0.0, 600.0, "Setup".to_string()),
+            (600.0, 1215.0, "Usage + Outro".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn chapters_without_title_are_numbered() {
+    let probe = json!({
+        "chapters": [
+            { "start_time": "0.000000", "end_time": "120.000000", "tags": {} },
+            { "start_time": "120.000000", "end_time": "240.000000" }
+        ]
+    });
+
+    let titles: Vec<String> = parse_ffprobe_chapters(&probe)
+        .unwrap()
+        .into_iter()
+        .map(|chapter| chapter.title)
+        .collect();
+    assert_eq!(titles, vec!["Chapter 1", "Chapter 2"]);
+}
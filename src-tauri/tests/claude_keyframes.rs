@@ -0,0 +1,36 @@
+use document_encoder_lib::claude::{
+    downscale_keyframe, fit_keyframes_to_budget, keyframe_timestamps,
+};
+
+#[test]
+fn keyframes_are_taken_every_30_seconds_up_to_100_images() {
+    assert_eq!(keyframe_timestamps(75.0), vec![0.0, 30.0, 60.0]);
+
+    let long_video = keyframe_timestamps(3.0 * 3600.0);
+    assert_eq!(long_video.len(), 100);
+    assert_eq!(long_video[1], 108.0);
+}
+
+#[test]
+fn large_keyframes_are_downscaled() {
+    let frame = image::RgbImage::from_pixel(3840, 2160, image::Rgb([40, 80, 120]));
+    let mut png = Vec::new();
+    frame
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+
+    let jpeg = image::load_from_memory(&downscale_keyframe(&png).unwrap()).unwrap();
+
+    assert_eq!((jpeg.width(), jpeg.height()), (1568, 882));
+}
+
+#[test]
+fn keyframes_over_the_budget_are_thinned_out() {
+    let keyframes: Vec<(f64, Vec<u8>)> = (0..8).map(|i| (i as f64 * 30.0, vec![0; 100])).collect();
+
+    let kept = fit_keyframes_to_budget(keyframes.clone(), 300);
+    let timestamps: Vec<f64> = kept.iter().map(|(timestamp, _)| *timestamp).collect();
+    assert_eq!(timestamps, vec![0.0, 120.0]);
+
+    assert_eq!(fit_keyframes_to_budget(keyframes, 800).len(), 8);
+}
@@ -0,0 +1,110 @@
+use std::fs;
+use std::time::Duration;
+
+use document_encoder_lib::file::{
+    clean_intermediate_files, record_intermediate_files, CleanupList,
+};
+
+#[test]
+fn intermediate_files_are_removed_on_drop() {
+    let dir = tempfile::tempdir().unwrap();
+    let segment = dir.path().join("video_part_001.mp4");
+    let encoded = dir.path().join("video_part_001_720p.mp4");
+    let document = dir.path().join("video.md");
+    for path in [&segment, &encoded, &document] {
+        fs::write(path, b"data").unwrap();
+    }
+
+    {
+        let mut intermediate_files = CleanupList::new(dir.path(), true);
+        intermediate_files.add(segment.clone());
+        intermediate_files.add(encoded.clone());
+        // Already deleted files are ignored
+        intermediate_files.add(dir.path().join("missing.mp4"));
+    }
+
+    assert!(!segment.exists());
+    assert!(!encoded.exists());
+    assert!(document.exists());
+    // Nothing is left to clean up later
+    let report = clean_intermediate_files(dir.path(), true, Duration::ZERO).unwrap();
+    assert!(report.files_found.is_empty());
+}
+
+#[test]
+fn kept_intermediate_files_are_cleaned_up_later() {
+    let dir = tempfile::tempdir().unwrap();
+    let segment = dir.path().join("lecture_segment_0.mp4");
+    fs::write(&segment, b"data").unwrap();
+
+    {
+        let mut intermediate_files = CleanupList::new(dir.path(), false);
+        intermediate_files.add(segment.clone());
+    }
+    assert!(segment.exists());
+
+    let report = clean_intermediate_files(dir.path(), false, Duration::ZERO).unwrap();
+    assert_eq!(
+        report.files_deleted,
+        vec![segment.to_string_lossy().to_string()]
+    );
+    assert!(!segment.exists());
+}
+
+#[test]
+fn only_recorded_intermediate_files_are_cleaned_up() {
+    let dir = tempfile::tempdir().unwrap();
+    let job_dir = dir.path().join("2026-01-01_00-00-00_abc123");
+    let images = job_dir.join("images");
+    fs::create_dir_all(&images).unwrap();
+    let recorded = [
+        dir.path().join("lecture_segment_0.mp4"),
+        dir.path().join("lecture_720p.mp4"),
+    ];
+    let recorded_in_job = [images.join("abc123_section_1_grid.jpg")];
+    // The user's own files look like intermediates but were never recorded by a job
+    let kept = [
+        dir.path().join("lecture.mp4"),
+        dir.path().join("lecture.md"),
+        dir.path().join("holiday_720p.mp4"),
+        dir.path().join("holiday_segment_2.mp4"),
+        images.join("image-1-12_5s.png"),
+    ];
+    for path in recorded.iter().chain(&recorded_in_job).chain(&kept) {
+        fs::write(path, b"data").unwrap();
+    }
+    record_intermediate_files(dir.path(), &recorded);
+    record_intermediate_files(&job_dir, &recorded_in_job);
+    let total = recorded.len() + recorded_in_job.len();
+
+    let report = clean_intermediate_files(dir.path(), true, Duration::ZERO).unwrap();
+    assert_eq!(report.files_found.len(), total);
+    assert_eq!(report.total_bytes_found, 4 * total as u64);
+    assert!(report.files_deleted.is_empty());
+    assert!(recorded
+        .iter()
+        .chain(&recorded_in_job)
+        .all(|path| path.exists()));
+
+    let report = clean_intermediate_files(dir.path(), false, Duration::ZERO).unwrap();
+    assert_eq!(report.files_deleted.len(), total);
+    assert_eq!(report.total_bytes_freed, report.total_bytes_found);
+    assert!(recorded
+        .iter()
+        .chain(&recorded_in_job)
+        .all(|path| !path.exists()));
+    assert!(kept.iter().all(|path| path.exists()));
+}
+
+#[test]
+fn recently_modified_intermediate_files_are_skipped() {
+    let dir = tempfile::tempdir().unwrap();
+    let segment = dir.path().join("lecture_segment_1.mp4");
+    fs::write(&segment, b"data").unwrap();
+    record_intermediate_files(dir.path(), std::slice::from_ref(&segment));
+
+    let report = clean_intermediate_files(dir.path(), false, Duration::from_secs(60 * 60)).unwrap();
+
+    assert!(report.files_found.is_empty());
+    assert!(segment.exists());
+}
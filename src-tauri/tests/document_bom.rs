@@ -0,0 +1,49 @@
+use std::fs;
+
+use document_encoder_lib::file::write_document;
+use document_encoder_lib::types::OutputEncoding;
+
+#[test]
+fn utf8_document_starts_with_bom_when_requested() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("manual.md");
+
+    write_document(&path, "# マニュアル\n", &OutputEncoding::Utf8, true).unwrap();
+
+    let bytes = fs::read(&path).unwrap();
+    assert_eq!(&bytes[..3], b"\xEF\xBB\xBF");
+    assert_eq!(&bytes[3..], "# マニュアル\n".as_bytes());
+}
+
+#[test]
+fn utf8_document_has_no_bom_by_default() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("manual.md");
+
+    write_document(&path, "# Manual\n", &OutputEncoding::Utf8, false).unwrap();
+
+    assert_eq!(&fs::read(&path).unwrap()[..3], b"# M");
+}
+
+#[test]
+fn bom_flag_does_not_affect_shift_jis() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("manual.md");
+
+    write_document(&path, "# Manual\n", &OutputEncoding::ShiftJis, true).unwrap();
+
+    assert_eq!(fs::read(&path).unwrap(), b"# Manual\n");
+}
+
+#[test]
+fn characters_missing_from_shift_jis_are_replaced_with_question_marks() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("manual.md");
+
+    write_document(&path, "# 手順 ✅ 🎉\n", &OutputEncoding::ShiftJis, false).unwrap();
+
+    let bytes = fs::read(&path).unwrap();
+    let (expected, _, _) = encoding_rs::SHIFT_JIS.encode("# 手順 ? ?\n");
+    assert_eq!(bytes, expected.as_ref());
+    assert!(!bytes.windows(2).any(|w| w == b"&#"));
+}
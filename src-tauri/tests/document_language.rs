@@ -0,0 +1,29 @@
+use document_encoder_lib::types::DocumentLanguage;
+
+#[test]
+fn legacy_language_strings_are_migrated() {
+    let parse = |json: &str| serde_json::from_str::<DocumentLanguage>(json).unwrap();
+
+    assert_eq!(parse("\"japanese\""), DocumentLanguage::Japanese);
+    assert_eq!(parse("\"English\""), DocumentLanguage::English);
+    assert_eq!(
+        parse("\"chinese_simplified\""),
+        DocumentLanguage::ChineseSimplified
+    );
+    assert_eq!(
+        parse("\"Klingon\""),
+        DocumentLanguage::Custom("Klingon".to_string())
+    );
+}
+
+#[test]
+fn languages_are_saved_as_lowercase_strings() {
+    assert_eq!(
+        serde_json::to_string(&DocumentLanguage::ChineseTraditional).unwrap(),
+        "\"chinese_traditional\""
+    );
+    assert_eq!(
+        serde_json::to_string(&DocumentLanguage::Custom("Klingon".to_string())).unwrap(),
+        "\"Klingon\""
+    );
+}
@@ -0,0 +1,53 @@
+use std::fs;
+
+use document_encoder_lib::file::{render_html_report, validate_html_report_template};
+
+#[test]
+fn template_placeholders_are_filled() {
+    let template = "<html><head><title>{{title}}</title></head>\
+        <body><img src=\"logo.png\">{{content}}<p>{{source_video}} / {{generated_date}}</p></body></html>";
+
+    let html = render_html_report(
+        "# Setup\n\n| a | b |\n|---|---|\n| 1 | 2 |\n",
+        Some(template),
+        "Setup <guide>",
+        "2025-01-01 10:00",
+        "demo.mp4",
+    );
+
+    assert!(html.contains("<title>Setup &lt;guide&gt;</title>"));
+    assert!(html.contains("<h1>Setup</h1>"));
+    assert!(html.contains("<table>"));
+    assert!(html.contains("<p>demo.mp4 / 2025-01-01 10:00</p>"));
+    assert!(html.contains("logo.png"));
+}
+
+#[test]
+fn default_template_is_used_without_a_custom_one() {
+    let html = render_html_report("Hello", None, "Doc", "2025-01-01", "");
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<style>"));
+    assert!(html.contains("<p>Hello</p>"));
+    assert!(!html.contains("{{"));
+}
+
+#[test]
+fn template_must_contain_content_placeholder_and_html() {
+    let dir = tempfile::tempdir().unwrap();
+    let valid = dir.path().join("valid.html");
+    let no_content = dir.path().join("no_content.html");
+    let not_html = dir.path().join("not_html.html");
+    fs::write(&valid, "<HTML><body>{{content}}</body></HTML>").unwrap();
+    fs::write(&no_content, "<html><body>{{title}}</body></html>").unwrap();
+    fs::write(&not_html, "{{content}}").unwrap();
+
+    assert!(validate_html_report_template(valid.to_str().unwrap()).is_ok());
+    assert!(validate_html_report_template(no_content.to_str().unwrap())
+        .unwrap_err()
+        .contains("{{content}}"));
+    assert!(validate_html_report_template(not_html.to_str().unwrap()).is_err());
+    assert!(
+        validate_html_report_template(dir.path().join("missing.html").to_str().unwrap()).is_err()
+    );
+}
@@ -0,0 +1,30 @@
+use document_encoder_lib::types::{AppSettings, LineEnding};
+
+const MIXED: &str = "# Title\r\n\nFirst line\nSecond line\r\n";
+
+#[test]
+fn lf_removes_carriage_returns() {
+    assert_eq!(
+        LineEnding::Lf.apply(MIXED),
+        "# Title\n\nFirst line\nSecond line\n"
+    );
+}
+
+#[test]
+fn crlf_does_not_double_existing_carriage_returns() {
+    let converted = LineEnding::Crlf.apply(MIXED);
+
+    assert_eq!(converted, "# Title\r\n\r\nFirst line\r\nSecond line\r\n");
+    assert_eq!(LineEnding::Crlf.apply(&converted), converted);
+}
+
+#[test]
+fn platform_keeps_the_document_unchanged() {
+    assert_eq!(LineEnding::Platform.apply(MIXED), MIXED);
+}
+
+#[test]
+fn documents_are_saved_with_lf_by_default() {
+    let settings = AppSettings::defaults_with_api_key("test-key".to_string());
+    assert_eq!(settings.line_ending, LineEnding::Lf);
+}
@@ -0,0 +1,811 @@
+use std::io::Write;
+
+use document_encoder_lib::gemini::{
+    count_gemini_tokens, create_gemini_cache, delete_all_uploaded_gemini_files,
+    ensure_gemini_file_active, generate_image_caption, generate_with_gemini_internal,
+    integrate_documents, parse_gemini_error, upload_to_gemini_internal, validate_model_name,
+    GeminiClient, GenerationOptions,
+};
+use document_encoder_lib::types::{
+    AppSettings, DocumentLanguage, GeminiApiVersion, GeminiContent, GeminiFileData, GeminiPart,
+    GeminiRequest, GeminiSafetySetting, ModelFallback, ProcessTimeouts, ProgressVerbosity,
+    BLOCK_ONLY_HIGH, HARM_CATEGORY_DANGEROUS_CONTENT,
+};
+use serde_json::json;
+use wiremock::matchers::{
+    body_partial_json, body_string_contains, header, method, path, query_param,
+};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const API_KEY: &str = "test-api-key";
+const MODEL: &str = "gemini-2.5-pro";
+const FILE_NAME: &str = "files/mock-video";
+const CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+fn gemini_client(server: &MockServer) -> GeminiClient {
+    GeminiClient::new(API_KEY).with_base_url(&server.uri())
+}
+
+/// Stubs the resumable upload session (start + upload/finalize)
+async fn mount_upload(server: &MockServer) {
+    Mock::given(method("POST"))
+        .and(path("/upload/v1beta/files"))
+        .and(query_param("key", API_KEY))
+        .respond_with(ResponseTemplate::new(200).insert_header(
+            "X-Goog-Upload-URL",
+            format!("{}/upload-session/mock-video", server.uri()).as_str(),
+        ))
+        .mount(server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/upload-session/mock-video"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "file": { "name": FILE_NAME }
+        })))
+        .mount(server)
+        .await;
+}
+
+fn file_status(state: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "name": FILE_NAME,
+        "state": state,
+        "uri": format!("https://example.com/v1beta/{}", FILE_NAME),
+    }))
+}
+
+fn generate_response(text: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "candidates": [
+            { "content": { "parts": [ { "text": text } ] } }
+        ]
+    }))
+}
+
+/// Settings for generating with the mocked model, tests override only the fields they exercise
+fn generation_settings() -> AppSettings {
+    AppSettings {
+        gemini_model: MODEL.to_string(),
+        language: DocumentLanguage::English,
+        ..AppSettings::defaults_with_api_key(API_KEY.to_string())
+    }
+}
+
+/// Generates a document from the mock video as configured in `settings`,
+/// returning the result together with the reported model fallbacks
+async fn generate_document(
+    server: &MockServer,
+    settings: &AppSettings,
+    cached_content: Option<&str>,
+) -> (anyhow::Result<String>, Vec<ModelFallback>) {
+    let file_uris = [format!("https://example.com/v1beta/{}", FILE_NAME)];
+    let fallbacks = std::sync::Mutex::new(Vec::new());
+    let result = generate_with_gemini_internal(
+        &GenerationOptions::from_settings(&file_uris, settings),
+        settings,
+        &gemini_client(server),
+        cached_content,
+        |_| {},
+        |fallback| fallbacks.lock().unwrap().push(fallback),
+    )
+    .await;
+    (result, fallbacks.into_inner().unwrap())
+}
+
+fn sample_video() -> tempfile::NamedTempFile {
+    let mut file = tempfile::Builder::new()
+        .suffix(".mp4")
+        .tempfile()
+        .expect("failed to create temp video");
+    file.write_all(b"not really a video")
+        .expect("failed to write temp video");
+    file
+}
+
+#[tokio::test]
+async fn upload_and_generate_with_immediately_active_file() {
+    let server = MockServer::start().await;
+    mount_upload(&server).await;
+
+    Mock::given(method("GET"))
+        .and(path(format!("/v1beta/{}", FILE_NAME)))
+        .respond_with(file_status("ACTIVE"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/v1beta/models/{}:generateContent", MODEL)))
+        .and(body_string_contains(FILE_NAME))
+        .respond_with(generate_response("# Generated document"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let video = sample_video();
+    let uri = upload_to_gemini_internal(
+        video.path().to_str().unwrap(),
+        &gemini_client(&server),
+        CHUNK_SIZE,
+        None,
+        &ProgressVerbosity::Normal,
+        &ProcessTimeouts::default(),
+        |_| {},
+    )
+    .await
+    .expect("upload should succeed");
+    assert_eq!(uri, format!("https://example.com/v1beta/{}", FILE_NAME));
+
+    let (document, _) = generate_document(&server, &generation_settings(), None).await;
+    let document = document.expect("generation should succeed");
+    assert_eq!(document, "# Generated document");
+}
+
+#[tokio::test]
+async fn upload_waits_until_processing_finishes() {
+    let server = MockServer::start().await;
+    mount_upload(&server).await;
+
+    // PROCESSING for the first two polls, then ACTIVE
+    Mock::given(method("GET"))
+        .and(path(format!("/v1beta/{}", FILE_NAME)))
+        .respond_with(file_status("PROCESSING"))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path(format!("/v1beta/{}", FILE_NAME)))
+        .respond_with(file_status("ACTIVE"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let video = sample_video();
+    let uri = upload_to_gemini_internal(
+        video.path().to_str().unwrap(),
+        &gemini_client(&server),
+        CHUNK_SIZE,
+        None,
+        &ProgressVerbosity::Normal,
+        &ProcessTimeouts::default(),
+        |_| {},
+    )
+    .await
+    .expect("upload should succeed after processing");
+    assert_eq!(uri, format!("https://example.com/v1beta/{}", FILE_NAME));
+}
+
+#[tokio::test]
+async fn upload_sends_file_in_chunks_and_finalizes_last() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/upload/v1beta/files"))
+        .respond_with(ResponseTemplate::new(200).insert_header(
+            "X-Goog-Upload-URL",
+            format!("{}/upload-session/mock-video", server.uri()).as_str(),
+        ))
+        .mount(&server)
+        .await;
+
+    // "not really a video" is 18 bytes: chunks at offsets 0 and 8, the last one at 16
+    Mock::given(method("POST"))
+        .and(path("/upload-session/mock-video"))
+        .and(header("X-Goog-Upload-Offset", "16"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "file": { "name": FILE_NAME }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/upload-session/mock-video"))
+        .and(header("X-Goog-Upload-Command", "upload"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path(format!("/v1beta/{}", FILE_NAME)))
+        .respond_with(file_status("ACTIVE"))
+        .mount(&server)
+        .await;
+
+    let video = sample_video();
+    let messages = std::sync::Mutex::new(Vec::new());
+    upload_to_gemini_internal(
+        video.path().to_str().unwrap(),
+        &gemini_client(&server),
+        8,
+        None,
+        &ProgressVerbosity::Verbose,
+        &ProcessTimeouts::default(),
+        |message| messages.lock().unwrap().push(message),
+    )
+    .await
+    .expect("chunked upload should succeed");
+
+    let messages = messages.into_inner().unwrap();
+    assert!(messages.contains(&"アップロード中... 0.0 / 0.0 MB (44%)".to_string()));
+    assert!(messages.contains(&"アップロード中... 0.0 / 0.0 MB (100%)".to_string()));
+    let chunk_messages: Vec<&String> = messages
+        .iter()
+        .filter(|message| message.starts_with("チャンク送信中"))
+        .collect();
+    assert_eq!(
+        chunk_messages,
+        vec![
+            "チャンク送信中: 0〜8 バイト (全 18 バイト)",
+            "チャンク送信中: 8〜16 バイト (全 18 バイト)",
+            "チャンク送信中: 16〜18 バイト (全 18 バイト)",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn upload_progress_mentions_bandwidth_limit() {
+    let server = MockServer::start().await;
+    mount_upload(&server).await;
+
+    Mock::given(method("GET"))
+        .and(path(format!("/v1beta/{}", FILE_NAME)))
+        .respond_with(file_status("ACTIVE"))
+        .mount(&server)
+        .await;
+
+    let video = sample_video();
+    let messages = std::sync::Mutex::new(Vec::new());
+    upload_to_gemini_internal(
+        video.path().to_str().unwrap(),
+        &gemini_client(&server),
+        CHUNK_SIZE,
+        Some(1000),
+        &ProgressVerbosity::Normal,
+        &ProcessTimeouts::default(),
+        |message| messages.lock().unwrap().push(message),
+    )
+    .await
+    .expect("upload should succeed");
+
+    let messages = messages.into_inner().unwrap();
+    assert!(messages
+        .contains(&"アップロード中... 0.0 / 0.0 MB (100%) (帯域制限: 1000 kbps)".to_string()));
+}
+
+#[tokio::test]
+async fn upload_fails_when_server_processing_fails() {
+    let server = MockServer::start().await;
+    mount_upload(&server).await;
+
+    Mock::given(method("GET"))
+        .and(path(format!("/v1beta/{}", FILE_NAME)))
+        .respond_with(file_status("FAILED"))
+        .mount(&server)
+        .await;
+
+    let video = sample_video();
+    let error = upload_to_gemini_internal(
+        video.path().to_str().unwrap(),
+        &gemini_client(&server),
+        CHUNK_SIZE,
+        None,
+        &ProgressVerbosity::Normal,
+        &ProcessTimeouts::default(),
+        |_| {},
+    )
+    .await
+    .expect_err("upload should fail");
+    assert!(error.to_string().contains("File processing failed"));
+}
+
+#[tokio::test]
+async fn integrate_documents_sends_all_document_content() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/v1beta/models/{}:generateContent", MODEL)))
+        .respond_with(generate_response("# Integrated document"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let documents = vec![
+        "First segment about installation".to_string(),
+        "Second segment about configuration".to_string(),
+    ];
+    let messages = std::sync::Mutex::new(Vec::new());
+    let integrated = integrate_documents(
+        &documents,
+        &DocumentLanguage::English,
+        &gemini_client(&server),
+        0.0,
+        None,
+        MODEL,
+        &GeminiApiVersion::V1Beta,
+        &[],
+        0.0,
+        |message| messages.lock().unwrap().push(message),
+    )
+    .await
+    .expect("integration should succeed");
+    assert_eq!(integrated, "# Integrated document");
+    assert_eq!(
+        messages.into_inner().unwrap(),
+        vec![
+            "統合プロンプトを構築中...".to_string(),
+            "統合リクエストをGemini APIに送信中 (合計 2 ドキュメント, 66 文字)...".to_string(),
+            "統合レスポンスを受信中...".to_string(),
+        ]
+    );
+
+    let requests = server.received_requests().await.unwrap();
+    let body = String::from_utf8_lossy(&requests[0].body);
+    for document in &documents {
+        assert!(body.contains(document.as_str()));
+    }
+}
+
+#[tokio::test]
+async fn search_grounding_appends_references() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/v1beta/models/{}:generateContent", MODEL)))
+        .and(body_string_contains("google_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{
+                "content": { "parts": [ { "text": "# Release notes" } ] },
+                "groundingMetadata": {
+                    "groundingChunks": [
+                        { "web": { "uri": "https://example.com/changelog", "title": "Changelog" } },
+                        { "web": { "uri": "https://example.com/changelog", "title": "Changelog" } }
+                    ]
+                }
+            }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let settings = AppSettings {
+        enable_experimental_features: true,
+        enable_search_grounding: true,
+        ..generation_settings()
+    };
+    let (document, _) = generate_document(&server, &settings, None).await;
+    let document = document.expect("generation should succeed");
+    assert_eq!(
+        document,
+        "# Release notes\n\n## References\n\n1. [Changelog](https://example.com/changelog)\n"
+    );
+}
+
+#[tokio::test]
+async fn safety_settings_are_sent_with_the_request() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/v1beta/models/{}:generateContent", MODEL)))
+        .and(body_partial_json(json!({
+            "safetySettings": [
+                { "category": HARM_CATEGORY_DANGEROUS_CONTENT, "threshold": BLOCK_ONLY_HIGH }
+            ]
+        })))
+        .respond_with(generate_response("# Pentest walkthrough"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let safety_settings = vec![GeminiSafetySetting {
+        category: HARM_CATEGORY_DANGEROUS_CONTENT.to_string(),
+        threshold: BLOCK_ONLY_HIGH.to_string(),
+    }];
+    let document = integrate_documents(
+        &["Exploit demo".to_string(), "Remediation".to_string()],
+        &DocumentLanguage::English,
+        &gemini_client(&server),
+        0.0,
+        None,
+        MODEL,
+        &GeminiApiVersion::V1Beta,
+        &safety_settings,
+        0.0,
+        |_| {},
+    )
+    .await
+    .expect("request with safety settings should succeed");
+    assert_eq!(document, "# Pentest walkthrough");
+}
+
+#[tokio::test]
+async fn model_list_is_cached_after_the_first_validation() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1beta/models"))
+        .and(query_param("key", API_KEY))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "models": [
+                { "name": "models/gemini-2.5-pro", "supportedGenerationMethods": ["generateContent"] },
+                { "name": "models/text-embedding-004", "supportedGenerationMethods": ["embedContent"] }
+            ]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let cache_dir = tempfile::tempdir().unwrap();
+    let cache_path = cache_dir.path().join("model_cache.json");
+
+    assert!(
+        validate_model_name(MODEL, &gemini_client(&server), &cache_path)
+            .await
+            .unwrap()
+    );
+    assert!(
+        !validate_model_name("text-embedding-004", &gemini_client(&server), &cache_path)
+            .await
+            .unwrap()
+    );
+    assert!(cache_path.exists());
+}
+
+#[tokio::test]
+async fn delete_all_uploaded_files_follows_pagination() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/v1beta/files"))
+        .and(query_param("pageToken", "page-2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "files": [ { "name": "files/second" } ]
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1beta/files"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "files": [ { "name": "files/first" } ],
+            "nextPageToken": "page-2"
+        })))
+        .mount(&server)
+        .await;
+    for name in ["first", "second"] {
+        Mock::given(method("DELETE"))
+            .and(path(format!("/v1beta/files/{}", name)))
+            .and(query_param("key", API_KEY))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+
+    let deleted = delete_all_uploaded_gemini_files(&gemini_client(&server))
+        .await
+        .expect("deletion should succeed");
+
+    assert_eq!(deleted, vec!["files/first", "files/second"]);
+}
+
+#[tokio::test]
+async fn count_tokens_sends_prompt_and_files() {
+    let server = MockServer::start().await;
+    let file_uri = format!("https://example.com/v1beta/{}", FILE_NAME);
+
+    Mock::given(method("POST"))
+        .and(path(format!("/v1beta/models/{}:countTokens", MODEL)))
+        .and(query_param("key", API_KEY))
+        .and(body_string_contains("Summarise the video"))
+        .and(body_string_contains(file_uri.as_str()))
+        .and(body_string_contains("video/webm"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "totalTokens": 12345 })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let tokens = count_gemini_tokens(
+        &[GeminiFileData {
+            mime_type: "video/webm".to_string(),
+            file_uri: file_uri.clone(),
+        }],
+        "Summarise the video",
+        &gemini_client(&server),
+        MODEL,
+    )
+    .await
+    .expect("token count should succeed");
+
+    assert_eq!(tokens, 12345);
+}
+
+#[tokio::test]
+async fn reused_file_uri_must_be_active() {
+    let server = MockServer::start().await;
+    let active_uri = format!("{}/v1beta/{}", server.uri(), FILE_NAME);
+    let expired_uri = format!("{}/v1beta/files/expired-video", server.uri());
+
+    Mock::given(method("GET"))
+        .and(path(format!("/v1beta/{}", FILE_NAME)))
+        .and(query_param("key", API_KEY))
+        .respond_with(file_status("ACTIVE"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1beta/files/expired-video"))
+        .respond_with(ResponseTemplate::new(404))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    ensure_gemini_file_active(&active_uri, &gemini_client(&server))
+        .await
+        .expect("active file should be accepted");
+
+    let expired = ensure_gemini_file_active(&expired_uri, &gemini_client(&server))
+        .await
+        .expect_err("expired file should be rejected");
+    assert!(expired.to_string().contains("re-upload the video"));
+
+    let invalid =
+        ensure_gemini_file_active("https://example.com/video.mp4", &gemini_client(&server))
+            .await
+            .expect_err("foreign URI should be rejected");
+    assert!(invalid.to_string().contains("Invalid Gemini file URI"));
+}
+
+#[tokio::test]
+async fn image_caption_is_sent_inline_and_sanitized() {
+    let server = MockServer::start().await;
+    let mut image = tempfile::Builder::new()
+        .suffix(".png")
+        .tempfile()
+        .expect("failed to create temp image");
+    image
+        .write_all(b"png bytes")
+        .expect("failed to write temp image");
+
+    Mock::given(method("POST"))
+        .and(path(format!("/v1beta/models/{}:generateContent", MODEL)))
+        .and(body_string_contains("image/png"))
+        .and(body_string_contains("image alt-text"))
+        .respond_with(generate_response(
+            "The `Settings` dialog\nwith the [Save] button.\n",
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let caption = generate_image_caption(
+        image.path().to_str().unwrap(),
+        &gemini_client(&server),
+        MODEL,
+        &GeminiApiVersion::V1Beta,
+        &[],
+    )
+    .await
+    .expect("caption generation should succeed");
+
+    assert_eq!(caption, "The Settings dialog with the Save button.");
+}
+
+#[tokio::test]
+async fn rate_limited_model_falls_back_to_the_next_model() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/v1beta/models/{}:generateContent", MODEL)))
+        .respond_with(ResponseTemplate::new(429).set_body_string("quota exceeded"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.5-flash:generateContent"))
+        .and(body_partial_json(
+            json!({ "generation_config": { "temperature": 0.4 } }),
+        ))
+        .respond_with(generate_response("# Fallback document"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let settings = AppSettings {
+        temperature: 0.4,
+        model_fallback_chain: vec!["gemini-2.5-flash".to_string()],
+        ..generation_settings()
+    };
+    let (document, fallbacks) = generate_document(&server, &settings, None).await;
+    let document = document.expect("fallback model should succeed");
+
+    assert_eq!(document, "# Fallback document");
+    assert_eq!(fallbacks.len(), 1);
+    assert_eq!(fallbacks[0].original_model, MODEL);
+    assert_eq!(fallbacks[0].fallback_model, "gemini-2.5-flash");
+    assert!(fallbacks[0].reason.starts_with("429"));
+}
+
+#[tokio::test]
+async fn fallback_model_gets_the_file_parts_instead_of_the_cache() {
+    let server = MockServer::start().await;
+    let file_uri = format!("https://example.com/v1beta/{}", FILE_NAME);
+
+    Mock::given(method("POST"))
+        .and(path(format!("/v1beta/models/{}:generateContent", MODEL)))
+        .and(body_string_contains("cachedContents/mock-cache"))
+        .respond_with(ResponseTemplate::new(429).set_body_string("quota exceeded"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.5-flash:generateContent"))
+        .and(body_string_contains(file_uri.as_str()))
+        .respond_with(generate_response("# Fallback document"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let settings = AppSettings {
+        model_fallback_chain: vec!["gemini-2.5-flash".to_string()],
+        ..generation_settings()
+    };
+    let (document, _) =
+        generate_document(&server, &settings, Some("cachedContents/mock-cache")).await;
+    let document = document.expect("fallback model should succeed");
+
+    assert_eq!(document, "# Fallback document");
+}
+
+#[tokio::test]
+async fn exhausted_fallback_chain_returns_the_last_error() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503).set_body_string("model overloaded"))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let settings = AppSettings {
+        model_fallback_chain: vec!["gemini-2.5-flash".to_string()],
+        ..generation_settings()
+    };
+    let (result, _) = generate_document(&server, &settings, None).await;
+    let error = result.expect_err("every model is unavailable");
+
+    let message = error.to_string();
+    assert!(message.contains("All models in fallback chain failed."));
+    assert!(message.contains("model overloaded"));
+}
+
+#[tokio::test]
+async fn integration_prompt_mentions_segment_overlap() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/v1beta/models/{}:generateContent", MODEL)))
+        .and(body_string_contains(
+            "consecutive documents overlap by approximately 60 seconds",
+        ))
+        .respond_with(generate_response("# Lecture"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let document = integrate_documents(
+        &["First hour".to_string(), "Second hour".to_string()],
+        &DocumentLanguage::English,
+        &gemini_client(&server),
+        0.0,
+        None,
+        MODEL,
+        &GeminiApiVersion::V1Beta,
+        &[],
+        60.0,
+        |_| {},
+    )
+    .await
+    .expect("integration should succeed");
+    assert_eq!(document, "# Lecture");
+}
+
+#[test]
+fn known_gemini_errors_get_user_facing_messages() {
+    let rate_limited = parse_gemini_error(
+        r#"{"error": {"code": 429, "message": "Resource has been exhausted (e.g. check quota).", "status": "RESOURCE_EXHAUSTED"}}"#,
+    );
+    assert_eq!(rate_limited.code, 429);
+    assert_eq!(rate_limited.status, "RESOURCE_EXHAUSTED");
+    assert_eq!(
+        rate_limited.to_string(),
+        "Rate limit reached. Please wait and try again."
+    );
+
+    let missing_model = parse_gemini_error(
+        r#"{"error": {"code": 404, "message": "models/gemini-9 is not found for API version v1beta", "status": "NOT_FOUND"}}"#,
+    );
+    assert_eq!(
+        missing_model.user_facing_message,
+        "Model not found. Please check model name in settings."
+    );
+
+    let blocked = parse_gemini_error(
+        r#"{"error": {"code": 400, "message": "Request blocked due to SAFETY", "status": "INVALID_ARGUMENT"}}"#,
+    );
+    assert_eq!(
+        blocked.user_facing_message,
+        "Content was blocked by safety filters. Try adjusting safety settings."
+    );
+
+    let other = parse_gemini_error(
+        r#"{"error": {"code": 400, "message": "Invalid JSON payload", "status": "INVALID_ARGUMENT"}}"#,
+    );
+    assert_eq!(other.user_facing_message, "Invalid JSON payload");
+
+    let plain = parse_gemini_error("upstream connect error\n");
+    assert_eq!(plain.code, 0);
+    assert_eq!(plain.user_facing_message, "upstream connect error");
+}
+
+#[tokio::test]
+async fn cache_is_created_for_the_uploaded_files() {
+    let server = MockServer::start().await;
+    let file_uri = format!("https://example.com/v1beta/{}", FILE_NAME);
+
+    Mock::given(method("POST"))
+        .and(path("/v1beta/cachedContents"))
+        .and(body_string_contains(file_uri.as_str()))
+        .and(body_string_contains(r#""mime_type":"video/webm""#))
+        .and(body_string_contains(r#""ttl":"600s""#))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "cachedContents/mock-cache",
+            "model": format!("models/{}", MODEL)
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let files = [GeminiFileData {
+        mime_type: "video/webm".to_string(),
+        file_uri,
+    }];
+    let cache_name = create_gemini_cache(&files, &gemini_client(&server), MODEL, 600)
+        .await
+        .expect("cache creation should succeed");
+    assert_eq!(cache_name, "cachedContents/mock-cache");
+}
+
+#[test]
+fn cached_content_replaces_file_parts() {
+    let request = GeminiRequest {
+        contents: vec![GeminiContent {
+            parts: vec![
+                GeminiPart::FileData {
+                    file_data: GeminiFileData {
+                        mime_type: "video/mp4".to_string(),
+                        file_uri: format!("https://example.com/v1beta/{}", FILE_NAME),
+                    },
+                },
+                GeminiPart::Text {
+                    text: "Summarize the video".to_string(),
+                },
+            ],
+        }],
+        generation_config: None,
+        tools: None,
+        safety_settings: None,
+        cached_content: None,
+    }
+    .with_cached_content("cachedContents/mock-cache".to_string());
+
+    assert_eq!(
+        serde_json::to_value(&request).unwrap(),
+        json!({
+            "contents": [{ "parts": [{ "text": "Summarize the video" }] }],
+            "cachedContent": "cachedContents/mock-cache"
+        })
+    );
+}
@@ -0,0 +1,61 @@
+use document_encoder_lib::gemini::{calculate_relative_timestamp, parse_timestamp};
+
+#[test]
+fn supported_formats_are_parsed() {
+    assert_eq!(parse_timestamp("01:30"), Ok(90.0));
+    assert_eq!(parse_timestamp("00:14.5"), Ok(14.5));
+    assert_eq!(parse_timestamp("123.45"), Ok(123.45));
+}
+
+#[test]
+fn malformed_timestamps_are_rejected() {
+    assert_eq!(
+        parse_timestamp("1:02:03"),
+        Err("Could not parse timestamp: 1:02:03".to_string())
+    );
+    assert!(parse_timestamp("abc").is_err());
+    assert!(parse_timestamp("").is_err());
+}
+
+#[test]
+fn relative_timestamps_subtract_preceding_videos() {
+    let durations = [1800.0, 1800.0, 1800.0];
+
+    assert_eq!(
+        calculate_relative_timestamp(600.0, 0, &durations, 0.0),
+        600.0
+    );
+    assert_eq!(
+        calculate_relative_timestamp(2730.0, 1, &durations, 0.0),
+        930.0
+    );
+    // Exactly on a boundary: end of the first video, start of the second
+    assert_eq!(
+        calculate_relative_timestamp(1800.0, 0, &durations, 0.0),
+        1800.0
+    );
+    assert_eq!(
+        calculate_relative_timestamp(1800.0, 1, &durations, 0.0),
+        0.0
+    );
+    // Inside the last video
+    assert_eq!(
+        calculate_relative_timestamp(5000.0, 2, &durations, 0.0),
+        1400.0
+    );
+}
+
+#[test]
+fn relative_timestamps_account_for_segment_overlap() {
+    // Segments after the first start 30 seconds before the end of the previous one
+    let durations = [3600.0, 3630.0, 3630.0];
+
+    assert_eq!(
+        calculate_relative_timestamp(3700.0, 1, &durations, 30.0),
+        130.0
+    );
+    assert_eq!(
+        calculate_relative_timestamp(7300.0, 2, &durations, 30.0),
+        130.0
+    );
+}
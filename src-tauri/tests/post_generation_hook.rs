@@ -0,0 +1,22 @@
+#![cfg(unix)]
+
+use document_encoder_lib::file::post_generation_hook_command;
+
+#[tokio::test]
+async fn document_path_is_not_interpreted_by_the_shell() {
+    let dir = tempfile::tempdir().unwrap();
+    let marker = dir.path().join("injected");
+    let document_path = format!(
+        "{}/My Manual $(touch {}); echo oops.md",
+        dir.path().display(),
+        marker.display()
+    );
+
+    let (mut command, command_line) = post_generation_hook_command("printf %s {}", &document_path);
+    let output = command.output().await.unwrap();
+
+    assert_eq!(command_line, r#"printf %s "$1""#);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), document_path);
+    assert!(!marker.exists());
+}
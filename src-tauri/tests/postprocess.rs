@@ -0,0 +1,111 @@
+use document_encoder_lib::postprocess::{
+    cap_heading_depth, lint_markdown, normalise_code_blocks, normalize_list_numbering,
+    strip_ai_preamble, truncate_at_section_boundary,
+};
+use document_encoder_lib::types::NumberingStyle;
+
+#[test]
+fn missing_blank_lines_and_trailing_spaces_are_fixed() {
+    let content = "# Title\nIntro text   \n## Steps\n```bash\nls   \n```\nDone.\n";
+
+    let (fixed, issues) = lint_markdown(content);
+
+    assert_eq!(
+        fixed,
+        "# Title\n\nIntro text\n\n## Steps\n\n```bash\nls   \n```\n\nDone.\n"
+    );
+    let rules: Vec<&str> = issues.iter().map(|issue| issue.rule.as_str()).collect();
+    assert_eq!(rules, vec!["MD022", "MD009", "MD022", "MD022", "MD031"]);
+    assert!(issues.iter().all(|issue| issue.auto_fixed));
+}
+
+#[test]
+fn missing_top_level_heading_is_reported_but_not_fixed() {
+    let content = "Overview  \nwith a line break\n";
+
+    let (fixed, issues) = lint_markdown(content);
+
+    assert_eq!(fixed, content);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].rule, "MD041");
+    assert_eq!(issues[0].line, 1);
+    assert!(!issues[0].auto_fixed);
+}
+
+#[test]
+fn long_document_is_truncated_at_last_section_before_limit() {
+    let content = "# Title\n\n## First\n\nShort.\n\n## Second\n\nA much longer section body.\n";
+
+    let truncated = truncate_at_section_boundary(content, 40);
+
+    assert_eq!(
+        truncated,
+        "# Title\n\n## First\n\nShort.\n\n> *Document truncated at 40 characters*\n"
+    );
+    assert_eq!(truncate_at_section_boundary(content, 1000), content);
+}
+
+#[test]
+fn ordered_list_markers_follow_the_numbering_style() {
+    let content =
+        "# Steps\n\n1. Open\n2. Save\n   a. Name\n   b. Folder\n3. Close\n\nDone.\n\n1) Again\n";
+
+    let normalized = normalize_list_numbering(content, &NumberingStyle::RomanUpper);
+
+    assert_eq!(
+        normalized,
+        "# Steps\n\nI. Open\nII. Save\n   I. Name\n   II. Folder\nIII. Close\n\nDone.\n\nI) Again\n"
+    );
+    assert_eq!(
+        normalize_list_numbering(content, &NumberingStyle::Automatic),
+        content
+    );
+}
+
+#[test]
+fn deep_headings_are_capped_at_the_maximum_depth() {
+    let content = "# Title\n\n##### Detail\n\n###### Note ######\n\n```md\n##### Kept\n```\n";
+
+    assert_eq!(
+        cap_heading_depth(content, 4),
+        "# Title\n\n#### Detail\n\n#### Note ######\n\n```md\n##### Kept\n```\n"
+    );
+    assert_eq!(
+        cap_heading_depth(content, 0),
+        "Title\n\nDetail\n\nNote\n\n```md\n##### Kept\n```\n"
+    );
+    assert_eq!(cap_heading_depth(content, 6), content);
+}
+
+#[test]
+fn conversational_preamble_and_closing_are_removed() {
+    let content = "Certainly! Here is the document you requested:\n\n# Setup Guide\n\nInstall the app.\n\nLet me know if you need any changes.\n";
+    assert_eq!(
+        strip_ai_preamble(content),
+        "# Setup Guide\n\nInstall the app.\n"
+    );
+
+    let content = "Of course, here is a comprehensive analysis of the video:\n# Report\n\nSure: this line stays.\n";
+    assert_eq!(
+        strip_ai_preamble(content),
+        "# Report\n\nSure: this line stays.\n"
+    );
+
+    let content = "# Report\n\nHere is what changed in version 2.\n";
+    assert_eq!(strip_ai_preamble(content), content);
+}
+
+#[test]
+fn code_block_languages_are_lowercased_and_filtered() {
+    let content = "```Python\nprint(1)\n```\n\n```js\nlet a = 1;\n```\n\n~~~ Haskell\nmain = pure ()\n~~~\n\n```sh\n```py\n```\n";
+    let preferred = vec![
+        "python".to_string(),
+        "javascript".to_string(),
+        "bash".to_string(),
+    ];
+
+    assert_eq!(
+        normalise_code_blocks(content, &preferred),
+        "```python\nprint(1)\n```\n\n```javascript\nlet a = 1;\n```\n\n~~~\nmain = pure ()\n~~~\n\n```bash\n```py\n```\n"
+    );
+}
@@ -0,0 +1,33 @@
+use document_encoder_lib::gemini::{effective_prompt, format_preset_prompt};
+use document_encoder_lib::types::{AppSettings, DocumentFormatPreset, DocumentLanguage};
+
+#[test]
+fn default_settings_keep_only_the_api_key() {
+    let settings = AppSettings::defaults_with_api_key("test-key".to_string());
+
+    assert_eq!(settings.gemini_api_key, "test-key");
+    assert_eq!(settings.language, DocumentLanguage::Japanese);
+    assert_eq!(settings.temperature, 0.0);
+    assert_eq!(settings.upload_chunk_size_mb, 8);
+    assert!(settings.custom_prompt.is_none());
+    assert!(settings.recent_prompts.is_empty());
+}
+
+#[test]
+fn custom_prompt_is_used_with_the_default_format_preset() {
+    let mut settings = AppSettings::defaults_with_api_key("test-key".to_string());
+    assert_eq!(settings.format_preset, DocumentFormatPreset::UserManual);
+    assert_eq!(
+        effective_prompt(&settings),
+        format_preset_prompt(&DocumentFormatPreset::UserManual)
+    );
+
+    settings.custom_prompt = Some("Summarise the meeting".to_string());
+    assert_eq!(effective_prompt(&settings), Some("Summarise the meeting"));
+
+    settings.custom_prompt = Some("   ".to_string());
+    assert_eq!(
+        effective_prompt(&settings),
+        format_preset_prompt(&DocumentFormatPreset::UserManual)
+    );
+}
@@ -0,0 +1,30 @@
+use document_encoder_lib::types::{
+    detect_settings_migrations, AppSettings, FrameExtractionMethod, ImageEmbedFrequency,
+    VideoQuality,
+};
+use serde_json::json;
+
+#[test]
+fn legacy_enum_values_are_migrated() {
+    let mut raw = serde_json::to_value(AppSettings::defaults_with_api_key("key".to_string()))
+        .expect("settings should serialise");
+    raw["video_quality"] = json!("no_conversion");
+    raw["image_embed_frequency"] = json!(null);
+    raw["frame_extraction_method"] = json!("fast");
+
+    let migrations = detect_settings_migrations(&raw);
+    let settings: AppSettings = serde_json::from_value(raw).expect("legacy values should load");
+
+    assert_eq!(settings.video_quality, VideoQuality::NoConversion);
+    assert_eq!(
+        settings.image_embed_frequency,
+        ImageEmbedFrequency::Moderate
+    );
+    assert_eq!(
+        settings.frame_extraction_method,
+        FrameExtractionMethod::Fast
+    );
+    let fields: Vec<&str> = migrations.iter().map(|m| m.field.as_str()).collect();
+    assert_eq!(fields, vec!["video_quality", "image_embed_frequency"]);
+    assert!(serde_json::from_value::<VideoQuality>(json!("4k")).is_err());
+}
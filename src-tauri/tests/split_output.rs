@@ -0,0 +1,34 @@
+use document_encoder_lib::file::split_document_files;
+
+#[test]
+fn document_without_level_one_headings_is_saved_as_single_file() {
+    let content = "## Overview\n\nSome text\n\n## Details\n\nMore text\n";
+
+    let files = split_document_files(content, "manual");
+
+    assert!(files.sections.is_empty());
+    assert_eq!(files.index, ("manual.md".to_string(), content.to_string()));
+}
+
+#[test]
+fn each_level_one_section_is_saved_with_an_index() {
+    let content = "Intro text\n\n# Getting Started\n\nInstall it.\n\n```bash\n# not a heading\n```\n\n# API Reference\n\nCall it.\n";
+
+    let files = split_document_files(content, "manual");
+
+    assert_eq!(files.sections.len(), 2);
+    let (first_name, first) = &files.sections[0];
+    assert_eq!(first_name, "manual_section_01_getting-started.md");
+    assert!(first.starts_with("# Getting Started"));
+    assert!(first.contains("# not a heading"));
+
+    let (second_name, second) = &files.sections[1];
+    assert_eq!(second_name, "manual_section_02_api-reference.md");
+    assert!(second.starts_with("# API Reference"));
+
+    let (index_name, index) = &files.index;
+    assert_eq!(index_name, "manual_index.md");
+    assert!(index.starts_with("Intro text"));
+    assert!(index.contains("[Getting Started](./manual_section_01_getting-started.md)"));
+    assert!(index.contains("[API Reference](./manual_section_02_api-reference.md)"));
+}
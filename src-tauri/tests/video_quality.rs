@@ -0,0 +1,36 @@
+use document_encoder_lib::types::VideoQuality;
+
+#[test]
+fn only_larger_videos_are_scaled_down() {
+    // (width, height, output size) against a 1280x720 target
+    let cases = [
+        (640, 360, None),
+        (1280, 360, None),
+        (1920, 360, None),
+        (640, 720, None),
+        (1280, 720, None),
+        (1920, 720, Some((1280, 480))),
+        (640, 800, Some((576, 720))),
+        (1280, 800, Some((1152, 720))),
+        (1920, 1080, Some((1280, 720))),
+        (1080, 1920, Some((406, 720))),
+    ];
+    for (width, height, expected) in cases {
+        assert_eq!(
+            VideoQuality::Quality720p.downscaled_size(width, height),
+            expected,
+            "{}x{}",
+            width,
+            height
+        );
+        assert_eq!(
+            VideoQuality::Quality720p.needs_downscale(width, height),
+            expected.is_some(),
+            "{}x{}",
+            width,
+            height
+        );
+    }
+    assert!(!VideoQuality::NoConversion.needs_downscale(3840, 2160));
+    assert_eq!(VideoQuality::NoConversion.downscaled_size(3840, 2160), None);
+}
@@ -1,15 +1,16 @@
-import { useState, useEffect } from "react";
+import { useState, useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import "./App.css";
-import { VideoFile, AppSettings, PromptPreset, ProgressUpdate } from './types';
-import { generateFilename, getDirectoryFromPath } from './utils/fileUtils';
+import { VideoFile, AppSettings, PromptPreset, DuplicatePresetWarning, ProgressUpdate, VideoValidationResult } from './types';
+import { generateFilename, getDirectoryFromPath, titleToFilename } from './utils/fileUtils';
 import { useLogger } from './hooks/useLogger';
 import Settings from './components/Settings';
 import PromptSettings from './components/PromptSettings';
 import PresetEditModal from './components/PresetEditModal';
 import MainDashboard from './components/MainDashboard';
 import LicenseDisplay from './components/LicenseDisplay';
+import TelemetryConsentModal from './components/TelemetryConsentModal';
 
 type Page = 'main' | 'settings' | 'promptSettings' | 'licenses';
 
@@ -28,11 +29,15 @@ function App() {
   const [progressMessage, setProgressMessage] = useState("");
   const [progressStep, setProgressStep] = useState(0);
   const [totalSteps, setTotalSteps] = useState(0);
+  const [elapsedSeconds, setElapsedSeconds] = useState(0);
+  const [remainingSeconds, setRemainingSeconds] = useState<number | undefined>(undefined);
   const [showLogs, setShowLogs] = useState(false);
   const [saveDirectory, setSaveDirectory] = useState<string>("");
   const [currentPrompt, setCurrentPrompt] = useState<string>("");
   const [promptPresets, setPromptPresets] = useState<PromptPreset[]>([]);
   const [selectedPresetId, setSelectedPresetId] = useState<string>("");
+  const suggestedTitleRef = useRef<string | null>(null);
+  const jobOutputDirRef = useRef<string | null>(null);
   const [editingPreset, setEditingPreset] = useState<PromptPreset | null>(null);
   const [showEditModal, setShowEditModal] = useState(false);
   const [newPresetName, setNewPresetName] = useState("");
@@ -40,6 +45,7 @@ function App() {
   const [isDeleting, setIsDeleting] = useState(false);
   const [showDeleteConfirm, setShowDeleteConfirm] = useState(false);
   const [deleteTargetId, setDeleteTargetId] = useState<string | null>(null);
+  const [showTelemetryConsent, setShowTelemetryConsent] = useState(false);
 
   const { logs, addLog, clearLogs } = useLogger();
 
@@ -62,24 +68,55 @@ function App() {
     
     addLog("[INFO] Setting up progress update listener...");
     const unsubscribe = listen<ProgressUpdate>("progress_update", (event) => {
-      const { message, step, total_steps } = event.payload;
+      const { message, step, total_steps, elapsed_seconds, estimated_remaining_seconds } = event.payload;
       addLog(`[PROGRESS] Received progress update: ${step}/${total_steps} - ${message}`);
       setProgressMessage(message);
       setProgressStep(step);
       setTotalSteps(total_steps);
+      setElapsedSeconds(elapsed_seconds);
+      setRemainingSeconds(estimated_remaining_seconds ?? undefined);
+    });
+
+    const unsubscribeTitle = listen<string>("document_title_suggestion", (event) => {
+      suggestedTitleRef.current = event.payload;
+      addLog(`[INFO] AIによるタイトル案: ${event.payload}`);
+    });
+
+    const unsubscribeJobDir = listen<string>("job_output_dir", (event) => {
+      jobOutputDirRef.current = event.payload;
+      addLog(`[INFO] ジョブの出力先: ${event.payload}`);
     });
 
     return () => {
       unsubscribe.then(f => f());
+      unsubscribeTitle.then(f => f());
+      unsubscribeJobDir.then(f => f());
     };
   }, []);
 
   const handleFileSelect = async () => {
     addLog("[INFO] Starting file selection...");
     try {
-      const files = await invoke<VideoFile[]>("select_video_files");
+      const pickedFiles = await invoke<VideoFile[]>("select_video_files");
+
+      // 破損・不完全なファイルを除外する
+      const validationResults = await invoke<VideoValidationResult[]>("validate_video_files", { files: pickedFiles });
+      validationResults.forEach(result => {
+        result.warnings.forEach(warning => addLog(`[WARNING] ${result.file_name}: ${warning}`));
+        if (!result.valid) {
+          addLog(`[ERROR] ${result.file_name} は読み込めないため除外しました: ${result.error}`);
+        }
+      });
+      const files = pickedFiles.filter((_, i) => validationResults[i]?.valid ?? true);
       addLog(`[SUCCESS] Selected ${files.length} files: ${files.map(f => f.name).join(", ")}`);
       setSelectedFiles(files);
+
+      // 同名の字幕ファイルがあれば焼き込み対象に設定（単一動画のみ対応）
+      const subtitlePath = files.length === 1 ? files[0].subtitle_path : undefined;
+      setSettings(prev => ({ ...prev, subtitle_burn_in: subtitlePath }));
+      if (subtitlePath) {
+        addLog(`[INFO] 字幕ファイルを検出しました: ${subtitlePath}`);
+      }
       
       // 動画ファイルが選択された場合、最初のファイルのディレクトリを保存先として設定
       if (files.length > 0 && files[0].path) {
@@ -152,25 +189,49 @@ function App() {
     setProgressStep(0);
     setTotalSteps(0);
     setShowLogs(true);
+    suggestedTitleRef.current = null;
+    jobOutputDirRef.current = null;
     
+    // 組み込みプリセットが選択されている場合はそのプロンプトをバックエンド側で使用する
+    const selectedPreset = promptPresets.find(p => p.id === selectedPresetId);
+    const formatPreset = selectedPreset?.format_preset ?? "custom";
+
     try {
       const result = await invoke<string>("generate_document", {
         files: selectedFiles,
         settings: {
           ...settings,
+          format_preset: formatPreset,
           custom_prompt: currentPrompt || undefined
         },
         saveDirectory: currentSaveDirectory
       });
       addLog("[SUCCESS] Document generation completed successfully");
-      setGeneratedDocument(result);
       setProgressMessage("処理が完了しました！");
 
+      // バックエンドで更新されたプロンプト履歴を反映し、設定保存時に上書きしないようにする
+      invoke<string[]>("get_recent_prompts")
+        .then(recentPrompts => setSettings(prev => ({ ...prev, recent_prompts: recentPrompts })))
+        .catch(error => console.error("Error loading recent prompts:", error));
+
+      // In split mode the backend has already saved the sections and returns the index path
+      if (settings.split_output_per_section) {
+        addLog(`[SUCCESS] セクションごとに保存しました: ${result}`);
+        return;
+      }
+      setGeneratedDocument(result);
+
+      // AIタイトルを使用する設定の場合は提案されたタイトルをファイル名にする
+      const saveFilename = settings.use_ai_title && suggestedTitleRef.current
+        ? titleToFilename(suggestedTitleRef.current)
+        : filename;
+
       try {
         const savedPath = await invoke<string>("save_document_to_file", {
           content: result,
-          savePath: currentSaveDirectory,
-          filename: filename
+          savePath: jobOutputDirRef.current ?? currentSaveDirectory,
+          filename: saveFilename,
+          settings
         });
         addLog(`[SUCCESS] ドキュメントを自動保存しました: ${savedPath}`);
       } catch (saveError) {
@@ -208,6 +269,10 @@ function App() {
         setSettings(savedSettings);
         addLog(`[SUCCESS] Settings loaded successfully`);
       }
+      // Telemetry is opt-in, ask once on first launch
+      if (!savedSettings?.telemetry_prompt_shown) {
+        setShowTelemetryConsent(true);
+      }
     } catch (error) {
       addLog(`[ERROR] Error loading settings: ${error}`);
       console.error("Error loading settings:", error);
@@ -321,8 +386,11 @@ function App() {
         updatedPresets = [...promptPresets, newPreset];
       }
 
-      await invoke("save_prompt_presets", { presets: updatedPresets });
-      setPromptPresets(updatedPresets);
+      const dropped = await invoke<DuplicatePresetWarning[]>("save_prompt_presets", { presets: updatedPresets });
+      for (const warning of dropped) {
+        addLog(`[WARNING] 「${warning.existing_name}」と同じプロンプトのため「${warning.dropped_name}」は保存されませんでした`);
+      }
+      setPromptPresets(updatedPresets.filter(p => !dropped.some(d => d.dropped_id === p.id)));
       setShowEditModal(false);
       setEditingPreset(null);
       setNewPresetName("");
@@ -367,6 +435,15 @@ function App() {
     }
   };
 
+  const handleTelemetryConsent = async (enabled: boolean) => {
+    setShowTelemetryConsent(false);
+    await handleUpdateSettingsWithSave({
+      ...settings,
+      enable_telemetry: enabled,
+      telemetry_prompt_shown: true,
+    });
+  };
+
   const renderPage = () => {
     switch (currentPage) {
       case 'settings':
@@ -439,6 +516,8 @@ function App() {
             progressMessage={progressMessage}
             progressStep={progressStep}
             totalSteps={totalSteps}
+            elapsedSeconds={elapsedSeconds}
+            remainingSeconds={remainingSeconds}
             logs={logs}
             showLogs={showLogs}
             onToggleLogs={() => setShowLogs(!showLogs)}
@@ -452,7 +531,16 @@ function App() {
     }
   };
 
-  return <div className="App">{renderPage()}</div>;
+  return (
+    <div className="App">
+      {renderPage()}
+      <TelemetryConsentModal
+        isOpen={showTelemetryConsent}
+        onAccept={() => handleTelemetryConsent(true)}
+        onDecline={() => handleTelemetryConsent(false)}
+      />
+    </div>
+  );
 }
 
 export default App;
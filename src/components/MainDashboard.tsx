@@ -1,5 +1,5 @@
 import React, { useRef, useEffect, useState } from 'react';
-import { VideoFile, AppSettings, PromptPreset, VideoQuality, ImageEmbedFrequency } from '../types';
+import { VideoFile, AppSettings, PromptPreset, VideoQuality, ImageEmbedFrequency, DocumentLanguage } from '../types';
 import { 
   FaPlay, 
   FaCog, 
@@ -33,6 +33,8 @@ interface MainDashboardProps {
   progressMessage: string;
   progressStep: number;
   totalSteps: number;
+  elapsedSeconds: number;
+  remainingSeconds?: number;
   logs: string[];
   showLogs: boolean;
   onToggleLogs: () => void;
@@ -61,6 +63,8 @@ export default function MainDashboard({
   progressMessage,
   progressStep,
   totalSteps,
+  elapsedSeconds,
+  remainingSeconds,
   logs,
   showLogs,
   onToggleLogs,
@@ -97,8 +101,15 @@ export default function MainDashboard({
     return parseFloat((bytes / Math.pow(k, i)).toFixed(2)) + " " + sizes[i];
   };
 
+  const formatDuration = (seconds: number): string => {
+    const total = Math.max(0, Math.round(seconds));
+    const minutes = Math.floor(total / 60);
+    const secs = total % 60;
+    return minutes > 0 ? `${minutes}m ${secs}s` : `${secs}s`;
+  };
+
   const handleLanguageChange = async (e: React.ChangeEvent<HTMLSelectElement>) => {
-    const newSettings = { ...settings, language: e.target.value };
+    const newSettings = { ...settings, language: e.target.value as DocumentLanguage };
     onUpdateSettings(newSettings);
   };
 
@@ -163,6 +174,13 @@ export default function MainDashboard({
                   >
                     <option value="japanese">日本語</option>
                     <option value="english">English</option>
+                    <option value="chinese_simplified">简体中文</option>
+                    <option value="chinese_traditional">繁體中文</option>
+                    <option value="korean">한국어</option>
+                    <option value="spanish">Español</option>
+                    <option value="french">Français</option>
+                    <option value="german">Deutsch</option>
+                    <option value="portuguese">Português</option>
                   </select>
                 </div>
                 <div className="setting-group">
@@ -297,6 +315,12 @@ export default function MainDashboard({
                 {progressStep} / {totalSteps}
               </div>
             </div>
+            {isProcessing && (
+              <div className="progress-time">
+                Elapsed: {formatDuration(elapsedSeconds)}
+                {remainingSeconds !== undefined && ` | Remaining: ~${formatDuration(remainingSeconds)}`}
+              </div>
+            )}
             
             <div className="log-section">
               <div className="log-header">
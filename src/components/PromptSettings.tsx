@@ -71,10 +71,10 @@ export default function PromptSettings({
                 <FaPlus className="icon" /> 新規プリセット作成
               </button>
               <button onClick={onImportXML}>
-                <FaUpload className="icon" /> XMLファイルから読み込み
+                <FaUpload className="icon" /> XML/JSONファイルから読み込み
               </button>
               <button onClick={onExportXML}>
-                <FaDownload className="icon" /> XMLファイルにエクスポート
+                <FaDownload className="icon" /> XML/JSONファイルにエクスポート
               </button>
             </div>
           </div>
@@ -1,5 +1,5 @@
 import { AppSettings } from '../types';
-import { FaSave, FaTimes, FaKey, FaThermometerHalf, FaRobot, FaMicrochip, FaVideo, FaInfoCircle, FaFlask } from 'react-icons/fa';
+import { FaSave, FaTimes, FaKey, FaThermometerHalf, FaRobot, FaMicrochip, FaVideo, FaInfoCircle, FaFlask, FaChartBar } from 'react-icons/fa';
 
 interface SettingsProps {
   settings: AppSettings;
@@ -129,6 +129,39 @@ export default function Settings({ settings, onUpdateSettings, onClose, onSave,
             )}
           </div>
           
+          <div className="settings-section">
+            <h3 className="section-title">
+              <FaChartBar className="icon" /> 利用統計
+            </h3>
+
+            <div className="form-group">
+              <label className="checkbox-label">
+                <input
+                  type="checkbox"
+                  checked={settings.enable_telemetry || false}
+                  onChange={(e) => onUpdateSettings({ ...settings, enable_telemetry: e.target.checked })}
+                />
+                <span className="checkbox-text">匿名の利用統計を送信する</span>
+              </label>
+              <small style={{ color: '#666', fontSize: '12px', marginTop: '4px', display: 'block' }}>
+                モデル名のハッシュ、言語、ファイル数、動画の合計時間などのみを送信します。ファイルパス、APIキー、ドキュメントの内容は送信されません。
+              </small>
+            </div>
+
+            {settings.enable_telemetry && (
+              <div className="form-group">
+                <label htmlFor="telemetryEndpoint">送信先URL:</label>
+                <input
+                  type="text"
+                  id="telemetryEndpoint"
+                  value={settings.telemetry_endpoint || ''}
+                  onChange={(e) => onUpdateSettings({ ...settings, telemetry_endpoint: e.target.value })}
+                  placeholder="https://"
+                />
+              </div>
+            )}
+          </div>
+
           <div className="settings-section">
             <h3 className="section-title">
               <FaInfoCircle className="icon" /> アプリケーション情報
@@ -0,0 +1,38 @@
+import { FaChartBar, FaCheck, FaTimes } from 'react-icons/fa';
+
+interface TelemetryConsentModalProps {
+  isOpen: boolean;
+  onAccept: () => void;
+  onDecline: () => void;
+}
+
+export default function TelemetryConsentModal({
+  isOpen,
+  onAccept,
+  onDecline
+}: TelemetryConsentModalProps) {
+  if (!isOpen) return null;
+
+  return (
+    <div className="modal-overlay">
+      <div className="modal-content">
+        <h3><FaChartBar className="icon" /> 匿名の利用統計の送信</h3>
+        <p>
+          機能改善のため、ドキュメント生成が成功したときに匿名の利用統計を送信してもよろしいですか？
+        </p>
+        <p>
+          送信されるのはモデル名のハッシュ、言語、ファイル数、動画の合計時間、カスタムプロンプトの有無、アプリのバージョンのみです。
+          ファイルパス、APIキー、ドキュメントの内容は送信されません。この設定は後から変更できます。
+        </p>
+        <div className="modal-buttons">
+          <button onClick={onAccept}>
+            <FaCheck className="icon" /> 送信を許可する
+          </button>
+          <button onClick={onDecline}>
+            <FaTimes className="icon" /> 送信しない
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
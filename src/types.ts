@@ -2,17 +2,70 @@ export interface VideoFile {
   path: string;
   name: string;
   size: number;
+  subtitle_path?: string;
+  start_offset_seconds?: number;
+  end_offset_seconds?: number;
+  custom_prompt_override?: string;
+  sha256_first_mb?: string;
 }
 
 export type VideoQuality = "NoConversion" | "1080p" | "720p" | "480p";
 
+export type DocumentLanguage =
+  | "japanese"
+  | "english"
+  | "chinese_simplified"
+  | "chinese_traditional"
+  | "korean"
+  | "spanish"
+  | "french"
+  | "german"
+  | "portuguese";
+
 export type ImageEmbedFrequency = "minimal" | "moderate" | "detailed";
 
 export type FrameExtractionMethod = "standard" | "fast" | "multiple";
 
+export type FrameOutputFormat =
+  | "png"
+  | { jpeg: { quality: number } }
+  | { webp: { quality: number } };
+
+export type OutputEncoding = "utf8" | "utf8_bom" | "shift_jis";
+
+export type LineEnding = "platform" | "lf" | "crlf";
+
+export type DocumentFormatPreset =
+  | "user_manual"
+  | "technical_spec"
+  | "meeting_notes"
+  | "tutorial_guide"
+  | "lecture_notes"
+  | "custom";
+
+export type SplitMode =
+  | "duration"
+  | { silence_detect: { silence_threshold_db: number; min_silence_duration_secs: number } };
+
+export type GeminiApiVersion = "v1beta" | "v1";
+
+export type PresetFileFormat = "xml" | "json";
+
+export type DocumentWatermarkPosition = "top" | "bottom";
+
+export type WatermarkPosition = "top_left" | "top_right" | "bottom_left" | "bottom_right" | "center";
+
+export interface WatermarkSettings {
+  text: string;
+  position: WatermarkPosition;
+  font_size: number;
+  opacity: number;
+  font_path?: string;
+}
+
 export interface AppSettings {
   gemini_api_key: string;
-  language: string;
+  language: DocumentLanguage;
   temperature: number;
   custom_prompt?: string;
   gemini_model?: string;
@@ -23,6 +76,157 @@ export interface AppSettings {
   // 実験用機能
   enable_experimental_features?: boolean;
   frame_extraction_method?: FrameExtractionMethod;
+  post_generation_hook?: string;
+  max_upload_bytes?: number;
+  audio_track_index?: number;
+  structured_output?: boolean;
+  video_watermark?: WatermarkSettings;
+  translate_output_to?: string;
+  split_mode?: SplitMode;
+  thinking_budget?: number;
+  reference_documents?: string[];
+  output_encoding?: OutputEncoding;
+  stabilize_video?: boolean;
+  split_output_per_section?: boolean;
+  two_pass_encoding?: boolean;
+  enable_search_grounding?: boolean;
+  format_preset?: DocumentFormatPreset;
+  subtitle_burn_in?: string;
+  save_intermediate_files?: boolean;
+  concurrent_frame_extraction?: boolean;
+  max_concurrent_frame_extractions?: number;
+  document_watermark_text?: string;
+  document_watermark_position?: DocumentWatermarkPosition;
+  auto_open_document?: boolean;
+  use_ai_title?: boolean;
+  gemini_api_version?: GeminiApiVersion;
+  auto_generate_summary?: boolean;
+  frame_output_format?: FrameOutputFormat;
+  ffmpeg_extra_input_args?: string[];
+  ffmpeg_extra_output_args?: string[];
+  output_dir_per_job?: boolean;
+  enable_document_linting?: boolean;
+  video_stream_index?: number;
+  recent_prompts?: string[];
+  upload_chunk_size_mb?: number;
+  generate_flashcards?: boolean;
+  ffprobe_timeout_seconds?: number;
+  ffmpeg_timeout_seconds?: number;
+  slide_frame_rate?: number;
+  safety_settings?: GeminiSafetySetting[];
+  generate_glossary?: boolean;
+  max_document_size_chars?: number;
+  truncate_at_limit?: boolean;
+  embed_video_link?: boolean;
+  local_file_base_url?: string;
+  top_p?: number;
+  top_k?: number;
+  document_numbering_style?: NumberingStyle;
+  video_sampling_fps?: number;
+  ai_backend?: AiBackend;
+  claude_api_key?: string;
+  claude_model?: string;
+  integration_strategy?: IntegrationStrategy;
+  keep_uploaded_files?: boolean;
+  notification_sound?: NotificationSound;
+  chunk_long_prompts?: boolean;
+  max_prompt_tokens?: number;
+  image_grid_mode?: boolean;
+  image_grid_columns?: number;
+  auto_detect_document_type?: boolean;
+  max_heading_depth?: number;
+  strip_ai_preamble?: boolean;
+  preferred_code_languages?: string[];
+  progress_verbosity?: ProgressVerbosity;
+  add_utf8_bom?: boolean;
+  gemini_request_timeout_seconds?: number;
+  gemini_generation_timeout_seconds?: number;
+  enable_telemetry?: boolean;
+  telemetry_endpoint?: string;
+  telemetry_prompt_shown?: boolean;
+  generate_image_captions?: boolean;
+  html_report_template?: string;
+  keyframe_aligned_extraction?: boolean;
+  model_fallback_chain?: string[];
+  segment_overlap_seconds?: number;
+  prevent_duplicate_presets?: boolean;
+  upload_bandwidth_limit_kbps?: number;
+  chapter_split_min_duration_seconds?: number;
+  line_ending?: LineEnding;
+  progress_event_debounce_ms?: number;
+  enable_context_cache?: boolean;
+}
+
+export interface ModelFallback {
+  original_model: string;
+  fallback_model: string;
+  reason: string;
+}
+
+export interface CleanupReport {
+  files_found: string[];
+  total_bytes_found: number;
+  files_deleted: string[];
+  total_bytes_freed: number;
+}
+
+export interface GenerationSidecar {
+  generated_at: string;
+  model: string;
+  temperature: number;
+  language: string;
+  source_files: string[];
+  prompt_preset_id?: string;
+  prompt_preset_version?: number;
+  gemini_file_uris: string[];
+}
+
+export type NotificationSound = "none" | "system" | { custom: string };
+
+export type ProgressVerbosity = "minimal" | "normal" | "verbose";
+
+export type IntegrationStrategy = "sequential" | "hierarchical" | "summary_first";
+
+export type AiBackend = "gemini" | "claude";
+
+export type NumberingStyle =
+  | "automatic"
+  | "arabic"
+  | "alpha_lower"
+  | "alpha_upper"
+  | "roman_lower"
+  | "roman_upper"
+  | "none";
+
+export interface DocumentSizeWarning {
+  actual: number;
+  limit: number;
+}
+
+export interface GeminiSafetySetting {
+  category: string;
+  threshold: string;
+}
+
+export interface Flashcard {
+  front: string;
+  back: string;
+  tags: string[];
+}
+
+export interface LintIssue {
+  rule: string;
+  line: number;
+  message: string;
+  auto_fixed: boolean;
+}
+
+export interface VideoValidationResult {
+  file_name: string;
+  valid: boolean;
+  duration_seconds?: number;
+  error?: string;
+  warnings: string[];
 }
 
 export interface PromptPreset {
@@ -30,10 +234,83 @@ export interface PromptPreset {
   name: string;
   prompt: string;
   is_default?: boolean;
+  format_preset?: DocumentFormatPreset;
+  tags?: string[];
+  version?: number;
+}
+
+export interface DuplicatePresetWarning {
+  existing_id: string;
+  existing_name: string;
+  dropped_id: string;
+  dropped_name: string;
+}
+
+export interface PromptPresetRevision {
+  preset_id: string;
+  version: number;
+  prompt: string;
+  prompt_sha256: string;
+  saved_at: string;
 }
 
 export interface ProgressUpdate {
   message: string;
   step: number;
   total_steps: number;
+  elapsed_seconds: number;
+  estimated_remaining_seconds?: number;
+}
+
+export interface ConfigPaths {
+  settings_path: string;
+  presets_path: string;
+}
+
+export interface DiskSpaceCheck {
+  required_bytes: number;
+  available_bytes: number;
+  sufficient: boolean;
+  recommendation: string;
+}
+
+export interface HookOutput {
+  command: string;
+  stdout: string;
+  stderr: string;
+}
+
+export interface HookFailure {
+  command: string;
+  exit_code?: number;
+  message: string;
+}
+
+export interface StreamInfo {
+  index: number;
+  type: string;
+  codec: string;
+  label?: string;
+}
+
+export interface AudioTrackInfo {
+  index: number;
+  language?: string;
+  codec: string;
+  channels: number;
+  sample_rate: number;
+  bit_rate_kbps?: number;
+  title?: string;
+}
+
+export interface AudioFile {
+  path: string;
+  name: string;
+  size: number;
+  duration?: number;
+}
+
+export interface AudioChapter {
+  path: string;
+  title: string;
 }
@@ -15,6 +15,12 @@ export const generateFilename = (files: { name: string }[]): string => {
   return `${nameWithoutExt}.md`;
 };
 
+export const titleToFilename = (title: string): string => {
+  // Windows/macOSでファイル名に使えない文字を置換する
+  const sanitized = title.replace(/[\\/:*?"<>|\x00-\x1f]/g, "_").trim().replace(/\.+$/, "");
+  return `${sanitized || "document"}.md`;
+};
+
 export const getDirectoryFromPath = (filePath: string): string => {
   // Handle both Windows (\) and Unix (/) path separators
   const lastBackslash = filePath.lastIndexOf('\\');